@@ -0,0 +1,118 @@
+#![no_std]
+#![no_main]
+
+use core::{fmt::Write, panic::PanicInfo};
+
+use esp32_hal::{
+    analog::{
+        config::Attenuation,
+        continuous::{ContinuousADC, ContinuousConfig},
+        SensExt,
+    },
+    clock_control::{ClockControl, XTAL_FREQUENCY_AUTO},
+    dma::CircularBuffer,
+    dport::Split,
+    dprintln,
+    prelude::*,
+    serial::{config::Config, Pins, Serial},
+    target,
+    timer::Timer,
+};
+
+#[repr(align(4))]
+struct Aligned([u8; 4000]);
+
+static mut BUFFER: Aligned = Aligned([0; 4000]);
+
+#[entry]
+fn main() -> ! {
+    let dp = target::Peripherals::take().expect("Failed to obtain Peripherals");
+
+    let (mut dport, dport_clock_control) = dp.DPORT.split();
+
+    let clkcntrl = ClockControl::new(
+        dp.RTCCNTL,
+        dp.APB_CTRL,
+        dport_clock_control,
+        XTAL_FREQUENCY_AUTO,
+    )
+    .unwrap();
+
+    let (clkcntrl_config, mut watchdog) = clkcntrl.freeze().unwrap();
+    watchdog.disable();
+
+    let (_, _, _, mut watchdog0) = Timer::new(dp.TIMG0, clkcntrl_config);
+    let (_, _, _, mut watchdog1) = Timer::new(dp.TIMG1, clkcntrl_config);
+    watchdog0.disable();
+    watchdog1.disable();
+
+    let pins = dp.GPIO.split();
+
+    let mut serial: Serial<_, _, _> = Serial::new(
+        dp.UART0,
+        Pins {
+            tx: pins.gpio1,
+            rx: pins.gpio3,
+            cts: None,
+            rts: None,
+        },
+        Config::default().baudrate(115200.Hz()),
+        clkcntrl_config,
+        &mut dport,
+    )
+    .unwrap();
+
+    let pin36 = pins.gpio36.into_analog();
+    let pin39 = pins.gpio39.into_analog();
+
+    let mut config = ContinuousConfig::new(100.kHz().into());
+    config
+        .add_pin(&pin36, Attenuation::Attenuation11dB)
+        .unwrap()
+        .add_pin(&pin39, Attenuation::Attenuation11dB)
+        .unwrap();
+
+    let analog = dp.SENS.split();
+    let buffer = CircularBuffer::new(unsafe { &mut BUFFER.0 }).unwrap();
+    let mut adc = ContinuousADC::new(
+        analog.adc1,
+        dp.I2S,
+        config,
+        buffer,
+        clkcntrl_config,
+        &mut dport,
+    )
+    .unwrap();
+
+    writeln!(serial, "\n\nESP32 Started\n\n").unwrap();
+
+    adc.start();
+
+    loop {
+        if let Some(samples) = adc.samples() {
+            // average of each channel over half of the buffer
+            let mut sums = [0u32; 8];
+            let mut counts = [0u32; 8];
+            for (channel, value) in samples {
+                if let Some(sum) = sums.get_mut(channel as usize) {
+                    *sum += value as u32;
+                    counts[channel as usize] += 1;
+                }
+            }
+
+            writeln!(
+                serial,
+                "GPIO36: {:4}, GPIO39: {:4}",
+                sums[0] / counts[0].max(1),
+                sums[3] / counts[3].max(1)
+            )
+            .unwrap();
+        }
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    dprintln!("\n\n*** {:?}", info);
+    loop {}
+}
@@ -0,0 +1,89 @@
+#![no_std]
+#![no_main]
+
+use core::{fmt::Write, panic::PanicInfo};
+
+use esp32_hal::{
+    clock_control::{sleep, ClockControl, XTAL_FREQUENCY_AUTO},
+    dport::Split,
+    dprintln,
+    i2c::{self, I2c},
+    prelude::*,
+    serial::{config::Config, Pins, Serial},
+    target,
+    timer::Timer,
+};
+
+/// Address of the MPU-6050 motion sensor
+const MPU6050_ADDRESS: u8 = 0x68;
+/// Register containing the device id
+const MPU6050_WHO_AM_I: u8 = 0x75;
+
+#[entry]
+fn main() -> ! {
+    let dp = target::Peripherals::take().expect("Failed to obtain Peripherals");
+
+    let (mut dport, dport_clock_control) = dp.DPORT.split();
+
+    let clkcntrl = ClockControl::new(
+        dp.RTCCNTL,
+        dp.APB_CTRL,
+        dport_clock_control,
+        XTAL_FREQUENCY_AUTO,
+    )
+    .unwrap();
+
+    let (clkcntrl_config, mut watchdog) = clkcntrl.freeze().unwrap();
+    watchdog.disable();
+
+    let (_, _, _, mut watchdog0) = Timer::new(dp.TIMG0, clkcntrl_config);
+    let (_, _, _, mut watchdog1) = Timer::new(dp.TIMG1, clkcntrl_config);
+    watchdog0.disable();
+    watchdog1.disable();
+
+    let pins = dp.GPIO.split();
+
+    let mut serial: Serial<_, _, _> = Serial::new(
+        dp.UART0,
+        Pins {
+            tx: pins.gpio1,
+            rx: pins.gpio3,
+            cts: None,
+            rts: None,
+        },
+        Config::default().baudrate(115200.Hz()),
+        clkcntrl_config,
+        &mut dport,
+    )
+    .unwrap();
+
+    let mut i2c0 = I2c::new(
+        dp.I2C0,
+        i2c::Pins {
+            sda: pins.gpio21,
+            scl: pins.gpio22,
+        },
+        i2c::config::Config::default().frequency(400.kHz().into()),
+        clkcntrl_config,
+        &mut dport,
+    )
+    .unwrap();
+
+    writeln!(serial, "\n\nESP32 Started\n\n").unwrap();
+
+    loop {
+        let mut id = [0u8; 1];
+        match i2c0.write_read(MPU6050_ADDRESS, &[MPU6050_WHO_AM_I], &mut id) {
+            Ok(()) => writeln!(serial, "Device id: {:#x}", id[0]).unwrap(),
+            Err(err) => writeln!(serial, "I2C error: {:?}", err).unwrap(),
+        }
+
+        sleep(1.s());
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    dprintln!("\n\n*** {:?}", info);
+    loop {}
+}
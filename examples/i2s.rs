@@ -0,0 +1,118 @@
+#![no_std]
+#![no_main]
+
+use core::{fmt::Write, panic::PanicInfo};
+
+use esp32_hal::{
+    clock_control::{ClockControl, XTAL_FREQUENCY_AUTO},
+    dma::CircularBuffer,
+    dport::Split,
+    dprintln,
+    i2s::{config::Config as I2sConfig, I2s, Pins as I2sPins},
+    prelude::*,
+    serial::{config::Config, Pins, Serial},
+    target,
+    timer::Timer,
+};
+
+/// Number of samples per period of the generated triangle wave (440Hz at 44.1kHz)
+const PERIOD: u32 = 100;
+
+#[repr(align(4))]
+struct Aligned([u8; 4096]);
+
+static mut BUFFER: Aligned = Aligned([0; 4096]);
+
+#[entry]
+fn main() -> ! {
+    let dp = target::Peripherals::take().expect("Failed to obtain Peripherals");
+
+    let (mut dport, dport_clock_control) = dp.DPORT.split();
+
+    let clkcntrl = ClockControl::new(
+        dp.RTCCNTL,
+        dp.APB_CTRL,
+        dport_clock_control,
+        XTAL_FREQUENCY_AUTO,
+    )
+    .unwrap();
+
+    let (clkcntrl_config, mut watchdog) = clkcntrl.freeze().unwrap();
+    watchdog.disable();
+
+    let (_, _, _, mut watchdog0) = Timer::new(dp.TIMG0, clkcntrl_config);
+    let (_, _, _, mut watchdog1) = Timer::new(dp.TIMG1, clkcntrl_config);
+    watchdog0.disable();
+    watchdog1.disable();
+
+    let pins = dp.GPIO.split();
+
+    let mut serial: Serial<_, _, _> = Serial::new(
+        dp.UART0,
+        Pins {
+            tx: pins.gpio1,
+            rx: pins.gpio3,
+            cts: None,
+            rts: None,
+        },
+        Config::default().baudrate(115200.Hz()),
+        clkcntrl_config,
+        &mut dport,
+    )
+    .unwrap();
+
+    // 16 bit stereo at 44.1kHz to an I2S amplifier
+    let i2s = I2s::new(
+        dp.I2S,
+        I2sPins {
+            bck: pins.gpio26,
+            ws: pins.gpio25,
+            data_out: Some(pins.gpio22),
+            data_in: None,
+        },
+        I2sConfig::default(),
+        clkcntrl_config,
+        &mut dport,
+    )
+    .unwrap();
+
+    writeln!(
+        serial,
+        "\n\nESP32 Started\n\nSample rate: {}",
+        i2s.sample_rate()
+    )
+    .unwrap();
+
+    let mut phase = 0;
+    let mut fill = |data: &mut [u8]| {
+        for frame in data.chunks_mut(4) {
+            let level = if phase < PERIOD / 2 {
+                phase
+            } else {
+                PERIOD - phase
+            };
+            let sample = ((level * 2 * 8192 / PERIOD) as i16 - 4096).to_le_bytes();
+
+            frame[0..2].copy_from_slice(&sample);
+            frame[2..4].copy_from_slice(&sample);
+
+            phase = (phase + 1) % PERIOD;
+        }
+    };
+
+    let mut buffer = CircularBuffer::new(unsafe { &mut BUFFER.0 }).unwrap();
+    fill(buffer.half_mut(0));
+    fill(buffer.half_mut(1));
+
+    let mut stream = i2s.transmit(buffer);
+
+    loop {
+        stream.process(|_, data| fill(data));
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    dprintln!("\n\n*** {:?}", info);
+    loop {}
+}
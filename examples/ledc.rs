@@ -0,0 +1,105 @@
+#![no_std]
+#![no_main]
+
+use core::{fmt::Write, panic::PanicInfo};
+
+use esp32_hal::{
+    clock_control::{ClockControl, XTAL_FREQUENCY_AUTO},
+    dport::Split,
+    dprintln,
+    ledc::{config::TimerConfig, Ledc},
+    prelude::*,
+    serial::{config::Config, Pins, Serial},
+    target,
+    timer::Timer,
+};
+
+#[entry]
+fn main() -> ! {
+    let dp = target::Peripherals::take().expect("Failed to obtain Peripherals");
+
+    let (mut dport, dport_clock_control) = dp.DPORT.split();
+
+    let clkcntrl = ClockControl::new(
+        dp.RTCCNTL,
+        dp.APB_CTRL,
+        dport_clock_control,
+        XTAL_FREQUENCY_AUTO,
+    )
+    .unwrap();
+
+    let (clkcntrl_config, mut watchdog) = clkcntrl.freeze().unwrap();
+    watchdog.disable();
+
+    let (_, _, _, mut watchdog0) = Timer::new(dp.TIMG0, clkcntrl_config);
+    let (_, _, _, mut watchdog1) = Timer::new(dp.TIMG1, clkcntrl_config);
+    watchdog0.disable();
+    watchdog1.disable();
+
+    let pins = dp.GPIO.split();
+
+    let mut serial: Serial<_, _, _> = Serial::new(
+        dp.UART0,
+        Pins {
+            tx: pins.gpio1,
+            rx: pins.gpio3,
+            cts: None,
+            rts: None,
+        },
+        Config::default().baudrate(115200.Hz()),
+        clkcntrl_config,
+        &mut dport,
+    )
+    .unwrap();
+
+    let mut ledc = Ledc::new(dp.LEDC, clkcntrl_config, &mut dport);
+
+    // 1kHz with 9 bits resolution can be derived from the reference clock
+    ledc.lstimer0
+        .configure(
+            TimerConfig::default()
+                .frequency(1.kHz().into())
+                .resolution(9),
+        )
+        .unwrap();
+
+    // 20kHz with 12 bits resolution needs the APB clock
+    ledc.hstimer0
+        .configure(
+            TimerConfig::default()
+                .frequency(20.kHz().into())
+                .resolution(12),
+        )
+        .unwrap();
+
+    writeln!(
+        serial,
+        "\n\nESP32 Started\n\nlstimer0: {} APB clock: {}\nhstimer0: {} APB clock: {}",
+        ledc.lstimer0.frequency(),
+        ledc.lstimer0.is_clock_apb(),
+        ledc.hstimer0.frequency(),
+        ledc.hstimer0.is_clock_apb()
+    )
+    .unwrap();
+
+    let mut led = ledc.lschannel0.connect(pins.gpio2, &ledc.lstimer0);
+    let mut pwm = ledc.hschannel0.connect(pins.gpio4, &ledc.hstimer0);
+
+    pwm.set_duty(pwm.get_max_duty() / 3);
+    pwm.enable();
+    led.enable();
+
+    // breathing led via hardware fades
+    loop {
+        led.start_fade(led.get_max_duty(), 256, 4).unwrap();
+        led.wait_fade();
+        led.start_fade(0, 256, 4).unwrap();
+        led.wait_fade();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    dprintln!("\n\n*** {:?}", info);
+    loop {}
+}
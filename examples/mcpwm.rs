@@ -0,0 +1,126 @@
+#![no_std]
+#![no_main]
+
+use core::{fmt::Write, panic::PanicInfo};
+
+use esp32_hal::{
+    clock_control::{sleep, ClockControl, XTAL_FREQUENCY_AUTO},
+    dport::Split,
+    dprintln,
+    mcpwm::{
+        config::{
+            Action, CountMode, DeadTimeConfig, DeadTimeMode, FaultHandlerConfig, FaultMode,
+            TimerConfig,
+        },
+        Generator, Mcpwm,
+    },
+    prelude::*,
+    serial::{config::Config, Pins, Serial},
+    target,
+    timer::Timer,
+};
+
+#[entry]
+fn main() -> ! {
+    let dp = target::Peripherals::take().expect("Failed to obtain Peripherals");
+
+    let (mut dport, dport_clock_control) = dp.DPORT.split();
+
+    let clkcntrl = ClockControl::new(
+        dp.RTCCNTL,
+        dp.APB_CTRL,
+        dport_clock_control,
+        XTAL_FREQUENCY_AUTO,
+    )
+    .unwrap();
+
+    let (clkcntrl_config, mut watchdog) = clkcntrl.freeze().unwrap();
+    watchdog.disable();
+
+    let (_, _, _, mut watchdog0) = Timer::new(dp.TIMG0, clkcntrl_config);
+    let (_, _, _, mut watchdog1) = Timer::new(dp.TIMG1, clkcntrl_config);
+    watchdog0.disable();
+    watchdog1.disable();
+
+    let pins = dp.GPIO.split();
+
+    let mut serial: Serial<_, _, _> = Serial::new(
+        dp.UART0,
+        Pins {
+            tx: pins.gpio1,
+            rx: pins.gpio3,
+            cts: None,
+            rts: None,
+        },
+        Config::default().baudrate(115200.Hz()),
+        clkcntrl_config,
+        &mut dport,
+    )
+    .unwrap();
+
+    let mut mcpwm = Mcpwm::new(dp.PWM0, clkcntrl_config, &mut dport);
+
+    // symmetric 20kHz PWM
+    mcpwm
+        .timer0
+        .configure(
+            TimerConfig::default()
+                .frequency(20.kHz().into())
+                .mode(CountMode::UpDown),
+        )
+        .unwrap();
+
+    // over current signal of the driver (active low)
+    let fault = mcpwm.fault0.enable(pins.gpio4, false);
+
+    // high and low side of a half bridge with 1us dead time
+    let mut bridge = mcpwm
+        .operator0
+        .connect(&mcpwm.timer0, pins.gpio25, pins.gpio26);
+    bridge
+        .set_dead_time(DeadTimeConfig {
+            mode: DeadTimeMode::ActiveHighComplementary,
+            rising_edge_delay: 160,
+            falling_edge_delay: 160,
+        })
+        .configure_fault_handler(FaultHandlerConfig {
+            inputs: [Some(FaultMode::OneShot), None, None],
+            action_a: Action::Low,
+            action_b: Action::Low,
+        });
+
+    mcpwm.timer0.start();
+
+    writeln!(
+        serial,
+        "\n\nESP32 Started\n\nPWM frequency: {}",
+        mcpwm.timer0.frequency()
+    )
+    .unwrap();
+
+    let max_duty = bridge.get_max_duty();
+    let mut duty = 0;
+
+    loop {
+        if bridge.is_tripped(FaultMode::OneShot) {
+            writeln!(serial, "Fault, outputs switched off").unwrap();
+
+            while fault.is_active() {}
+            sleep(1.s());
+
+            bridge.clear_one_shot_trip();
+            duty = 0;
+        }
+
+        bridge.set_duty(Generator::A, duty);
+        duty = (duty + max_duty / 100) % max_duty;
+
+        sleep(20.ms());
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    dprintln!("\n\n*** {:?}", info);
+    loop {}
+}
@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+
+use core::{fmt::Write, panic::PanicInfo};
+
+use esp32_hal::{
+    clock_control::{sleep, ClockControl, XTAL_FREQUENCY_AUTO},
+    dport::Split,
+    dprintln,
+    prelude::*,
+    rmt::{
+        config::ChannelConfig,
+        item::{ByteEncoder, Item, Pulse},
+        Rmt,
+    },
+    serial::{config::Config, Pins, Serial},
+    target,
+    timer::Timer,
+};
+
+#[entry]
+fn main() -> ! {
+    let dp = target::Peripherals::take().expect("Failed to obtain Peripherals");
+
+    let (mut dport, dport_clock_control) = dp.DPORT.split();
+
+    let clkcntrl = ClockControl::new(
+        dp.RTCCNTL,
+        dp.APB_CTRL,
+        dport_clock_control,
+        XTAL_FREQUENCY_AUTO,
+    )
+    .unwrap();
+
+    let (clkcntrl_config, mut watchdog) = clkcntrl.freeze().unwrap();
+    watchdog.disable();
+
+    let (_, _, _, mut watchdog0) = Timer::new(dp.TIMG0, clkcntrl_config);
+    let (_, _, _, mut watchdog1) = Timer::new(dp.TIMG1, clkcntrl_config);
+    watchdog0.disable();
+    watchdog1.disable();
+
+    let pins = dp.GPIO.split();
+
+    let mut serial: Serial<_, _, _> = Serial::new(
+        dp.UART0,
+        Pins {
+            tx: pins.gpio1,
+            rx: pins.gpio3,
+            cts: None,
+            rts: None,
+        },
+        Config::default().baudrate(115200.Hz()),
+        clkcntrl_config,
+        &mut dport,
+    )
+    .unwrap();
+
+    let rmt = Rmt::new(dp.RMT, clkcntrl_config, &mut dport);
+
+    // 20MHz ticks (50ns) from the 80MHz APB clock
+    let mut led = rmt
+        .channel0
+        .into_transmitter(pins.gpio18, ChannelConfig::default().clock_divider(4))
+        .unwrap();
+
+    writeln!(
+        serial,
+        "\n\nESP32 Started\n\nRMT tick: {}",
+        led.tick_frequency()
+    )
+    .unwrap();
+
+    // WS2812 timing: 0.4us high + 0.85us low for a 0, 0.8us high + 0.45us low for a 1
+    let encoder = ByteEncoder {
+        zero: Item::new(Pulse::high(8), Pulse::low(17)),
+        one: Item::new(Pulse::high(16), Pulse::low(9)),
+    };
+
+    let mut hue: u8 = 0;
+    loop {
+        // green, red, blue
+        let color = [hue, 255 - hue, 0];
+
+        let mut items = [Item::END; 24];
+        for (item, bit) in items.iter_mut().zip(encoder.encode(&color)) {
+            *item = bit;
+        }
+
+        led.transmit(&items).unwrap();
+        led.wait();
+
+        hue = hue.wrapping_add(1);
+        sleep(10.ms());
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    dprintln!("\n\n*** {:?}", info);
+    loop {}
+}
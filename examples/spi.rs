@@ -0,0 +1,87 @@
+#![no_std]
+#![no_main]
+
+use core::{fmt::Write, panic::PanicInfo};
+
+use esp32_hal::{
+    clock_control::{sleep, ClockControl, XTAL_FREQUENCY_AUTO},
+    dport::Split,
+    dprintln,
+    prelude::*,
+    serial::{config::Config, Pins, Serial},
+    spi::{self, Spi},
+    target,
+    timer::Timer,
+};
+
+#[entry]
+fn main() -> ! {
+    let dp = target::Peripherals::take().expect("Failed to obtain Peripherals");
+
+    let (mut dport, dport_clock_control) = dp.DPORT.split();
+
+    let clkcntrl = ClockControl::new(
+        dp.RTCCNTL,
+        dp.APB_CTRL,
+        dport_clock_control,
+        XTAL_FREQUENCY_AUTO,
+    )
+    .unwrap();
+
+    let (clkcntrl_config, mut watchdog) = clkcntrl.freeze().unwrap();
+    watchdog.disable();
+
+    let (_, _, _, mut watchdog0) = Timer::new(dp.TIMG0, clkcntrl_config);
+    let (_, _, _, mut watchdog1) = Timer::new(dp.TIMG1, clkcntrl_config);
+    watchdog0.disable();
+    watchdog1.disable();
+
+    let pins = dp.GPIO.split();
+
+    let mut serial: Serial<_, _, _> = Serial::new(
+        dp.UART0,
+        Pins {
+            tx: pins.gpio1,
+            rx: pins.gpio3,
+            cts: None,
+            rts: None,
+        },
+        Config::default().baudrate(115200.Hz()),
+        clkcntrl_config,
+        &mut dport,
+    )
+    .unwrap();
+
+    // connect MOSI (gpio13) to MISO (gpio12) to receive the transmitted data
+    let mut spi: Spi<_, _, _, _, _> = Spi::new(
+        dp.SPI2,
+        spi::Pins {
+            sclk: pins.gpio14,
+            mosi: pins.gpio13,
+            miso: Some(pins.gpio12),
+            cs: Some(pins.gpio15),
+        },
+        spi::config::Config::default()
+            .baudrate(1.MHz().into())
+            .data_mode(spi::config::MODE_0),
+        clkcntrl_config,
+        &mut dport,
+    )
+    .unwrap();
+
+    writeln!(serial, "\n\nESP32 Started\n\n").unwrap();
+
+    loop {
+        let mut data = [0xde, 0xad, 0xbe, 0xef];
+        spi.transfer(&mut data).unwrap();
+        writeln!(serial, "Received: {:x?}", data).unwrap();
+
+        sleep(1.s());
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    dprintln!("\n\n*** {:?}", info);
+    loop {}
+}
@@ -0,0 +1,78 @@
+//! Host tests of the hardware independent parts of esp32-hal
+//!
+//! esp32-hal only builds for the xtensa target, so its unit tests can't be run with
+//! `cargo test` in the crate itself. This crate includes the modules without register accesses
+//! together with stubs of the items of esp32-hal they use:
+//!
+//! ```text
+//! cd host-tests
+//! cargo test
+//! ```
+
+// lints of newer toolchains than the one of esp32-hal
+#![allow(clippy::new_without_default)]
+
+#[path = "../../src/analog/calibration.rs"]
+pub mod calibration;
+#[path = "../../src/analog/config.rs"]
+pub mod config;
+#[path = "../../src/analog/filter.rs"]
+pub mod filter;
+#[path = "../../src/rmt/item.rs"]
+pub mod item;
+#[path = "../../src/persistent.rs"]
+pub mod persistent;
+
+/// Stubs of the ADC driver
+pub mod analog {
+    pub use crate::config;
+
+    pub struct ADC1;
+    pub struct ADC2;
+
+    pub mod adc {
+        use core::marker::PhantomData;
+
+        use crate::config::{Attenuation, Resolution};
+
+        #[derive(Debug)]
+        pub enum Error {
+            ChannelNotEnabled,
+        }
+
+        pub struct ADC<ADC> {
+            adc: PhantomData<ADC>,
+            resolution: Resolution,
+            attenuations: [Option<Attenuation>; 10],
+        }
+
+        impl<ADCX> ADC<ADCX> {
+            pub fn resolution(&self) -> Resolution {
+                self.resolution
+            }
+
+            pub fn attenuation(&self, channel: u8) -> Option<Attenuation> {
+                self.attenuations.get(channel as usize).copied().flatten()
+            }
+        }
+    }
+}
+
+/// Stub of the eFuse without calibration data
+pub mod efuse {
+    pub struct Efuse;
+
+    impl Efuse {
+        pub fn get_adc_vref() -> Option<i32> {
+            None
+        }
+
+        pub fn get_adc1_two_point_cal() -> Option<(i32, i32)> {
+            None
+        }
+
+        pub fn get_adc2_two_point_cal() -> Option<(i32, i32)> {
+            None
+        }
+    }
+}
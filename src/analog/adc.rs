@@ -18,6 +18,11 @@
 //! | 8       |                      | GPIO25        |
 //! | 9       |                      | GPIO26        |
 //!
+//! ADC2 is shared with the RF subsystem, which uses it for the power detection of Wi-Fi.
+//! The RF driver has priority: while it holds ADC2 via [adc2_rf_acquire], conversions
+//! return [Error::Busy], and conversions in progress when it acquires ADC2 return
+//! [Error::Invalidated].
+//!
 
 use core::marker::PhantomData;
 use embedded_hal::adc::{Channel, OneShot};
@@ -25,12 +30,88 @@ use embedded_hal::adc::{Channel, OneShot};
 use crate::analog::config;
 use crate::analog::{ADC1, ADC2};
 use crate::gpio::*;
-use crate::target::{RTCIO, SENS};
+use crate::prelude::*;
+use crate::target::{APB_CTRL, RTCIO, SENS};
+
+/// ADC error
+#[derive(Debug)]
+pub enum Error {
+    /// The channel is not enabled
+    ChannelNotEnabled,
+    /// A conversion is in progress
+    ConversionInProgress,
+    /// ADC2 is used by the RF subsystem
+    Busy,
+    /// The conversion was invalidated, because the RF subsystem took over ADC2
+    Invalidated,
+    /// One-shot conversions need the RTC controller
+    NotRtcController,
+}
+
+/// Controller driving the ADC
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Controller {
+    /// RTC controller, used for one-shot conversions
+    Rtc,
+    /// Digital controller, used for continuous conversions
+    Digital,
+}
+
+/// Usage of ADC2 by the RF subsystem
+struct Adc2Arbiter {
+    rf_in_use: bool,
+    /// Incremented each time the RF subsystem acquires ADC2
+    generation: u32,
+}
+
+static ADC2_ARBITER: CriticalSectionSpinLockMutex<Adc2Arbiter> =
+    CriticalSectionSpinLockMutex::new(Adc2Arbiter {
+        rf_in_use: false,
+        generation: 0,
+    });
+
+/// Acquire ADC2 for the RF subsystem
+///
+/// This is to be called by the Wi-Fi driver before it uses ADC2 for power detection.
+/// Conversions in progress are invalidated and new conversions fail with [Error::Busy] until
+/// [adc2_rf_release] is called.
+pub fn adc2_rf_acquire() {
+    (&ADC2_ARBITER).lock(|arbiter| {
+        arbiter.rf_in_use = true;
+        arbiter.generation = arbiter.generation.wrapping_add(1);
+    });
+}
+
+/// Release ADC2 from the RF subsystem
+pub fn adc2_rf_release() {
+    (&ADC2_ARBITER).lock(|arbiter| arbiter.rf_in_use = false);
+}
+
+/// Returns true if ADC2 is acquired by the RF subsystem
+pub fn adc2_rf_in_use() -> bool {
+    (&ADC2_ARBITER).lock(|arbiter| arbiter.rf_in_use)
+}
 
 pub struct ADC<ADC> {
     adc: PhantomData<ADC>,
+    resolution: config::Resolution,
     attenuations: [Option<config::Attenuation>; 10],
     active_channel: Option<u8>,
+    controller: Controller,
+    /// Generation of the arbiter at the start of the conversion
+    generation: u32,
+}
+
+impl<ADCX> ADC<ADCX> {
+    /// Returns the configured resolution
+    pub fn resolution(&self) -> config::Resolution {
+        self.resolution
+    }
+
+    /// Returns the attenuation of the channel, if the channel is enabled
+    pub fn attenuation(&self, channel: u8) -> Option<config::Attenuation> {
+        self.attenuations.get(channel as usize).copied().flatten()
+    }
 }
 
 macro_rules! impl_adc_setup {
@@ -109,7 +190,7 @@ macro_rules! impl_adc_setup {
 }
 
 impl ADC<ADC1> {
-    pub fn adc1(_adc_instance: ADC1, config: config::Adc1Config) -> Result<Self, ()> {
+    pub fn adc1(_adc_instance: ADC1, config: config::Adc1Config) -> Result<Self, Error> {
         impl_adc_setup!(
             config,
             sar1_bit_width,
@@ -134,8 +215,11 @@ impl ADC<ADC1> {
 
         let adc = ADC {
             adc: PhantomData,
+            resolution: config.resolution,
             attenuations: config.attenuations,
             active_channel: None,
+            controller: Controller::Rtc,
+            generation: 0,
         };
 
         Ok(adc)
@@ -143,7 +227,11 @@ impl ADC<ADC1> {
 }
 
 impl ADC<ADC2> {
-    pub fn adc2(_adc_instance: ADC2, config: config::Adc2Config) -> Result<Self, ()> {
+    pub fn adc2(_adc_instance: ADC2, config: config::Adc2Config) -> Result<Self, Error> {
+        if adc2_rf_in_use() {
+            return Err(Error::Busy);
+        }
+
         impl_adc_setup!(
             config,
             sar2_bit_width,
@@ -157,16 +245,185 @@ impl ADC<ADC2> {
             sar2_en_pad_force
         );
 
+        /* Take ADC2 from the power detection of the RF subsystem */
+        let sensors = unsafe { &*SENS::ptr() };
+        sensors
+            .sar_read_ctrl2
+            .modify(|_, w| w.sar2_pwdet_force().clear_bit());
+
         let adc = ADC {
             adc: PhantomData,
+            resolution: config.resolution,
             attenuations: config.attenuations,
             active_channel: None,
+            controller: Controller::Rtc,
+            generation: 0,
         };
 
         Ok(adc)
     }
 }
 
+impl ADC<ADC1> {
+    /// ADC1 is not shared
+    fn start_arbitration(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// ADC1 is not shared
+    fn finish_arbitration(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ADC<ADC2> {
+    /// Returns the controller driving ADC2
+    pub fn controller(&self) -> Controller {
+        self.controller
+    }
+
+    /// Select the controller driving ADC2
+    ///
+    /// One-shot conversions are only possible with the RTC controller.
+    pub fn set_controller(&mut self, controller: Controller) -> Result<&mut Self, Error> {
+        if self.active_channel.is_some() {
+            return Err(Error::ConversionInProgress);
+        }
+
+        let sensors = unsafe { &*SENS::ptr() };
+        let apb_ctrl = unsafe { &*APB_CTRL::ptr() };
+
+        match controller {
+            Controller::Rtc => {
+                sensors.sar_read_ctrl2.modify(|_, w| {
+                    w.sar2_dig_force()
+                        .clear_bit()
+                        .sar2_pwdet_force()
+                        .clear_bit()
+                });
+                sensors.sar_meas_start2.modify(|_, w| {
+                    w.meas2_start_force()
+                        .set_bit()
+                        .sar2_en_pad_force()
+                        .set_bit()
+                });
+            }
+            Controller::Digital => {
+                sensors
+                    .sar_read_ctrl2
+                    .modify(|_, w| w.sar2_dig_force().set_bit().sar2_pwdet_force().clear_bit());
+                // ADC2 is driven by the digital controller instead of the power detection
+                apb_ctrl
+                    .apb_saradc_ctrl
+                    .modify(|_, w| w.saradc_sar2_mux().set_bit());
+            }
+        }
+
+        self.controller = controller;
+        Ok(self)
+    }
+
+    /// Check that ADC2 can be used for a conversion
+    fn start_arbitration(&mut self) -> Result<(), Error> {
+        if self.controller != Controller::Rtc {
+            return Err(Error::NotRtcController);
+        }
+
+        let generation = (&ADC2_ARBITER).lock(|arbiter| {
+            if arbiter.rf_in_use {
+                Err(Error::Busy)
+            } else {
+                Ok(arbiter.generation)
+            }
+        })?;
+
+        self.generation = generation;
+        Ok(())
+    }
+
+    /// Check that ADC2 was not taken over during the conversion
+    fn finish_arbitration(&self) -> Result<(), Error> {
+        let sensors = unsafe { &*SENS::ptr() };
+
+        let taken_over = (&ADC2_ARBITER)
+            .lock(|arbiter| arbiter.rf_in_use || arbiter.generation != self.generation);
+        if taken_over
+            || sensors
+                .sar_read_ctrl2
+                .read()
+                .sar2_pwdet_force()
+                .bit_is_set()
+        {
+            return Err(Error::Invalidated);
+        }
+
+        Ok(())
+    }
+}
+
+macro_rules! impl_adc_runtime_config {
+    ($adc:ident ($bit_width:ident, $read_reg:ident, $sample_bit:ident, $atten_reg:ident,
+        $atten_field:ident)) => {
+        impl ADC<$adc> {
+            /// Change the resolution of the following conversions
+            pub fn set_resolution(
+                &mut self,
+                resolution: config::Resolution,
+            ) -> Result<&mut Self, Error> {
+                if self.active_channel.is_some() {
+                    return Err(Error::ConversionInProgress);
+                }
+
+                let sensors = unsafe { &*SENS::ptr() };
+
+                sensors
+                    .sar_start_force
+                    .modify(|_, w| unsafe { w.$bit_width().bits(resolution as u8) });
+                sensors
+                    .$read_reg
+                    .modify(|_, w| unsafe { w.$sample_bit().bits(resolution as u8) });
+
+                self.resolution = resolution;
+                Ok(self)
+            }
+
+            /// Change the attenuation of the pin
+            ///
+            /// The pin is enabled if it was not enabled before.
+            pub fn set_attenuation<PIN: Channel<$adc, ID = u8>>(
+                &mut self,
+                _pin: &PIN,
+                attenuation: config::Attenuation,
+            ) -> Result<&mut Self, Error> {
+                if self.active_channel.is_some() {
+                    return Err(Error::ConversionInProgress);
+                }
+
+                let sensors = unsafe { &*SENS::ptr() };
+                let channel = PIN::channel() as usize;
+
+                sensors.$atten_reg.modify(|r, w| {
+                    let new_value = (r.bits() & !(0b11 << (channel * 2)))
+                        | (((attenuation as u8 & 0b11) as u32) << (channel * 2));
+
+                    unsafe { w.$atten_field().bits(new_value) }
+                });
+
+                self.attenuations[channel] = Some(attenuation);
+                Ok(self)
+            }
+        }
+    };
+}
+
+impl_adc_runtime_config! {
+    ADC1 (sar1_bit_width, sar_read_ctrl, sar1_sample_bit, sar_atten1, sar1_atten)
+}
+
+impl_adc_runtime_config! {
+    ADC2 (sar2_bit_width, sar_read_ctrl2, sar2_sample_bit, sar_atten2, sar2_atten)
+}
+
 macro_rules! impl_adc_interface {
     ($adc:ident ($start_reg:ident, $en_pad:ident, $start:ident, $done:ident, $data:ident): [
         $( ($pin:ident, $channel:expr) ,)+
@@ -177,13 +434,13 @@ macro_rules! impl_adc_interface {
         WORD: From<u16>,
         PIN: Channel<$adc, ID=u8>,
         {
-            type Error = ();
+            type Error = Error;
 
             fn read(&mut self, _pin: &mut PIN) -> nb::Result<WORD, Self::Error> {
                 let sensors = unsafe { &*SENS::ptr() };
 
                 if self.attenuations[PIN::channel() as usize] == None {
-                    panic!("Channel {} is not configured reading!", PIN::channel());
+                    return Err(nb::Error::Other(Error::ChannelNotEnabled));
                 }
 
                 if let Some(active_channel) = self.active_channel {
@@ -196,6 +453,7 @@ macro_rules! impl_adc_interface {
                 }
                 else {
                     // If no conversions are in progress, start a new one for given channel
+                    self.start_arbitration().map_err(nb::Error::Other)?;
                     self.active_channel = Some(PIN::channel());
 
                     sensors.$start_reg.modify(|_, w| {
@@ -218,6 +476,9 @@ macro_rules! impl_adc_interface {
                 // Mark that no conversions are currently in progress
                 self.active_channel = None;
 
+                // Discard the value if a higher priority user took over the ADC
+                self.finish_arbitration().map_err(nb::Error::Other)?;
+
                 Ok(converted_value.into())
             }
         }
@@ -0,0 +1,342 @@
+//! Calibrated ADC readings.
+//!
+//! Converts raw ADC readings into millivolts using the calibration data stored in the eFuse.
+//! The characteristic of the ADC is linear and depends on the ADC unit, the attenuation and the
+//! resolution. It is obtained from one of the following sources, in order of preference:
+//!
+//! - two point calibration: ADC readings at 150mV and 850mV
+//! - reference voltage: the measured ADC reference voltage
+//! - default: a reference voltage of 1100mV
+//!
+//! # Example
+//!
+//! ```
+//! let mut adc1 = ADC::adc1(analog.adc1, adc1_config).unwrap();
+//!
+//! let millivolts = nb::block!(adc1.read_millivolts(&mut pin36)).unwrap();
+//! ```
+//!
+//! # TODO
+//! - Lookup table correction of the non-linearity at 11dB attenuation
+
+use embedded_hal::adc::{Channel, OneShot};
+
+use crate::analog::adc::{Error, ADC};
+use crate::analog::config::{Attenuation, Resolution};
+use crate::analog::{ADC1, ADC2};
+use crate::efuse::Efuse;
+
+/// Scale of the `coeff_a` coefficient
+const LIN_COEFF_A_SCALE: u32 = 65536;
+/// Resolution of the characteristic
+const ADC_12_BIT_RES: u32 = 4096;
+/// Voltage of the low point of the two point calibration in mV
+const TP_LOW_VOLTAGE: u32 = 150;
+/// Voltage of the high point of the two point calibration in mV
+const TP_HIGH_VOLTAGE: u32 = 850;
+/// Reference voltage if it is not stored in the eFuse in mV
+const DEFAULT_VREF: u32 = 1100;
+
+/// Source of the calibration data
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CalibrationSource {
+    /// Two point calibration from the eFuse
+    TwoPoint,
+    /// Reference voltage from the eFuse
+    Vref,
+    /// Default reference voltage
+    Default,
+}
+
+/// Calibration constants of an ADC unit
+pub trait Calibration {
+    /// Scale of the two point characteristic per attenuation
+    const TP_ATTEN_SCALE: [u32; 4];
+    /// Offset of the two point characteristic per attenuation in mV
+    const TP_ATTEN_OFFSET: [u32; 4];
+    /// Scale of the reference voltage characteristic per attenuation
+    const VREF_ATTEN_SCALE: [u32; 4];
+    /// Offset of the reference voltage characteristic per attenuation in mV
+    const VREF_ATTEN_OFFSET: [u32; 4];
+
+    /// Readings at 150mV and 850mV from the eFuse
+    fn two_point_calibration() -> Option<(i32, i32)>;
+}
+
+impl Calibration for ADC1 {
+    const TP_ATTEN_SCALE: [u32; 4] = [65504, 86975, 120389, 224310];
+    const TP_ATTEN_OFFSET: [u32; 4] = [0, 1, 27, 54];
+    const VREF_ATTEN_SCALE: [u32; 4] = [57431, 76236, 105481, 196602];
+    const VREF_ATTEN_OFFSET: [u32; 4] = [75, 78, 107, 142];
+
+    fn two_point_calibration() -> Option<(i32, i32)> {
+        Efuse::get_adc1_two_point_cal()
+    }
+}
+
+impl Calibration for ADC2 {
+    const TP_ATTEN_SCALE: [u32; 4] = [65467, 86861, 120416, 224708];
+    const TP_ATTEN_OFFSET: [u32; 4] = [0, 9, 26, 66];
+    const VREF_ATTEN_SCALE: [u32; 4] = [57236, 76175, 105678, 197170];
+    const VREF_ATTEN_OFFSET: [u32; 4] = [63, 66, 89, 128];
+
+    fn two_point_calibration() -> Option<(i32, i32)> {
+        Efuse::get_adc2_two_point_cal()
+    }
+}
+
+/// Linear characteristic of an ADC for one attenuation and resolution
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Characteristics {
+    pub source: CalibrationSource,
+    pub attenuation: Attenuation,
+    pub resolution: Resolution,
+    /// Gradient, scaled by 65536
+    coeff_a: u32,
+    /// Offset in mV
+    coeff_b: i32,
+}
+
+impl Characteristics {
+    /// Characteristic from the readings (at 12 bits) at 150mV and 850mV
+    pub fn from_two_point<ADCX: Calibration>(
+        low: u32,
+        high: u32,
+        attenuation: Attenuation,
+        resolution: Resolution,
+    ) -> Self {
+        let delta_x = core::cmp::max(high.saturating_sub(low), 1);
+        let delta_v = TP_HIGH_VOLTAGE - TP_LOW_VOLTAGE;
+        let atten = attenuation as usize;
+
+        let coeff_a = (delta_v * ADCX::TP_ATTEN_SCALE[atten] + delta_x / 2) / delta_x;
+        let coeff_b = TP_HIGH_VOLTAGE as i32 - ((delta_v * high + delta_x / 2) / delta_x) as i32
+            + ADCX::TP_ATTEN_OFFSET[atten] as i32;
+
+        Characteristics {
+            source: CalibrationSource::TwoPoint,
+            attenuation,
+            resolution,
+            coeff_a,
+            coeff_b,
+        }
+    }
+
+    /// Characteristic from the reference voltage in mV
+    pub fn from_vref<ADCX: Calibration>(
+        vref: u32,
+        attenuation: Attenuation,
+        resolution: Resolution,
+    ) -> Self {
+        let atten = attenuation as usize;
+
+        Characteristics {
+            source: CalibrationSource::Vref,
+            attenuation,
+            resolution,
+            coeff_a: vref * ADCX::VREF_ATTEN_SCALE[atten] / ADC_12_BIT_RES,
+            coeff_b: ADCX::VREF_ATTEN_OFFSET[atten] as i32,
+        }
+    }
+
+    /// Characteristic from the calibration data in the eFuse
+    ///
+    /// Falls back to the reference voltage and then to the default reference voltage if the
+    /// data is not available. Two point data with a high reading not above the low reading is
+    /// ignored.
+    pub fn from_efuse<ADCX: Calibration>(attenuation: Attenuation, resolution: Resolution) -> Self {
+        if let Some((low, high)) = ADCX::two_point_calibration().filter(|(low, high)| high > low) {
+            return Self::from_two_point::<ADCX>(
+                low.max(0) as u32,
+                high.max(0) as u32,
+                attenuation,
+                resolution,
+            );
+        }
+
+        match Efuse::get_adc_vref() {
+            Some(vref) => Self::from_vref::<ADCX>(vref.max(0) as u32, attenuation, resolution),
+            None => Characteristics {
+                source: CalibrationSource::Default,
+                ..Self::from_vref::<ADCX>(DEFAULT_VREF, attenuation, resolution)
+            },
+        }
+    }
+
+    /// Convert a raw reading into millivolts
+    pub fn millivolts(&self, raw: u16) -> u32 {
+        raw_to_millivolts(raw, self.resolution, self.coeff_a, self.coeff_b)
+    }
+}
+
+/// Convert a raw reading into millivolts with the linear coefficients
+///
+/// The reading is scaled to 12 bits before the conversion: `coeff_a * reading / 65536 + coeff_b`.
+fn raw_to_millivolts(raw: u16, resolution: Resolution, coeff_a: u32, coeff_b: i32) -> u32 {
+    let bits = 9 + resolution as u32;
+    let reading = (raw as u32 & ((1 << bits) - 1)) << (12 - bits);
+
+    // 64 bits, as the gradient of a degenerate characteristic can be large
+    let scaled =
+        (coeff_a as u64 * reading as u64 + LIN_COEFF_A_SCALE as u64 / 2) / LIN_COEFF_A_SCALE as u64;
+    let voltage = scaled as i64 + coeff_b as i64;
+    voltage.max(0) as u32
+}
+
+impl<ADCX: Calibration> ADC<ADCX> {
+    /// Returns the characteristic of the channel with its attenuation
+    ///
+    /// Returns `None` if the channel is not enabled.
+    pub fn characteristics(&self, channel: u8) -> Option<Characteristics> {
+        let attenuation = self.attenuation(channel)?;
+        Some(Characteristics::from_efuse::<ADCX>(
+            attenuation,
+            self.resolution(),
+        ))
+    }
+
+    /// Read the voltage of the pin in millivolts
+    pub fn read_millivolts<PIN>(&mut self, pin: &mut PIN) -> nb::Result<u32, Error>
+    where
+        PIN: Channel<ADCX, ID = u8>,
+        Self: OneShot<ADCX, u16, PIN, Error = Error>,
+    {
+        let raw: u16 = self.read(pin)?;
+        let characteristics = self
+            .characteristics(PIN::channel())
+            .ok_or(nb::Error::Other(Error::ChannelNotEnabled))?;
+
+        Ok(characteristics.millivolts(raw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATTENUATIONS: [Attenuation; 4] = [
+        Attenuation::Attenuation0dB,
+        Attenuation::Attenuation2p5dB,
+        Attenuation::Attenuation6dB,
+        Attenuation::Attenuation11dB,
+    ];
+
+    #[test]
+    fn default_vref() {
+        let adc1_full_scale = [1039, 1357, 1877, 3441];
+        let adc2_full_scale = [1023, 1344, 1862, 3437];
+
+        for (i, &attenuation) in ATTENUATIONS.iter().enumerate() {
+            let adc1 = Characteristics::from_vref::<ADC1>(
+                DEFAULT_VREF,
+                attenuation,
+                Resolution::Resolution12Bit,
+            );
+            assert_eq!(adc1.millivolts(0), ADC1::VREF_ATTEN_OFFSET[i]);
+            assert_eq!(adc1.millivolts(4095), adc1_full_scale[i]);
+
+            let adc2 = Characteristics::from_vref::<ADC2>(
+                DEFAULT_VREF,
+                attenuation,
+                Resolution::Resolution12Bit,
+            );
+            assert_eq!(adc2.millivolts(0), ADC2::VREF_ATTEN_OFFSET[i]);
+            assert_eq!(adc2.millivolts(4095), adc2_full_scale[i]);
+        }
+    }
+
+    #[test]
+    fn vref() {
+        let full_scale = [1083, 1415, 1957, 3591];
+
+        for (i, &attenuation) in ATTENUATIONS.iter().enumerate() {
+            let characteristics =
+                Characteristics::from_vref::<ADC1>(1150, attenuation, Resolution::Resolution12Bit);
+            assert_eq!(characteristics.source, CalibrationSource::Vref);
+            assert_eq!(characteristics.millivolts(0), ADC1::VREF_ATTEN_OFFSET[i]);
+            assert_eq!(characteristics.millivolts(4095), full_scale[i]);
+        }
+    }
+
+    #[test]
+    fn two_point() {
+        let low = 548;
+        let high = 2600;
+        let expected = [
+            (150, 849, 1359),
+            (212, 1141, 1818),
+            (333, 1619, 2556),
+            (657, 3053, 4798),
+        ];
+
+        for (i, &attenuation) in ATTENUATIONS.iter().enumerate() {
+            let characteristics = Characteristics::from_two_point::<ADC1>(
+                low,
+                high,
+                attenuation,
+                Resolution::Resolution12Bit,
+            );
+            assert_eq!(characteristics.source, CalibrationSource::TwoPoint);
+            assert_eq!(characteristics.millivolts(low as u16), expected[i].0);
+            assert_eq!(characteristics.millivolts(high as u16), expected[i].1);
+            assert_eq!(characteristics.millivolts(4095), expected[i].2);
+        }
+
+        // readings below the offset are clamped to 0
+        let characteristics = Characteristics::from_two_point::<ADC1>(
+            low,
+            high,
+            Attenuation::Attenuation0dB,
+            Resolution::Resolution12Bit,
+        );
+        assert_eq!(characteristics.millivolts(0), 0);
+    }
+
+    #[test]
+    fn two_point_degenerate() {
+        for &(low, high) in &[(2600, 2600), (2600, 548)] {
+            let characteristics = Characteristics::from_two_point::<ADC1>(
+                low,
+                high,
+                Attenuation::Attenuation11dB,
+                Resolution::Resolution12Bit,
+            );
+            // no overflow of the conversion
+            assert!(characteristics.millivolts(4095) > characteristics.millivolts(0));
+        }
+    }
+
+    #[test]
+    fn resolution() {
+        let resolutions = [
+            Resolution::Resolution9Bit,
+            Resolution::Resolution10Bit,
+            Resolution::Resolution11Bit,
+            Resolution::Resolution12Bit,
+        ];
+        let reference = Characteristics::from_vref::<ADC1>(
+            DEFAULT_VREF,
+            Attenuation::Attenuation11dB,
+            Resolution::Resolution12Bit,
+        );
+
+        for (i, &resolution) in resolutions.iter().enumerate() {
+            let characteristics = Characteristics::from_vref::<ADC1>(
+                DEFAULT_VREF,
+                Attenuation::Attenuation11dB,
+                resolution,
+            );
+            let shift = 3 - i;
+
+            assert_eq!(
+                characteristics.millivolts(0x123 >> shift),
+                reference.millivolts((0x123 >> shift) << shift)
+            );
+            // bits above the resolution are ignored
+            assert_eq!(
+                characteristics.millivolts(1 << (12 - shift)),
+                characteristics.millivolts(0)
+            );
+        }
+    }
+}
@@ -4,7 +4,7 @@ use crate::analog::{ADC1, ADC2};
 use embedded_hal::adc::Channel;
 
 /// The sampling/readout resolution of the ADC
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Resolution {
     Resolution9Bit = 0b00,
     Resolution10Bit = 0b01,
@@ -13,7 +13,7 @@ pub enum Resolution {
 }
 
 /// The attenuation of the ADC pin
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Attenuation {
     Attenuation0dB = 0b00,
     Attenuation2p5dB = 0b01,
@@ -0,0 +1,438 @@
+//! Continuous ADC conversion via DMA.
+//!
+//! In continuous mode the SAR ADC1 is driven by the digital controller, which cycles through a
+//! pattern table of up to 16 channels. The conversion results are handed to the I2S0
+//! peripheral, whose clock determines the sample rate, and are written into a
+//! [CircularBuffer] via DMA (requires the `alloc` feature).
+//!
+//! Each sample is a 16-bit word containing the channel number and the 12-bit conversion
+//! result.
+//!
+//! # Example
+//!
+//! ```
+//! #[repr(align(4))]
+//! struct Aligned([u8; 1024]);
+//!
+//! static mut BUFFER: Aligned = Aligned([0; 1024]);
+//!
+//! let mut pin36 = pins.gpio36.into_analog();
+//! let mut pin39 = pins.gpio39.into_analog();
+//!
+//! let mut config = ContinuousConfig::new(20.kHz().into());
+//! config.add_pin(&pin36, Attenuation::Attenuation11dB).unwrap();
+//! config.add_pin(&pin39, Attenuation::Attenuation11dB).unwrap();
+//!
+//! let buffer = CircularBuffer::new(unsafe { &mut BUFFER.0 }).unwrap();
+//! let mut adc = ContinuousADC::new(
+//!     analog.adc1,
+//!     dp.I2S,
+//!     config,
+//!     buffer,
+//!     clkcntrl_config,
+//!     &mut dport,
+//! )
+//! .unwrap();
+//!
+//! adc.start();
+//! loop {
+//!     if let Some(samples) = adc.samples() {
+//!         for (channel, value) in samples {
+//!             ...
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! # TODO
+//! - Continuous conversion with ADC2
+
+use embedded_hal::adc::Channel;
+
+use crate::analog::config::{Attenuation, Resolution};
+use crate::analog::ADC1;
+use crate::dma::CircularBuffer;
+use crate::i2s::{self, Instance};
+use crate::prelude::*;
+use crate::target::{self, APB_CTRL, SENS};
+
+/// Maximum number of entries in the pattern table
+pub const MAX_PATTERN_LENGTH: usize = 16;
+
+/// Minimum sample rate of the digital controller
+const MIN_SAMPLE_RATE: u32 = 20_000;
+/// Maximum sample rate of the digital controller
+const MAX_SAMPLE_RATE: u32 = 2_000_000;
+
+/// Continuous ADC error
+#[derive(Debug)]
+pub enum Error {
+    /// The pattern table is empty
+    EmptyPattern,
+    /// The pattern table is full
+    PatternTooLong,
+    /// The sample rate is not supported
+    InvalidSampleRate,
+}
+
+/// Entry of the pattern table
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct PatternEntry {
+    pub channel: u8,
+    pub attenuation: Attenuation,
+}
+
+impl PatternEntry {
+    /// Register representation of the entry
+    fn bits(&self) -> u8 {
+        (self.channel & 0xf) << 4
+            | (Resolution::Resolution12Bit as u8) << 2
+            | self.attenuation as u8
+    }
+}
+
+/// Configuration of the continuous conversion
+pub struct ContinuousConfig {
+    pub sample_rate: Hertz,
+    pattern: [Option<PatternEntry>; MAX_PATTERN_LENGTH],
+    length: usize,
+}
+
+impl ContinuousConfig {
+    pub fn new(sample_rate: Hertz) -> ContinuousConfig {
+        ContinuousConfig {
+            sample_rate,
+            pattern: [None; MAX_PATTERN_LENGTH],
+            length: 0,
+        }
+    }
+
+    /// Append a pin to the pattern table
+    ///
+    /// A pin can be added multiple times to sample it more often than the others.
+    pub fn add_pin<PIN: Channel<ADC1, ID = u8>>(
+        &mut self,
+        _pin: &PIN,
+        attenuation: Attenuation,
+    ) -> Result<&mut Self, Error> {
+        if self.length == MAX_PATTERN_LENGTH {
+            return Err(Error::PatternTooLong);
+        }
+
+        self.pattern[self.length] = Some(PatternEntry {
+            channel: PIN::channel(),
+            attenuation,
+        });
+        self.length += 1;
+
+        Ok(self)
+    }
+
+    /// Entries of the pattern table
+    pub fn pattern(&self) -> impl Iterator<Item = PatternEntry> + '_ {
+        self.pattern[..self.length].iter().flatten().copied()
+    }
+}
+
+/// Register values of the 4 pattern table registers
+///
+/// Each register holds 4 entries, with the first entry in the most significant byte.
+fn pattern_table<I: IntoIterator<Item = PatternEntry>>(pattern: I) -> [u32; 4] {
+    let mut table = [0; 4];
+    for (index, entry) in pattern.into_iter().take(MAX_PATTERN_LENGTH).enumerate() {
+        table[index / 4] |= (entry.bits() as u32) << ((3 - index % 4) * 8);
+    }
+    table
+}
+
+/// Split a sample into the channel and the raw conversion result
+pub fn decode_sample(sample: u16) -> (u8, u16) {
+    ((sample >> 12) as u8, sample & 0xfff)
+}
+
+/// Iterator over the samples of one half of the buffer
+pub struct Samples<'a> {
+    data: core::slice::ChunksExact<'a, u8>,
+}
+
+impl<'a> Iterator for Samples<'a> {
+    /// Channel and raw value
+    type Item = (u8, u16);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.data.next()?;
+        Some(decode_sample(u16::from_le_bytes([sample[0], sample[1]])))
+    }
+}
+
+/// ADC1 in continuous conversion mode
+///
+/// Dropping the ADC stops the conversion.
+pub struct ContinuousADC {
+    adc: Option<ADC1>,
+    i2s: Option<target::I2S>,
+    buffer: Option<CircularBuffer>,
+    _pll_lock: crate::clock_control::dfs::LockPllD2,
+    _apb_lock: crate::clock_control::dfs::LockAPB,
+}
+
+impl ContinuousADC {
+    /// Configure ADC1 and I2S0 for continuous conversion
+    ///
+    /// The length of each half of the buffer determines how many samples are collected before
+    /// they can be read.
+    pub fn new(
+        adc: ADC1,
+        mut i2s: target::I2S,
+        config: ContinuousConfig,
+        buffer: CircularBuffer,
+        clock_control: crate::clock_control::ClockControlConfig,
+        dport: &mut target::DPORT,
+    ) -> Result<Self, Error> {
+        if config.length == 0 {
+            return Err(Error::EmptyPattern);
+        }
+        if config.sample_rate.0 < MIN_SAMPLE_RATE || config.sample_rate.0 > MAX_SAMPLE_RATE {
+            return Err(Error::InvalidSampleRate);
+        }
+
+        // one 16-bit sample per word select period
+        let dividers =
+            i2s::clock_dividers(clock_control.pll_d2_frequency(), config.sample_rate, 16)
+                .map_err(|_| Error::InvalidSampleRate)?;
+
+        let sensors = unsafe { &*SENS::ptr() };
+        let apb_ctrl = unsafe { &*APB_CTRL::ptr() };
+
+        /* Select the digital controller and power on the ADC */
+        sensors
+            .sar_read_ctrl
+            .modify(|_, w| w.sar1_dig_force().set_bit());
+        sensors.sar_meas_start1.modify(|_, w| {
+            w.meas1_start_force()
+                .set_bit()
+                .sar1_en_pad_force()
+                .set_bit()
+        });
+        sensors
+            .sar_touch_ctrl1
+            .modify(|_, w| w.xpd_hall_force().set_bit().hall_phase_force().set_bit());
+        sensors
+            .sar_meas_wait2
+            .modify(|_, w| unsafe { w.force_xpd_sar().bits(0b11) });
+
+        /* Pattern table, conversion timing and output to I2S */
+        let table = pattern_table(config.pattern());
+        apb_ctrl
+            .apb_saradc_sar1_patt_tab1
+            .write(|w| unsafe { w.bits(table[0]) });
+        apb_ctrl
+            .apb_saradc_sar1_patt_tab2
+            .write(|w| unsafe { w.bits(table[1]) });
+        apb_ctrl
+            .apb_saradc_sar1_patt_tab3
+            .write(|w| unsafe { w.bits(table[2]) });
+        apb_ctrl
+            .apb_saradc_sar1_patt_tab4
+            .write(|w| unsafe { w.bits(table[3]) });
+
+        apb_ctrl.apb_saradc_fsm.write(|w| unsafe {
+            w.saradc_rstb_wait()
+                .bits(8)
+                .saradc_start_wait()
+                .bits(5)
+                .saradc_standby_wait()
+                .bits(100)
+                .saradc_sample_cycle()
+                .bits(2)
+        });
+        apb_ctrl.apb_saradc_ctrl2.modify(|_, w| unsafe {
+            w.saradc_meas_num_limit()
+                .set_bit()
+                .saradc_max_meas_num()
+                .bits(255)
+                // the digital controller of ADC1 delivers inverted data
+                .saradc_sar1_inv()
+                .set_bit()
+        });
+        apb_ctrl.apb_saradc_ctrl.modify(|_, w| unsafe {
+            w.saradc_work_mode()
+                .bits(0)
+                .saradc_sar_sel()
+                .clear_bit()
+                .saradc_sar_clk_div()
+                .bits(4)
+                .saradc_sar_clk_gated()
+                .set_bit()
+                .saradc_sar1_patt_len()
+                .bits(config.length as u8 - 1)
+                .saradc_sar1_patt_p_clear()
+                .set_bit()
+                .saradc_data_sar_sel()
+                .clear_bit()
+                .saradc_data_to_i2s()
+                .set_bit()
+        });
+        apb_ctrl
+            .apb_saradc_ctrl
+            .modify(|_, w| w.saradc_sar1_patt_p_clear().clear_bit());
+
+        /* I2S0 in ADC mode: master receiver, 16-bit single channel */
+        i2s.reset(dport).enable(dport);
+        i2s::reset_transfers(&i2s);
+
+        i2s.conf2
+            .write(|w| w.lcd_en().set_bit().camera_en().clear_bit());
+        i2s.pdm_conf
+            .modify(|_, w| w.rx_pdm_en().clear_bit().pdm2pcm_conv_en().clear_bit());
+        i2s.conf1.modify(|_, w| w.rx_pcm_bypass().set_bit());
+        i2s.conf_chan
+            .modify(|_, w| unsafe { w.rx_chan_mod().bits(1) });
+        i2s.fifo_conf.write(|w| unsafe {
+            w.dscr_en()
+                .set_bit()
+                .rx_fifo_mod_force_en()
+                .set_bit()
+                .rx_fifo_mod()
+                .bits(1)
+                .rx_data_num()
+                .bits(32)
+        });
+        i2s.conf.modify(|_, w| {
+            w.rx_slave_mod()
+                .clear_bit()
+                .rx_msb_shift()
+                .clear_bit()
+                .rx_short_sync()
+                .clear_bit()
+                .rx_mono()
+                .clear_bit()
+                .rx_msb_right()
+                .clear_bit()
+                .rx_right_first()
+                .clear_bit()
+        });
+        i2s.sample_rate_conf
+            .modify(|_, w| unsafe { w.rx_bits_mod().bits(16) });
+        i2s::set_clock_dividers(&i2s, dividers, false);
+        i2s.lc_conf.write(|w| w.indscr_burst_en().set_bit());
+
+        Ok(ContinuousADC {
+            adc: Some(adc),
+            i2s: Some(i2s),
+            buffer: Some(buffer),
+            _pll_lock: clock_control.lock_plld2(),
+            _apb_lock: clock_control.lock_apb_frequency(),
+        })
+    }
+
+    /// Start the conversion
+    ///
+    /// The interrupt enabled via [listen][ContinuousADC::listen] stays enabled.
+    pub fn start(&mut self) {
+        let i2s = self.i2s();
+        let buffer = match self.buffer.as_ref() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+
+        let int_ena = i2s.int_ena.read().bits();
+        i2s::reset_transfers(i2s);
+        i2s.int_ena.write(|w| unsafe { w.bits(int_ena) });
+
+        unsafe {
+            // end of frame after each half of the buffer (in words)
+            i2s.rxeof_num
+                .write(|w| w.rx_eof_num().bits(buffer.half_len() as u32 / 4));
+            i2s.in_link.write(|w| {
+                w.inlink_addr()
+                    .bits(buffer.descriptors.address())
+                    .inlink_start()
+                    .set_bit()
+            });
+        }
+        i2s.conf.modify(|_, w| w.rx_start().set_bit());
+    }
+
+    /// Stop the conversion
+    pub fn stop(&mut self) {
+        i2s::reset_transfers(self.i2s());
+    }
+
+    /// Returns the samples of the last completely filled half of the buffer, if any
+    ///
+    /// The samples need to be processed before the other half of the buffer is filled.
+    pub fn samples(&mut self) -> Option<Samples<'_>> {
+        let i2s = self.i2s();
+        let buffer = self.buffer.as_ref()?;
+
+        if i2s.int_raw.read().in_suc_eof_int_raw().bit_is_clear() {
+            return None;
+        }
+        i2s.int_clr.write(|w| w.in_suc_eof_int_clr().set_bit());
+
+        let address = i2s.in_eof_des_addr.read().bits();
+        let half = buffer.descriptors.half_of(address);
+
+        Some(Samples {
+            data: buffer.half(half).chunks_exact(2),
+        })
+    }
+
+    /// Starts listening for the interrupt signalling a filled half of the buffer
+    ///
+    /// The interrupt needs to be routed via [interrupt::enable][crate::interrupt]
+    /// (`I2S0_INTR`).
+    pub fn listen(&mut self) {
+        self.i2s()
+            .int_ena
+            .modify(|_, w| w.in_suc_eof_int_ena().set_bit());
+    }
+
+    /// Stop listening for the interrupt
+    pub fn unlisten(&mut self) {
+        self.i2s()
+            .int_ena
+            .modify(|_, w| w.in_suc_eof_int_ena().clear_bit());
+    }
+
+    /// Stop the conversion and release the resources
+    pub fn release(mut self) -> (ADC1, target::I2S, CircularBuffer) {
+        self.disable();
+
+        (
+            self.adc.take().unwrap(),
+            self.i2s.take().unwrap(),
+            self.buffer.take().unwrap(),
+        )
+    }
+
+    /// Stop the conversion and hand ADC1 back to the RTC controller
+    fn disable(&mut self) {
+        self.stop();
+
+        let sensors = unsafe { &*SENS::ptr() };
+        let apb_ctrl = unsafe { &*APB_CTRL::ptr() };
+
+        apb_ctrl
+            .apb_saradc_ctrl
+            .modify(|_, w| w.saradc_data_to_i2s().clear_bit());
+        sensors
+            .sar_read_ctrl
+            .modify(|_, w| w.sar1_dig_force().clear_bit());
+        self.i2s().conf2.write(|w| w.lcd_en().clear_bit());
+    }
+
+    fn i2s(&self) -> &'static target::i2s::RegisterBlock {
+        unsafe { &*target::I2S::ptr() }
+    }
+}
+
+impl Drop for ContinuousADC {
+    fn drop(&mut self) {
+        // the descriptors are freed together with the buffer, so the DMA engine must be stopped
+        if self.buffer.is_some() {
+            self.disable();
+        }
+    }
+}
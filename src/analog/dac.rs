@@ -5,13 +5,117 @@
 //!
 //! The DAC1 is avilable on the GPIO pin 25, and DAC2 on pin 26.
 //!
+//! Besides static levels, the DACs can output a cosine wave generated in hardware. The
+//! frequency is derived from the RTC 8MHz clock and shared by both DACs, while amplitude,
+//! DC offset and phase are set per DAC.
+//!
+//! # Example
+//!
+//! ```
+//! let mut dac1 = DAC::dac1(analog.dac1, pins.gpio25.into_analog()).unwrap();
+//!
+//! dac::set_cosine_frequency(1.kHz().into(), clkcntrl_config).unwrap();
+//! dac1.start_cosine(CosineConfig {
+//!     scale: CosineScale::Half,
+//!     offset: 0,
+//!     inverted: false,
+//! });
+//! ```
+//!
 
 use core::marker::PhantomData;
 
 use crate::analog::{DAC1, DAC2};
 use crate::gpio::{Analog, Gpio25, Gpio26};
+use crate::prelude::*;
 use crate::target::{RTCIO, SENS};
 
+/// DAC error
+#[derive(Debug)]
+pub enum Error {
+    /// The RTC 8MHz clock is not running
+    ClockNotRunning,
+    /// The cosine frequency is out of range for the RTC 8MHz clock
+    FrequencyOutOfRange,
+}
+
+/// Amplitude of the cosine wave
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CosineScale {
+    /// Full scale
+    Full = 0,
+    /// 1/2 of full scale
+    Half = 1,
+    /// 1/4 of full scale
+    Quarter = 2,
+    /// 1/8 of full scale
+    Eighth = 3,
+}
+
+/// Configuration of the cosine wave of one DAC
+#[derive(Clone, Copy, Debug)]
+pub struct CosineConfig {
+    pub scale: CosineScale,
+    /// DC offset
+    pub offset: i8,
+    /// Shift the phase by 180 degrees
+    pub inverted: bool,
+}
+
+impl Default for CosineConfig {
+    fn default() -> Self {
+        CosineConfig {
+            scale: CosineScale::Full,
+            offset: 0,
+            inverted: false,
+        }
+    }
+}
+
+/// Register value of the phase
+fn cosine_phase(inverted: bool) -> u8 {
+    if inverted {
+        0b11
+    } else {
+        0b10
+    }
+}
+
+/// Register value of the offset, which is inverted together with the phase
+fn cosine_offset(config: &CosineConfig) -> u8 {
+    if config.inverted {
+        config.offset.wrapping_neg() as u8
+    } else {
+        config.offset as u8
+    }
+}
+
+/// Set the frequency of the cosine generator of both DACs
+///
+/// Returns the actual frequency, which is a multiple of the RTC 8MHz clock divided by 65536.
+pub fn set_cosine_frequency(
+    frequency: Hertz,
+    clock_control: crate::clock_control::ClockControlConfig,
+) -> Result<Hertz, Error> {
+    let sensors = unsafe { &*SENS::ptr() };
+
+    let clock = clock_control.rtc8m_frequency().0 as u64;
+    if clock == 0 {
+        return Err(Error::ClockNotRunning);
+    }
+
+    let step = (frequency.0 as u64 * 65536 + clock / 2) / clock;
+    if step == 0 || step > 0xffff {
+        return Err(Error::FrequencyOutOfRange);
+    }
+
+    sensors
+        .sar_dac_ctrl1
+        .modify(|_, w| unsafe { w.sw_fstep().bits(step as u16).sw_tone_en().set_bit() });
+
+    Ok(Hertz((clock * step / 65536) as u32))
+}
+
 pub struct DAC<DAC> {
     _dac: PhantomData<DAC>,
 }
@@ -45,6 +149,33 @@ impl DAC<DAC1> {
             .pad_dac1
             .modify(|_, w| unsafe { w.pdac1_dac().bits(value) });
     }
+
+    /// Output a cosine wave with the frequency set by [set_cosine_frequency]
+    pub fn start_cosine(&mut self, config: CosineConfig) {
+        let sensors = unsafe { &*SENS::ptr() };
+
+        sensors.sar_dac_ctrl2.modify(|_, w| unsafe {
+            w.dac_scale1()
+                .bits(config.scale as u8)
+                .dac_inv1()
+                .bits(cosine_phase(config.inverted))
+                .dac_dc1()
+                .bits(cosine_offset(&config))
+                .dac_cw_en1()
+                .set_bit()
+        });
+    }
+
+    /// Stop the cosine wave
+    ///
+    /// The DAC outputs the level last set by `write`.
+    pub fn stop_cosine(&mut self) {
+        let sensors = unsafe { &*SENS::ptr() };
+
+        sensors
+            .sar_dac_ctrl2
+            .modify(|_, w| w.dac_cw_en1().clear_bit());
+    }
 }
 
 impl DAC<DAC2> {
@@ -76,4 +207,31 @@ impl DAC<DAC2> {
             .pad_dac2
             .modify(|_, w| unsafe { w.pdac2_dac().bits(value) });
     }
+
+    /// Output a cosine wave with the frequency set by [set_cosine_frequency]
+    pub fn start_cosine(&mut self, config: CosineConfig) {
+        let sensors = unsafe { &*SENS::ptr() };
+
+        sensors.sar_dac_ctrl2.modify(|_, w| unsafe {
+            w.dac_scale2()
+                .bits(config.scale as u8)
+                .dac_inv2()
+                .bits(cosine_phase(config.inverted))
+                .dac_dc2()
+                .bits(cosine_offset(&config))
+                .dac_cw_en2()
+                .set_bit()
+        });
+    }
+
+    /// Stop the cosine wave
+    ///
+    /// The DAC outputs the level last set by `write`.
+    pub fn stop_cosine(&mut self) {
+        let sensors = unsafe { &*SENS::ptr() };
+
+        sensors
+            .sar_dac_ctrl2
+            .modify(|_, w| w.dac_cw_en2().clear_bit());
+    }
 }
@@ -0,0 +1,330 @@
+//! DAC playback via I2S0 DMA.
+//!
+//! In the built-in DAC mode the I2S0 peripheral feeds the DACs with samples from memory via
+//! DMA, so waveforms and simple audio can be played on GPIO25 (DAC1) and GPIO26 (DAC2) without
+//! an external codec (requires the `alloc` feature).
+//!
+//! The samples are 8 bits wide. In mono mode each sample is output on all owned DACs, in stereo
+//! mode the samples are interleaved, starting with DAC1. The samples are expanded into the
+//! I2S format in a static DMA buffer, which therefore needs 4 bytes per mono sample or stereo
+//! frame.
+//!
+//! # Example
+//!
+//! ```
+//! #[repr(align(4))]
+//! struct Aligned([u8; 1024]);
+//!
+//! static mut BUFFER: Aligned = Aligned([0; 1024]);
+//!
+//! let dac1 = DAC::dac1(analog.dac1, pins.gpio25.into_analog()).unwrap();
+//!
+//! let mut playback = DacDma::new(
+//!     dp.I2S,
+//!     Dacs::Dac1(dac1),
+//!     unsafe { &mut BUFFER.0 },
+//!     Config::default(),
+//!     clkcntrl_config,
+//!     &mut dport,
+//! )
+//! .unwrap();
+//!
+//! // play a sawtooth continuously
+//! let mut samples = [0u8; 256];
+//! for (i, sample) in samples.iter_mut().enumerate() {
+//!     *sample = i as u8;
+//! }
+//! playback.play(&samples, true).unwrap();
+//! ```
+
+use crate::analog::dac::DAC;
+use crate::analog::{DAC1, DAC2};
+use crate::dma::{self, DescriptorList};
+use crate::i2s::{self, Instance};
+use crate::prelude::*;
+use crate::target::{self, SENS};
+
+/// DAC playback error
+#[derive(Debug)]
+pub enum Error {
+    /// The sample rate cannot be generated
+    InvalidSampleRate,
+    /// Stereo mode requires both DACs
+    StereoNeedsBothDacs,
+    /// The samples do not fit into the buffer
+    BufferTooSmall,
+    /// The buffer is not suitable for DMA
+    Dma(dma::Error),
+}
+
+impl From<dma::Error> for Error {
+    fn from(error: dma::Error) -> Self {
+        Error::Dma(error)
+    }
+}
+
+/// DACs fed by I2S0
+pub enum Dacs {
+    Dac1(DAC<DAC1>),
+    Dac2(DAC<DAC2>),
+    Both(DAC<DAC1>, DAC<DAC2>),
+}
+
+/// Sample layout
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Mode {
+    /// Each sample is output on all DACs
+    Mono,
+    /// Interleaved samples for DAC1 and DAC2
+    Stereo,
+}
+
+/// Playback configuration
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    pub sample_rate: Hertz,
+    pub mode: Mode,
+}
+
+impl Config {
+    pub fn sample_rate(mut self, sample_rate: Hertz) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            sample_rate: Hertz(44_100),
+            mode: Mode::Mono,
+        }
+    }
+}
+
+/// Expand 8-bit samples into the I2S format of the built-in DAC mode
+///
+/// Each frame is a 32-bit word with the DAC2 sample in the upper byte of the lower half-word
+/// and the DAC1 sample in the upper byte of the upper half-word. Returns the number of bytes
+/// written or `None` if the output is too small.
+pub fn expand_samples(samples: &[u8], mode: Mode, output: &mut [u8]) -> Option<usize> {
+    let frames = match mode {
+        Mode::Mono => samples.len(),
+        Mode::Stereo => samples.len() / 2,
+    };
+    let length = frames * 4;
+    if length > output.len() {
+        return None;
+    }
+
+    for (index, frame) in output[..length].chunks_exact_mut(4).enumerate() {
+        let (dac1, dac2) = match mode {
+            Mode::Mono => (samples[index], samples[index]),
+            Mode::Stereo => (samples[2 * index], samples[2 * index + 1]),
+        };
+        frame.copy_from_slice(&[0, dac2, 0, dac1]);
+    }
+
+    Some(length)
+}
+
+/// DAC playback via I2S0
+///
+/// Dropping the playback stops it and switches the DACs back to the RTC registers.
+pub struct DacDma {
+    i2s: Option<target::I2S>,
+    dacs: Option<Dacs>,
+    buffer: &'static mut [u8],
+    descriptors: Option<DescriptorList>,
+    mode: Mode,
+    _pll_lock: crate::clock_control::dfs::LockPllD2,
+    _apb_lock: crate::clock_control::dfs::LockAPB,
+}
+
+impl DacDma {
+    /// Configure I2S0 to feed the DACs
+    ///
+    /// The buffer needs to be located in internal DRAM and be word aligned.
+    pub fn new(
+        mut i2s: target::I2S,
+        dacs: Dacs,
+        buffer: &'static mut [u8],
+        config: Config,
+        clock_control: crate::clock_control::ClockControlConfig,
+        dport: &mut target::DPORT,
+    ) -> Result<Self, Error> {
+        match (config.mode, &dacs) {
+            (Mode::Stereo, Dacs::Dac1(_)) | (Mode::Stereo, Dacs::Dac2(_)) => {
+                return Err(Error::StereoNeedsBothDacs)
+            }
+            _ => {}
+        }
+        if !dma::is_word_aligned(buffer) {
+            return Err(Error::Dma(dma::Error::BufferNotAligned));
+        }
+        if !dma::is_dma_capable(buffer) {
+            return Err(Error::Dma(dma::Error::BufferNotDmaCapable));
+        }
+
+        let dividers =
+            i2s::clock_dividers(clock_control.pll_d2_frequency(), config.sample_rate, 16)
+                .map_err(|_| Error::InvalidSampleRate)?;
+
+        i2s.reset(dport).enable(dport);
+        i2s::reset_transfers(&i2s);
+
+        /* I2S0 in built-in DAC mode: master transmitter, 16-bit dual channel */
+        i2s.conf2
+            .write(|w| w.lcd_en().set_bit().camera_en().clear_bit());
+        i2s.pdm_conf
+            .modify(|_, w| w.tx_pdm_en().clear_bit().pcm2pdm_conv_en().clear_bit());
+        i2s.conf1
+            .modify(|_, w| w.tx_pcm_bypass().set_bit().tx_stop_en().set_bit());
+        i2s.conf_chan
+            .modify(|_, w| unsafe { w.tx_chan_mod().bits(0) });
+        i2s.fifo_conf.write(|w| unsafe {
+            w.dscr_en()
+                .set_bit()
+                .tx_fifo_mod_force_en()
+                .set_bit()
+                .tx_fifo_mod()
+                .bits(0)
+                .tx_data_num()
+                .bits(32)
+        });
+        i2s.conf.modify(|_, w| {
+            w.tx_slave_mod()
+                .clear_bit()
+                .tx_right_first()
+                .set_bit()
+                .tx_msb_right()
+                .set_bit()
+                .tx_msb_shift()
+                .clear_bit()
+                .tx_short_sync()
+                .clear_bit()
+                .tx_mono()
+                .clear_bit()
+        });
+        i2s.sample_rate_conf
+            .modify(|_, w| unsafe { w.tx_bits_mod().bits(16) });
+        i2s::set_clock_dividers(&i2s, dividers, false);
+        i2s.lc_conf.write(|w| {
+            w.out_eof_mode()
+                .set_bit()
+                .outdscr_burst_en()
+                .set_bit()
+                .out_data_burst_en()
+                .set_bit()
+        });
+
+        /* Switch the DACs from the RTC registers to I2S0 */
+        let sensors = unsafe { &*SENS::ptr() };
+        sensors
+            .sar_dac_ctrl2
+            .modify(|_, w| w.dac_cw_en1().clear_bit().dac_cw_en2().clear_bit());
+        sensors
+            .sar_dac_ctrl1
+            .modify(|_, w| w.dac_dig_force().set_bit().dac_clk_inv().set_bit());
+
+        Ok(DacDma {
+            i2s: Some(i2s),
+            dacs: Some(dacs),
+            buffer,
+            descriptors: None,
+            mode: config.mode,
+            _pll_lock: clock_control.lock_plld2(),
+            _apb_lock: clock_control.lock_apb_frequency(),
+        })
+    }
+
+    /// Play the samples, repeating them if `looped` is set
+    ///
+    /// A playback in progress is stopped first.
+    pub fn play(&mut self, samples: &[u8], looped: bool) -> Result<(), Error> {
+        self.stop();
+
+        let length = expand_samples(samples, self.mode, &mut self.buffer[..])
+            .ok_or(Error::BufferTooSmall)?;
+
+        let buffer = &self.buffer[..length];
+        let descriptors = if looped {
+            DescriptorList::new_looped(buffer)?
+        } else {
+            DescriptorList::new(buffer)?
+        };
+
+        let i2s = self.i2s();
+        unsafe {
+            i2s.out_link.write(|w| {
+                w.outlink_addr()
+                    .bits(descriptors.address())
+                    .outlink_start()
+                    .set_bit()
+            });
+        }
+        i2s.conf.modify(|_, w| w.tx_start().set_bit());
+
+        self.descriptors = Some(descriptors);
+        Ok(())
+    }
+
+    /// Returns true if all samples have been played (never for looped playback)
+    pub fn is_done(&self) -> bool {
+        self.descriptors.is_none()
+            || self
+                .i2s()
+                .int_raw
+                .read()
+                .out_total_eof_int_raw()
+                .bit_is_set()
+    }
+
+    /// Stop the playback
+    pub fn stop(&mut self) {
+        i2s::reset_transfers(self.i2s());
+        self.descriptors = None;
+    }
+
+    /// Stop the playback and release the resources
+    ///
+    /// The DACs are switched back to the RTC registers.
+    pub fn release(mut self) -> (target::I2S, Dacs, &'static mut [u8]) {
+        self.disable();
+
+        (
+            self.i2s.take().unwrap(),
+            self.dacs.take().unwrap(),
+            core::mem::take(&mut self.buffer),
+        )
+    }
+
+    /// Stop the playback and switch the DACs back to the RTC registers
+    fn disable(&mut self) {
+        self.stop();
+
+        let sensors = unsafe { &*SENS::ptr() };
+        sensors
+            .sar_dac_ctrl1
+            .modify(|_, w| w.dac_dig_force().clear_bit().dac_clk_inv().clear_bit());
+        self.i2s().conf2.write(|w| w.lcd_en().clear_bit());
+    }
+
+    fn i2s(&self) -> &'static target::i2s::RegisterBlock {
+        unsafe { &*target::I2S::ptr() }
+    }
+}
+
+impl Drop for DacDma {
+    fn drop(&mut self) {
+        // the descriptors may form a ring, which I2S0 would follow forever after they are freed
+        if self.i2s.is_some() {
+            self.disable();
+        }
+    }
+}
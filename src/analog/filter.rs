@@ -0,0 +1,322 @@
+//! Oversampling and filtering of ADC readings.
+//!
+//! The functions in this module take multiple readings of a channel with any
+//! [OneShot][embedded_hal::adc::OneShot] implementation and combine them:
+//!
+//! - [average]: mean of N readings
+//! - [median]: median of N readings, robust against single outliers
+//! - [decimate]: sum of 4^n readings shifted right by n, which gains n bits of resolution if
+//!   the signal is noisy enough
+//!
+//! Each result also contains the minimum and maximum raw reading, which indicates the amount of
+//! noise.
+//!
+//! # Example
+//!
+//! ```
+//! let reading = filter::average(&mut adc1, &mut pin36, 16).unwrap().unwrap();
+//! writeln!(tx, "{} (spread {})", reading.value, reading.spread()).unwrap();
+//!
+//! // 14 bit value from a 12 bit ADC
+//! let reading = filter::decimate(&mut adc1, &mut pin36, 2).unwrap();
+//! ```
+
+use embedded_hal::adc::{Channel, OneShot};
+
+/// Filtered reading
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Reading {
+    /// Filtered value
+    pub value: u32,
+    /// Smallest raw reading
+    pub min: u16,
+    /// Largest raw reading
+    pub max: u16,
+}
+
+impl Reading {
+    /// Difference between the largest and the smallest raw reading
+    pub fn spread(&self) -> u16 {
+        self.max - self.min
+    }
+}
+
+/// Accumulator of raw readings
+#[derive(Clone, Copy, Debug)]
+struct Accumulator {
+    sum: u32,
+    count: u32,
+    min: u16,
+    max: u16,
+}
+
+impl Accumulator {
+    fn new() -> Self {
+        Accumulator {
+            sum: 0,
+            count: 0,
+            min: u16::MAX,
+            max: 0,
+        }
+    }
+
+    fn add(&mut self, value: u16) {
+        self.sum += value as u32;
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// Reading with the given value, or `None` if nothing was accumulated
+    fn reading(&self, value: u32) -> Option<Reading> {
+        if self.count == 0 {
+            return None;
+        }
+
+        Some(Reading {
+            value,
+            min: self.min,
+            max: self.max,
+        })
+    }
+
+    /// Rounded mean of the readings
+    fn mean(&self) -> u32 {
+        (self.sum + self.count / 2) / self.count.max(1)
+    }
+}
+
+/// Median of the values, the lower one of the two middle values for an even number
+///
+/// The values are sorted in place.
+pub fn median_of(values: &mut [u16]) -> Option<u16> {
+    if values.is_empty() {
+        return None;
+    }
+
+    values.sort_unstable();
+    Some(values[(values.len() - 1) / 2])
+}
+
+/// Rounded sum of the readings shifted right by `extra_bits`
+pub fn decimated_value(sum: u32, extra_bits: u8) -> u32 {
+    if extra_bits == 0 {
+        return sum;
+    }
+    (sum + (1 << (extra_bits - 1))) >> extra_bits
+}
+
+/// Mean of `samples` readings
+///
+/// Returns `None` if `samples` is 0. At most 65536 readings can be averaged.
+pub fn average<ADCX, PIN, ADC>(
+    adc: &mut ADC,
+    pin: &mut PIN,
+    samples: u32,
+) -> Result<Option<Reading>, ADC::Error>
+where
+    PIN: Channel<ADCX>,
+    ADC: OneShot<ADCX, u16, PIN>,
+{
+    let mut accumulator = Accumulator::new();
+    for _ in 0..samples.min(1 << 16) {
+        accumulator.add(nb::block!(adc.read(pin))?);
+    }
+
+    Ok(accumulator.reading(accumulator.mean()))
+}
+
+/// Median of as many readings as fit into the buffer
+///
+/// Returns `None` if the buffer is empty. The buffer contains the sorted readings afterwards.
+pub fn median<ADCX, PIN, ADC>(
+    adc: &mut ADC,
+    pin: &mut PIN,
+    buffer: &mut [u16],
+) -> Result<Option<Reading>, ADC::Error>
+where
+    PIN: Channel<ADCX>,
+    ADC: OneShot<ADCX, u16, PIN>,
+{
+    let mut accumulator = Accumulator::new();
+    for value in buffer.iter_mut() {
+        *value = nb::block!(adc.read(pin))?;
+        accumulator.add(*value);
+    }
+
+    Ok(median_of(buffer).and_then(|median| accumulator.reading(median as u32)))
+}
+
+/// Oversampling with decimation to gain `extra_bits` bits of resolution
+///
+/// Takes 4^`extra_bits` readings (up to 8 extra bits). The value has the resolution of the ADC
+/// plus `extra_bits` bits.
+pub fn decimate<ADCX, PIN, ADC>(
+    adc: &mut ADC,
+    pin: &mut PIN,
+    extra_bits: u8,
+) -> Result<Reading, ADC::Error>
+where
+    PIN: Channel<ADCX>,
+    ADC: OneShot<ADCX, u16, PIN>,
+{
+    let extra_bits = extra_bits.min(8);
+
+    let mut accumulator = Accumulator::new();
+    for _ in 0..1u32 << (2 * extra_bits) {
+        accumulator.add(nb::block!(adc.read(pin))?);
+    }
+
+    let value = decimated_value(accumulator.sum, extra_bits);
+    Ok(Reading {
+        value,
+        min: accumulator.min,
+        max: accumulator.max,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockAdc;
+
+    struct MockPin {
+        values: &'static [u16],
+        reads: usize,
+    }
+
+    impl MockPin {
+        fn new(values: &'static [u16]) -> Self {
+            MockPin { values, reads: 0 }
+        }
+    }
+
+    impl Channel<MockAdc> for MockPin {
+        type ID = u8;
+
+        fn channel() -> u8 {
+            0
+        }
+    }
+
+    impl OneShot<MockAdc, u16, MockPin> for MockAdc {
+        type Error = ();
+
+        /// Returns the values of the pin in a cycle, every other read is not ready yet
+        fn read(&mut self, pin: &mut MockPin) -> nb::Result<u16, ()> {
+            pin.reads += 1;
+            if pin.reads % 2 == 1 {
+                return Err(nb::Error::WouldBlock);
+            }
+            Ok(pin.values[(pin.reads / 2 - 1) % pin.values.len()])
+        }
+    }
+
+    #[test]
+    fn average_rounds() {
+        let mut pin = MockPin::new(&[100, 101, 101, 103]);
+
+        let reading = average(&mut MockAdc, &mut pin, 4).unwrap().unwrap();
+        assert_eq!(
+            reading,
+            Reading {
+                value: 101,
+                min: 100,
+                max: 103
+            }
+        );
+        assert_eq!(reading.spread(), 3);
+
+        let reading = average(&mut MockAdc, &mut pin, 2).unwrap().unwrap();
+        assert_eq!(reading.value, 101);
+    }
+
+    #[test]
+    fn average_zero_and_single_sample() {
+        let mut pin = MockPin::new(&[42]);
+
+        assert_eq!(average(&mut MockAdc, &mut pin, 0).unwrap(), None);
+        assert_eq!(pin.reads, 0);
+
+        let reading = average(&mut MockAdc, &mut pin, 1).unwrap().unwrap();
+        assert_eq!(
+            reading,
+            Reading {
+                value: 42,
+                min: 42,
+                max: 42
+            }
+        );
+    }
+
+    #[test]
+    fn average_sample_limit_without_overflow() {
+        let mut pin = MockPin::new(&[u16::MAX]);
+
+        let reading = average(&mut MockAdc, &mut pin, 100_000).unwrap().unwrap();
+        assert_eq!(reading.value, u16::MAX as u32);
+        // limited to 65536 readings
+        assert_eq!(pin.reads, 2 << 16);
+    }
+
+    #[test]
+    fn median_rejects_outliers() {
+        let mut pin = MockPin::new(&[100, 4095, 102, 0, 101]);
+        let mut buffer = [0; 5];
+
+        let reading = median(&mut MockAdc, &mut pin, &mut buffer)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            reading,
+            Reading {
+                value: 101,
+                min: 0,
+                max: 4095
+            }
+        );
+        assert_eq!(buffer, [0, 100, 101, 102, 4095]);
+    }
+
+    #[test]
+    fn median_empty_and_even_count() {
+        let mut pin = MockPin::new(&[7, 3, 5, 1]);
+
+        assert_eq!(median(&mut MockAdc, &mut pin, &mut []).unwrap(), None);
+
+        let mut buffer = [u16::MAX; 4];
+        let reading = median(&mut MockAdc, &mut pin, &mut buffer)
+            .unwrap()
+            .unwrap();
+        // lower of the two middle values
+        assert_eq!(reading.value, 3);
+        assert_eq!(buffer, [1, 3, 5, 7]);
+
+        assert_eq!(median_of(&mut []), None);
+        assert_eq!(median_of(&mut [9]), Some(9));
+    }
+
+    #[test]
+    fn decimate_gains_resolution() {
+        let mut pin = MockPin::new(&[100, 101, 101, 101]);
+
+        let reading = decimate(&mut MockAdc, &mut pin, 1).unwrap();
+        assert_eq!(reading.value, 202);
+        assert_eq!(pin.reads, 2 * 4);
+
+        assert_eq!(decimate(&mut MockAdc, &mut pin, 0).unwrap().value, 100);
+        assert_eq!(decimated_value(6, 2), 2);
+        assert_eq!(decimated_value(5, 2), 1);
+    }
+
+    #[test]
+    fn decimate_extra_bits_limit_without_overflow() {
+        let mut pin = MockPin::new(&[u16::MAX]);
+
+        // limited to 8 extra bits
+        let reading = decimate(&mut MockAdc, &mut pin, 10).unwrap();
+        assert_eq!(reading.value, (u16::MAX as u32) << 8);
+        assert_eq!(pin.reads, 2 << 16);
+    }
+}
@@ -5,9 +5,16 @@
 //!
 
 pub mod adc;
+pub mod calibration;
 pub mod config;
+#[cfg(feature = "alloc")]
+pub mod continuous;
 pub mod dac;
+#[cfg(feature = "alloc")]
+pub mod dac_dma;
+pub mod filter;
 pub mod hall;
+pub mod temperature;
 
 use crate::target::SENS;
 use core::marker::PhantomData;
@@ -27,11 +34,17 @@ pub struct DAC2 {
     _private: PhantomData<()>,
 }
 
+pub struct TEMPERATURE {
+    _private: PhantomData<()>,
+}
+
 pub struct AvailableAnalog {
     pub adc1: ADC1,
     pub adc2: ADC2,
     pub dac1: DAC1,
     pub dac2: DAC2,
+    pub temperature: TEMPERATURE,
+    pub touch: crate::touch::TOUCH,
 }
 
 pub trait SensExt {
@@ -53,6 +66,10 @@ impl SensExt for SENS {
             dac2: DAC2 {
                 _private: PhantomData,
             },
+            temperature: TEMPERATURE {
+                _private: PhantomData,
+            },
+            touch: crate::touch::TOUCH::new(),
         }
     }
 }
@@ -0,0 +1,123 @@
+//! Built-in temperature sensor readout.
+//!
+//! The internal temperature sensor measures the die temperature. The raw value roughly
+//! corresponds to degrees Fahrenheit, but the offset differs from chip to chip by up to several
+//! degrees, so the value is mainly useful to detect changes of the temperature, e.g. for thermal
+//! throttling via DFS.
+//!
+//! The sensor is part of the SAR ADC block, which is powered on when the sensor is created.
+//!
+//! # Example
+//!
+//! ```
+//! let mut sensor = TemperatureSensor::new(analog.temperature);
+//!
+//! let temperature = sensor.read().unwrap();
+//! writeln!(serial, "{} ({:.1}°C)", temperature.raw, temperature.celsius()).unwrap();
+//! ```
+
+use crate::analog::TEMPERATURE;
+use crate::target::SENS;
+
+/// Clock divider of the sensor
+const TSENS_CLK_DIV: u8 = 10;
+/// Maximum number of polls of the ready flag
+const TSENS_READY_POLLS: u32 = 100_000;
+
+/// Temperature sensor error
+#[derive(Debug)]
+pub enum Error {
+    /// The conversion did not complete
+    Timeout,
+}
+
+/// Temperature reading
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Temperature {
+    /// Raw sensor value
+    pub raw: u8,
+}
+
+impl Temperature {
+    /// Approximate temperature in degrees Celsius
+    pub fn celsius(&self) -> f32 {
+        raw_to_celsius(self.raw)
+    }
+}
+
+/// Convert a raw sensor value into approximate degrees Celsius
+pub fn raw_to_celsius(raw: u8) -> f32 {
+    (raw as f32 - 32.0) / 1.8
+}
+
+/// Built-in temperature sensor
+pub struct TemperatureSensor {
+    temperature: TEMPERATURE,
+}
+
+impl TemperatureSensor {
+    /// Power up the temperature sensor
+    pub fn new(temperature: TEMPERATURE) -> Self {
+        let sensors = unsafe { &*SENS::ptr() };
+
+        sensors
+            .sar_meas_wait2
+            .modify(|_, w| unsafe { w.force_xpd_sar().bits(0b11) });
+        sensors.sar_tsens_ctrl.modify(|_, w| unsafe {
+            w.tsens_clk_div()
+                .bits(TSENS_CLK_DIV)
+                .tsens_dump_out()
+                .clear_bit()
+                .tsens_power_up_force()
+                .set_bit()
+                .tsens_power_up()
+                .set_bit()
+        });
+
+        TemperatureSensor { temperature }
+    }
+
+    /// Trigger a conversion and return the result
+    ///
+    /// Returns [Error::Timeout] if the conversion does not complete.
+    pub fn read(&mut self) -> Result<Temperature, Error> {
+        let sensors = unsafe { &*SENS::ptr() };
+
+        sensors
+            .sar_tsens_ctrl
+            .modify(|_, w| w.tsens_dump_out().clear_bit());
+        sensors
+            .sar_tsens_ctrl
+            .modify(|_, w| w.tsens_dump_out().set_bit());
+
+        let ready = (0..TSENS_READY_POLLS)
+            .any(|_| sensors.sar_slave_addr3.read().tsens_rdy_out().bit_is_set());
+        let raw = sensors.sar_slave_addr3.read().tsens_out().bits();
+
+        sensors
+            .sar_tsens_ctrl
+            .modify(|_, w| w.tsens_dump_out().clear_bit());
+
+        if !ready {
+            return Err(Error::Timeout);
+        }
+
+        Ok(Temperature { raw })
+    }
+
+    /// Power down the temperature sensor and release it
+    ///
+    /// The SAR ADC stays powered, as it may be used by the ADC drivers.
+    pub fn release(self) -> TEMPERATURE {
+        let sensors = unsafe { &*SENS::ptr() };
+
+        sensors.sar_tsens_ctrl.modify(|_, w| {
+            w.tsens_power_up()
+                .clear_bit()
+                .tsens_power_up_force()
+                .clear_bit()
+        });
+
+        self.temperature
+    }
+}
@@ -0,0 +1,195 @@
+//! Brown-out detector
+//!
+//! The brown-out detector monitors the supply voltage. When it drops below the threshold, the
+//! detector either resets the chip or raises the brown-out interrupt, which allows to e.g. log
+//! the event and shut down cleanly.
+//!
+//! The brown-out interrupt is part of the `RTC_CORE_INTR` peripheral interrupt and is delivered
+//! via the [interrupt](crate::interrupt) module.
+//!
+//! # Example
+//!
+//! ```
+//! static BROWN_OUT: CriticalSectionSpinLockMutex<Option<BrownOut>> =
+//!     CriticalSectionSpinLockMutex::new(None);
+//!
+//! let mut brown_out = BrownOut::new(
+//!     Config::default()
+//!         .threshold(Threshold::V2p67)
+//!         .action(Action::Interrupt),
+//! );
+//! brown_out.listen();
+//! (&BROWN_OUT).lock(|data| *data = Some(brown_out));
+//!
+//! interrupt::enable(Interrupt::RTC_CORE_INTR).unwrap();
+//!
+//! #[interrupt]
+//! fn RTC_CORE_INTR() {
+//!     (&BROWN_OUT).lock(|data| {
+//!         let brown_out = data.as_mut().unwrap();
+//!         if brown_out.is_interrupt_set() {
+//!             // save state and shut down
+//!             brown_out.clear_interrupt();
+//!         }
+//!     });
+//! }
+//! ```
+
+use crate::target::RTCCNTL;
+
+/// Delay of the brown-out reset in cycles of the slow RTC clock, needs to be >1
+const BROWN_OUT_RST_WAIT: u16 = 2;
+
+/// Approximate voltage threshold
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Threshold {
+    V2p43 = 0,
+    V2p48 = 1,
+    V2p58 = 2,
+    V2p62 = 3,
+    V2p67 = 4,
+    V2p70 = 5,
+    V2p77 = 6,
+    V2p80 = 7,
+}
+
+/// Action when the voltage drops below the threshold
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Action {
+    /// Reset the chip
+    Reset,
+    /// Only raise the brown-out interrupt
+    Interrupt,
+}
+
+/// Brown-out detector configuration
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    pub threshold: Threshold,
+    pub action: Action,
+    /// Power down the RF circuits on brown-out
+    pub power_down_rf: bool,
+    /// Close the flash on brown-out to prevent corruption of the flash contents
+    pub close_flash: bool,
+}
+
+impl Config {
+    pub fn threshold(mut self, threshold: Threshold) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn action(mut self, action: Action) -> Self {
+        self.action = action;
+        self
+    }
+
+    pub fn power_down_rf(mut self, power_down_rf: bool) -> Self {
+        self.power_down_rf = power_down_rf;
+        self
+    }
+
+    pub fn close_flash(mut self, close_flash: bool) -> Self {
+        self.close_flash = close_flash;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            threshold: Threshold::V2p43,
+            action: Action::Reset,
+            power_down_rf: true,
+            close_flash: false,
+        }
+    }
+}
+
+/// Brown-out detector
+pub struct BrownOut {
+    config: Config,
+}
+
+impl BrownOut {
+    /// Configure and enable the brown-out detector
+    pub fn new(config: Config) -> Self {
+        let mut brown_out = BrownOut { config };
+        brown_out.set_config(config);
+        brown_out
+    }
+
+    /// Change the configuration
+    pub fn set_config(&mut self, config: Config) {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+
+        rtc_control.brown_out.write(|w| unsafe {
+            w.brown_out_ena()
+                .set_bit()
+                .dbrown_out_thres()
+                .bits(config.threshold as u8)
+                .brown_out_rst_ena()
+                .bit(config.action == Action::Reset)
+                .brown_out_rst_wait()
+                .bits(BROWN_OUT_RST_WAIT)
+                .brown_out_pd_rf_ena()
+                .bit(config.power_down_rf)
+                .brown_out_close_flash_ena()
+                .bit(config.close_flash)
+        });
+
+        self.config = config;
+    }
+
+    /// Returns the configuration
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Returns true if the voltage is below the threshold
+    pub fn is_detected(&self) -> bool {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+        rtc_control.brown_out.read().brown_out_det().bit_is_set()
+    }
+
+    /// Starts listening for the brown-out interrupt
+    ///
+    /// The interrupt needs to be routed via [interrupt::enable][crate::interrupt]
+    /// (`RTC_CORE_INTR`).
+    pub fn listen(&mut self) {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+        rtc_control
+            .int_ena
+            .modify(|_, w| w.brown_out_int_ena().set_bit());
+    }
+
+    /// Stop listening for the brown-out interrupt
+    pub fn unlisten(&mut self) {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+        rtc_control
+            .int_ena
+            .modify(|_, w| w.brown_out_int_ena().clear_bit());
+    }
+
+    /// Returns true if the brown-out interrupt is set
+    pub fn is_interrupt_set(&self) -> bool {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+        rtc_control.int_raw.read().brown_out_int_raw().bit_is_set()
+    }
+
+    /// Clear the brown-out interrupt
+    pub fn clear_interrupt(&mut self) {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+        rtc_control
+            .int_clr
+            .write(|w| w.brown_out_int_clr().set_bit());
+    }
+
+    /// Disable the brown-out detector
+    pub fn disable(mut self) {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+
+        self.unlisten();
+        rtc_control.brown_out.write(|w| unsafe { w.bits(0) });
+    }
+}
@@ -139,6 +139,22 @@ impl<'a> super::ClockControlConfig {
         unsafe { CLOCK_CONTROL.as_mut().unwrap().lock_plld2() }
     }
 
+    /// Enter light sleep if allowed
+    ///
+    /// To be called from the idle loop with the time until the next deadline (`None` if there is
+    /// none). Light sleep is entered if it is enabled, no awake, CPU or APB lock is held and the
+    /// time until the next deadline is at least the minimum light sleep time.
+    ///
+    /// Returns true if light sleep has been entered.
+    pub fn idle<T: Into<MicroSecondsU64>>(&self, time_until_deadline: Option<T>) -> bool {
+        unsafe {
+            CLOCK_CONTROL
+                .as_mut()
+                .unwrap()
+                .idle(time_until_deadline.map(|time| time.into()))
+        }
+    }
+
     /// Add callback which will be called when clock speeds are changed.
     ///
     /// NOTE: these callbacks are called in an interrupt free environment,
@@ -1,10 +1,12 @@
 //! Dynamic Frequency Switching control
 //!
-//! #TODO
-//! - Sleep functionality/Awake lock
+//! Also controls the automatic light sleep: when enabled, light sleep is entered from
+//! [ClockControlConfig::idle][super::ClockControlConfig::idle] if no awake, CPU or APB lock is
+//! held.
 
 use super::Error;
 use crate::prelude::*;
+use crate::sleep::{set_wakeup_time, RTC_GPIO_TRIG_EN, RTC_TIMER_TRIG_EN};
 
 /// maximum number of callbacks
 pub const MAX_CALLBACKS: usize = 10;
@@ -183,7 +185,6 @@ impl<'a> super::ClockControl {
             data.awake += 1;
         });
 
-        // TODO: implement actual locking
         LockAwake {}
     }
 
@@ -191,12 +192,122 @@ impl<'a> super::ClockControl {
     fn unlock_awake(&'a mut self) {
         (&DFS_MUTEX).lock(|data| {
             data.awake -= 1;
-
-            // TODO: implement actual unlocking
-            unimplemented!();
         });
     }
 
+    /// Set the CPU frequency according to the locks
+    fn set_cpu_frequency_from_locks(&mut self, data: &Locks) -> Result<&mut Self, Error> {
+        let keep_pll_enabled = data.pll_d2 > 0;
+
+        if data.cpu > 0
+            && (data.apb == 0 || self.cpu_frequency_locked > self.cpu_frequency_apb_locked)
+        {
+            self.set_cpu_frequency_locked(keep_pll_enabled)
+        } else if data.apb > 0 {
+            self.set_cpu_frequency_apb_locked(keep_pll_enabled)
+        } else {
+            self.set_cpu_frequency_default(keep_pll_enabled)
+        }
+    }
+
+    /// Enter light sleep if enabled, no awake, CPU or APB lock is held and the time until the
+    /// next deadline is long enough
+    ///
+    /// The CPU runs from the Xtal during the sleep. It wakes up by the RTC timer at the deadline
+    /// or by GPIOs configured via
+    /// [listen_with_options][crate::gpio::Pin::listen_with_options]. Afterwards the clocks are
+    /// restored and the callbacks called.
+    pub(crate) fn idle(&'a mut self, time_until_deadline: Option<MicroSecondsU64>) -> bool {
+        if !self.light_sleep_enabled {
+            return false;
+        }
+
+        let min_time: MicroSecondsU64 = self.light_sleep_min_time.into();
+        if let Some(time) = time_until_deadline {
+            if time < min_time {
+                return false;
+            }
+        }
+
+        (&DFS_MUTEX).lock(|data| {
+            // peripherals holding a CPU or APB lock rely on their clock
+            if data.awake > 0 || data.cpu > 0 || data.apb > 0 {
+                return false;
+            }
+
+            let mut wakeup_sources = RTC_GPIO_TRIG_EN;
+            if let Some(time) = time_until_deadline {
+                let ticks: TicksU64 = time * self.slow_rtc_frequency;
+                set_wakeup_time(&self.rtc_control, self.rtc_tick_count().0 + ticks.0);
+                wakeup_sources |= RTC_TIMER_TRIG_EN;
+            }
+
+            let xtal_frequency = self.xtal_frequency;
+            self.set_cpu_frequency(super::CPUSource::Xtal, xtal_frequency, false)
+                .unwrap();
+
+            // keep the digital domain and memories powered
+            self.rtc_control.dig_pwc.modify(|_, w| {
+                w.dg_wrap_pd_en()
+                    .clear_bit()
+                    .lslp_mem_force_pu()
+                    .clear_bit()
+            });
+            self.rtc_control.pwc.modify(|_, w| {
+                w.fastmem_pd_en()
+                    .clear_bit()
+                    .slowmem_pd_en()
+                    .clear_bit()
+                    .pd_en()
+                    .clear_bit()
+            });
+            // keep VDD_SDIO (flash) powered
+            self.rtc_control
+                .sdio_conf
+                .modify(|_, w| w.sdio_force().clear_bit().sdio_pd_en().clear_bit());
+
+            self.rtc_control
+                .wakeup_state
+                .modify(|_, w| unsafe { w.wakeup_ena().bits(wakeup_sources) });
+            // do not sleep when a GPIO wakeup is already pending
+            self.rtc_control
+                .slp_reject_conf
+                .write(|w| w.light_slp_reject_en().set_bit().gpio_reject_en().set_bit());
+            self.rtc_control.int_clr.write(|w| {
+                w.slp_reject_int_clr()
+                    .set_bit()
+                    .slp_wakeup_int_clr()
+                    .set_bit()
+            });
+
+            self.rtc_control
+                .state0
+                .modify(|_, w| w.sleep_en().set_bit());
+
+            loop {
+                let raw = self.rtc_control.int_raw.read();
+                if raw.slp_reject_int_raw().bit_is_set() || raw.slp_wakeup_int_raw().bit_is_set() {
+                    break;
+                }
+            }
+
+            self.rtc_control.int_clr.write(|w| {
+                w.slp_reject_int_clr()
+                    .set_bit()
+                    .slp_wakeup_int_clr()
+                    .set_bit()
+            });
+
+            self.set_cpu_frequency_from_locks(data).unwrap();
+            if data.pll_d2 > 0 && self.pll_frequency == super::FREQ_OFF {
+                self.pll_enable(false).unwrap();
+            }
+            self.do_callbacks();
+
+            true
+        })
+    }
+
     /// lock the PLL/2 frequency
     pub(crate) fn lock_plld2(&'a mut self) -> LockPllD2 {
         (&DFS_MUTEX).lock(|data| {
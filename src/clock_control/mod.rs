@@ -10,7 +10,6 @@
 //! - 8M and 8MD256 enable/disable
 //! - 150kHz enable/disable
 //! - APLL support
-//! - Implement light sleep
 //! - 32kHz Xtal support
 //! - Allow 8.5MHz clock to be tuned
 //! - Automatic enabling/disabling of 8MHz source (when not in use for rtc_fast_clk or cpu frequency)
@@ -25,6 +24,7 @@ use crate::target::{APB_CTRL, RTCCNTL, TIMG0};
 use core::fmt;
 use xtensa_lx6::timer::{delay, get_cycle_count};
 
+pub mod brownout;
 pub mod config;
 pub mod cpu;
 pub mod dfs;
@@ -45,6 +45,7 @@ const CPU_SOURCE_LOCKED_DEFAULT: CPUSource = CPUSource::PLL;
 const CPU_FREQ_MAX_DEFAULT: Hertz = Hertz(240_000_000);
 const CPU_SOURCE_APB_LOCKED_DEFAULT: CPUSource = CPUSource::PLL;
 const CPU_FREQ_APB_DEFAULT: Hertz = Hertz(80_000_000);
+const LIGHT_SLEEP_MIN_TIME_DEFAULT: MicroSeconds = MicroSeconds(1_000);
 
 /////////////////////////////////
 // Non-configurable constants
@@ -215,8 +216,8 @@ pub struct ClockControl {
     cpu_source_locked: CPUSource,
     cpu_frequency_apb_locked: Hertz,
     cpu_source_apb_locked: CPUSource,
-    #[allow(dead_code)]
     light_sleep_enabled: bool,
+    light_sleep_min_time: MicroSeconds,
 
     apb_frequency_apb_locked: Hertz,
 
@@ -274,6 +275,7 @@ impl ClockControl {
             cpu_frequency_apb_locked: CPU_FREQ_APB_DEFAULT,
             cpu_source_apb_locked: CPU_SOURCE_APB_LOCKED_DEFAULT,
             light_sleep_enabled: false,
+            light_sleep_min_time: LIGHT_SLEEP_MIN_TIME_DEFAULT,
 
             apb_frequency_apb_locked: APB_FREQ_PLL,
 
@@ -678,6 +680,20 @@ impl ClockControl {
         Ok(self)
     }
 
+    /// Enable or disable automatic light sleep
+    ///
+    /// When enabled, [ClockControlConfig::idle] enters light sleep if no awake, CPU or APB lock
+    /// is held and the time until the next deadline is at least `min_time`.
+    pub fn set_light_sleep<T: Into<MicroSeconds>>(
+        &mut self,
+        enable: bool,
+        min_time: T,
+    ) -> &mut Self {
+        self.light_sleep_enabled = enable;
+        self.light_sleep_min_time = min_time.into();
+        self
+    }
+
     fn check_ref_clock_stable<T: Into<Hertz>>(&self, source: CPUSource, frequency: T) -> bool {
         let f_hz = frequency.into();
         match source {
@@ -0,0 +1,308 @@
+//! DMA descriptor lists
+//!
+//! The SPI and I2S peripherals use linked lists of descriptors to transfer data via DMA.
+//! A [DmaBuffer] wraps a static buffer together with its descriptors, a [CircularBuffer] does
+//! the same for continuous transfers alternating between the two halves of the buffer. The
+//! descriptors are allocated from the DRAM heap ([DRAM_ALLOCATOR]), as the DMA engine can only
+//! access internal DRAM. For the same reason the data buffers must also be located in internal
+//! DRAM: buffers in IRAM, flash or external RAM are rejected.
+//!
+//! # Example
+//!
+//! ```
+//! #[repr(align(4))]
+//! struct Aligned([u8; 4096]);
+//!
+//! static mut BUFFER: Aligned = Aligned([0; 4096]);
+//!
+//! let buffer = DmaBuffer::new(unsafe { &mut BUFFER.0 }).unwrap();
+//! ```
+//!
+//! # TODO
+//! - Bounce buffers for buffers outside of DMA capable memory
+
+use crate::alloc::DRAM_ALLOCATOR;
+use core::alloc::{GlobalAlloc, Layout};
+
+/// Maximum number of bytes transferred by one descriptor (4095 rounded down to whole words)
+const MAX_DESCRIPTOR_LENGTH: usize = 4092;
+
+/// Start of the internal DRAM accessible by the DMA engine
+const DMA_CAPABLE_START: usize = 0x3FFA_E000;
+/// End of the internal DRAM accessible by the DMA engine
+const DMA_CAPABLE_END: usize = 0x4000_0000;
+
+/// DMA error
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Error {
+    /// Buffer is not located in DMA capable (internal DRAM) memory
+    BufferNotDmaCapable,
+    /// Buffer is not word aligned or its length is not a multiple of 4 bytes
+    BufferNotAligned,
+    /// No memory to allocate the descriptors
+    OutOfMemory,
+}
+
+/// Returns true if the complete buffer is located in DMA capable memory
+pub fn is_dma_capable(buffer: &[u8]) -> bool {
+    let start = buffer.as_ptr() as usize;
+    start >= DMA_CAPABLE_START && start + buffer.len() <= DMA_CAPABLE_END
+}
+
+/// Returns true if the buffer is word aligned and a whole number of words
+pub fn is_word_aligned(buffer: &[u8]) -> bool {
+    buffer.as_ptr() as usize % 4 == 0 && buffer.len() % 4 == 0
+}
+
+/// DMA descriptor as used by the DMA engine (lldesc)
+// the fields are only read by the DMA engine
+#[allow(dead_code)]
+#[repr(C)]
+struct Descriptor {
+    /// size (bits 0-11), length (bits 12-23), end of frame (bit 30), owned by DMA (bit 31)
+    flags: u32,
+    buffer: *const u8,
+    next: *const Descriptor,
+}
+
+impl Descriptor {
+    fn flags(size: usize, length: usize, eof: bool) -> u32 {
+        1 << 31 | (eof as u32) << 30 | (length as u32) << 12 | size as u32
+    }
+}
+
+/// Linked list of DMA descriptors in DRAM covering a buffer
+pub(crate) struct DescriptorList {
+    descriptors: *mut Descriptor,
+    count: usize,
+}
+
+unsafe impl Send for DescriptorList {}
+
+impl DescriptorList {
+    /// Create a descriptor list covering the complete buffer
+    ///
+    /// The last descriptor is marked as end of frame.
+    pub(crate) fn new(buffer: &[u8]) -> Result<Self, Error> {
+        if !is_dma_capable(buffer) {
+            return Err(Error::BufferNotDmaCapable);
+        }
+
+        let count = (buffer.len() + MAX_DESCRIPTOR_LENGTH - 1) / MAX_DESCRIPTOR_LENGTH;
+
+        if count == 0 {
+            return Ok(DescriptorList {
+                descriptors: core::ptr::null_mut(),
+                count,
+            });
+        }
+
+        let layout = Layout::array::<Descriptor>(count).map_err(|_| Error::OutOfMemory)?;
+        let descriptors = unsafe { DRAM_ALLOCATOR.alloc(layout) } as *mut Descriptor;
+        if descriptors.is_null() {
+            return Err(Error::OutOfMemory);
+        }
+
+        for (index, chunk) in buffer.chunks(MAX_DESCRIPTOR_LENGTH).enumerate() {
+            let last = index == count - 1;
+            let next = if last {
+                core::ptr::null_mut()
+            } else {
+                unsafe { descriptors.add(index + 1) }
+            };
+
+            unsafe {
+                descriptors.add(index).write_volatile(Descriptor {
+                    flags: Descriptor::flags(chunk.len(), chunk.len(), last),
+                    buffer: chunk.as_ptr(),
+                    next,
+                })
+            };
+        }
+
+        Ok(DescriptorList { descriptors, count })
+    }
+
+    /// Create a descriptor list covering the complete buffer, with the last descriptor linking
+    /// back to the first one
+    pub(crate) fn new_looped(buffer: &[u8]) -> Result<Self, Error> {
+        let list = DescriptorList::new(buffer)?;
+        if list.count > 0 {
+            unsafe {
+                let last = list.descriptors.add(list.count - 1);
+                core::ptr::write_volatile(&mut (*last).next, list.descriptors);
+            }
+        }
+        Ok(list)
+    }
+
+    /// Create a circular descriptor list covering the buffer in two halves
+    ///
+    /// The last descriptor of each half is marked as end of frame and the last descriptor links
+    /// back to the first one. The buffer length must be a multiple of 8 bytes.
+    pub(crate) fn new_circular(buffer: &[u8]) -> Result<Self, Error> {
+        if !is_dma_capable(buffer) {
+            return Err(Error::BufferNotDmaCapable);
+        }
+        if buffer.is_empty() || buffer.len() % 8 != 0 {
+            return Err(Error::BufferNotAligned);
+        }
+
+        let half = buffer.len() / 2;
+        let per_half = (half + MAX_DESCRIPTOR_LENGTH - 1) / MAX_DESCRIPTOR_LENGTH;
+        let count = 2 * per_half;
+
+        let layout = Layout::array::<Descriptor>(count).map_err(|_| Error::OutOfMemory)?;
+        let descriptors = unsafe { DRAM_ALLOCATOR.alloc(layout) } as *mut Descriptor;
+        if descriptors.is_null() {
+            return Err(Error::OutOfMemory);
+        }
+
+        let chunks = buffer[..half]
+            .chunks(MAX_DESCRIPTOR_LENGTH)
+            .chain(buffer[half..].chunks(MAX_DESCRIPTOR_LENGTH));
+        for (index, chunk) in chunks.enumerate() {
+            let eof = index % per_half == per_half - 1;
+            let next = unsafe { descriptors.add((index + 1) % count) };
+
+            unsafe {
+                descriptors.add(index).write_volatile(Descriptor {
+                    flags: Descriptor::flags(chunk.len(), chunk.len(), eof),
+                    buffer: chunk.as_ptr(),
+                    next,
+                })
+            };
+        }
+
+        Ok(DescriptorList { descriptors, count })
+    }
+
+    /// Returns the half (0 or 1) of a circular list containing the descriptor at the address
+    pub(crate) fn half_of(&self, address: u32) -> usize {
+        let index = (address as usize).wrapping_sub(self.descriptors as usize)
+            / core::mem::size_of::<Descriptor>();
+        if index < self.count / 2 {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Address of the first descriptor as needed by the link registers (lower 20 bits)
+    pub(crate) fn address(&self) -> u32 {
+        self.descriptors as u32 & 0xfffff
+    }
+}
+
+impl Drop for DescriptorList {
+    fn drop(&mut self) {
+        if self.count != 0 {
+            unsafe {
+                DRAM_ALLOCATOR.dealloc(
+                    self.descriptors as *mut u8,
+                    Layout::array::<Descriptor>(self.count).unwrap(),
+                )
+            };
+        }
+    }
+}
+
+/// Static buffer prepared for DMA transfers
+///
+/// The buffer needs to be located in internal DRAM, be word aligned and have a length which is a
+/// multiple of 4 bytes (as the receiving DMA engine writes whole words).
+pub struct DmaBuffer {
+    buffer: &'static mut [u8],
+    pub(crate) tx_descriptors: DescriptorList,
+    pub(crate) rx_descriptors: DescriptorList,
+}
+
+impl DmaBuffer {
+    /// Prepare a buffer for DMA transfers
+    pub fn new(buffer: &'static mut [u8]) -> Result<Self, Error> {
+        if !is_word_aligned(buffer) {
+            return Err(Error::BufferNotAligned);
+        }
+
+        Ok(DmaBuffer {
+            tx_descriptors: DescriptorList::new(buffer)?,
+            rx_descriptors: DescriptorList::new(buffer)?,
+            buffer,
+        })
+    }
+
+    /// Length of the buffer in bytes
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns true if the buffer is empty
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Release the buffer and free the descriptors
+    pub fn release(self) -> &'static mut [u8] {
+        self.buffer
+    }
+}
+
+impl core::ops::Deref for DmaBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.buffer
+    }
+}
+
+impl core::ops::DerefMut for DmaBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.buffer
+    }
+}
+
+/// Static buffer prepared for circular (double buffered) DMA transfers
+///
+/// The DMA engine continuously transfers the two halves of the buffer in turn, so one half can
+/// be processed while the other one is transferred. The buffer needs to be located in internal
+/// DRAM, be word aligned and have a length which is a multiple of 8 bytes.
+pub struct CircularBuffer {
+    buffer: &'static mut [u8],
+    pub(crate) descriptors: DescriptorList,
+}
+
+impl CircularBuffer {
+    /// Prepare a buffer for circular DMA transfers
+    pub fn new(buffer: &'static mut [u8]) -> Result<Self, Error> {
+        if !is_word_aligned(buffer) {
+            return Err(Error::BufferNotAligned);
+        }
+
+        Ok(CircularBuffer {
+            descriptors: DescriptorList::new_circular(buffer)?,
+            buffer,
+        })
+    }
+
+    /// Length of one half of the buffer in bytes
+    pub fn half_len(&self) -> usize {
+        self.buffer.len() / 2
+    }
+
+    /// One half (0 or 1) of the buffer
+    pub fn half(&self, half: usize) -> &[u8] {
+        let len = self.half_len();
+        &self.buffer[half * len..(half + 1) * len]
+    }
+
+    /// One half (0 or 1) of the buffer
+    pub fn half_mut(&mut self, half: usize) -> &mut [u8] {
+        let len = self.half_len();
+        &mut self.buffer[half * len..(half + 1) * len]
+    }
+
+    /// Release the buffer and free the descriptors
+    pub fn release(self) -> &'static mut [u8] {
+        self.buffer
+    }
+}
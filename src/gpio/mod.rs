@@ -232,6 +232,9 @@ pub trait RTCInputPin {
 
     /// Get state of RTC input
     fn rtc_is_input_high(&mut self) -> bool;
+
+    /// Get the RTC GPIO number of the pad
+    fn rtc_pin_number(&self) -> u8;
 }
 
 /// Functions available on RTC output pins
@@ -846,7 +849,8 @@ macro_rules! impl_output_wrap {
     };
 }
 
-static RTCIO_LOCK: CriticalSectionSpinLockMutex<()> = CriticalSectionSpinLockMutex::new(());
+pub(crate) static RTCIO_LOCK: CriticalSectionSpinLockMutex<()> =
+    CriticalSectionSpinLockMutex::new(());
 
 macro_rules! impl_no_rtc {
     ($pxi:ident, $pin_num:expr, $bank:ident, $iomux:ident, IO, RTC) => {
@@ -1087,6 +1091,10 @@ macro_rules! impl_analog {
                 fn rtc_is_input_high(&mut self) -> bool {
                     unsafe{&*RTCIO::ptr()}.in_.read().in_next().bits() & (1 << $pin_num) != 0
                 }
+
+                fn rtc_pin_number(&self) -> u8 {
+                    $pin_num
+                }
             }
 
             $(
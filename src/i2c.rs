@@ -0,0 +1,838 @@
+//! I2C peripheral control
+//!
+//! Controls the 2 I2C peripherals (I2C0, I2C1) in master ([I2c]) or slave ([I2cSlave]) mode.
+//!
+//! The I2C peripheral uses the APB clock. In master mode an APB lock is held during a
+//! transaction, so the timing of the bus is not affected by frequency changes. In slave mode the
+//! APB lock is held as long as the slave is active, as the master can start a transaction at any
+//! time.
+//!
+//! # Example
+//!
+//! Creation of the I2C peripheral and reading a register of a slave.
+//! ```
+//! let mut i2c = I2c::new(
+//!     dp.I2C0,
+//!     esp32_hal::i2c::Pins {
+//!         sda: pins.gpio21,
+//!         scl: pins.gpio22,
+//!     },
+//!     esp32_hal::i2c::config::Config::default(),
+//!     clkcntrl_config,
+//!     &mut dport,
+//! )
+//! .unwrap();
+//!
+//! let mut data = [0u8; 2];
+//! i2c.write_read(0x68, &[0x75], &mut data).unwrap();
+//! ```
+//!
+//! Creation of an I2C slave, which handles transactions in the interrupt handler.
+//! ```
+//! let mut slave = I2cSlave::new(
+//!     dp.I2C1,
+//!     esp32_hal::i2c::Pins {
+//!         sda: pins.gpio18,
+//!         scl: pins.gpio19,
+//!     },
+//!     esp32_hal::i2c::config::SlaveConfig::new(SlaveAddress::SevenBit(0x28)),
+//!     clkcntrl_config,
+//!     &mut dport,
+//! )
+//! .unwrap();
+//!
+//! slave.preload(&[0x01, 0x02]);
+//! slave.listen(Event::Received);
+//! slave.listen(Event::ReadRequest);
+//! slave.listen(Event::TransactionComplete);
+//! interrupt::enable(Interrupt::I2C_EXT1_INTR).unwrap();
+//! ```
+//!
+//! # TODO
+//! - Bus recovery after a time out or lost arbitration
+//! - Interrupt driven (non-blocking) transfers
+//! - 10-bit addressing in master mode
+
+use crate::gpio::{InputPin, OutputPin};
+use crate::prelude::*;
+use crate::target;
+
+use embedded_hal::blocking::i2c;
+
+const I2C_FIFO_SIZE: usize = 32;
+
+/// Maximum bus frequency
+const I2C_MAX_FREQUENCY: Hertz = Hertz(1_000_000);
+
+/// I2C error
+#[derive(Debug)]
+pub enum Error {
+    /// Slave did not acknowledge
+    Nack,
+    /// Arbitration lost to another master
+    ArbitrationLost,
+    /// Time out (the slave stretched the clock for too long)
+    TimeOut,
+    /// Time out too long for the time out register
+    TimeOutTooLong,
+    /// Bus frequency too low
+    FrequencyTooLow,
+    /// Bus frequency too high
+    FrequencyTooHigh,
+    /// Slave address out of range
+    InvalidAddress,
+    /// FIFO threshold out of range
+    InvalidThreshold,
+}
+
+/// Interrupt event in slave mode
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Event {
+    /// Number of received bytes in the RX FIFO reached the threshold
+    Received,
+    /// The master is reading and the number of bytes in the TX FIFO dropped to the threshold
+    ReadRequest,
+    /// Stop condition detected
+    TransactionComplete,
+}
+
+/// I2C configuration
+pub mod config {
+    use crate::units::*;
+
+    /// I2C configuration
+    #[derive(Debug, Copy, Clone)]
+    pub struct Config {
+        /// Bus (SCL) frequency
+        pub frequency: Hertz,
+        /// Maximum time between two SCL edges (limits clock stretching by the slave)
+        pub timeout: MicroSeconds,
+    }
+
+    impl Config {
+        pub fn frequency(mut self, frequency: Hertz) -> Self {
+            self.frequency = frequency;
+            self
+        }
+
+        pub fn timeout(mut self, timeout: MicroSeconds) -> Self {
+            self.timeout = timeout;
+            self
+        }
+    }
+
+    impl Default for Config {
+        fn default() -> Config {
+            Config {
+                frequency: Hertz(100_000),
+                timeout: MicroSeconds(10_000),
+            }
+        }
+    }
+
+    /// Own address in slave mode
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum SlaveAddress {
+        /// 7-bit address
+        SevenBit(u8),
+        /// 10-bit address
+        TenBit(u16),
+    }
+
+    /// I2C slave configuration
+    #[derive(Debug, Copy, Clone)]
+    pub struct SlaveConfig {
+        pub address: SlaveAddress,
+        /// Number of bytes in the RX FIFO to trigger the [Received][super::Event::Received] event
+        pub rx_threshold: u8,
+        /// Number of bytes in the TX FIFO to trigger the
+        /// [ReadRequest][super::Event::ReadRequest] event
+        pub tx_threshold: u8,
+    }
+
+    impl SlaveConfig {
+        pub fn new(address: SlaveAddress) -> Self {
+            SlaveConfig {
+                address,
+                rx_threshold: 28,
+                tx_threshold: 5,
+            }
+        }
+
+        pub fn address(mut self, address: SlaveAddress) -> Self {
+            self.address = address;
+            self
+        }
+
+        pub fn rx_threshold(mut self, rx_threshold: u8) -> Self {
+            self.rx_threshold = rx_threshold;
+            self
+        }
+
+        pub fn tx_threshold(mut self, tx_threshold: u8) -> Self {
+            self.tx_threshold = tx_threshold;
+            self
+        }
+    }
+}
+
+/// Pins used by the I2C interface
+///
+/// Note that any two pins may be used. Both pins are configured as open drain with internal
+/// pull up, for longer busses or higher frequencies external pull ups are still needed.
+pub struct Pins<SDA: OutputPin + InputPin, SCL: OutputPin + InputPin> {
+    pub sda: SDA,
+    pub scl: SCL,
+}
+
+/// Commands as executed by the command list of the I2C controller
+#[derive(Debug, Copy, Clone)]
+enum Command {
+    Start,
+    Stop,
+    End,
+    Write { length: u8 },
+    Read { length: u8, ack: bool },
+}
+
+impl From<Command> for u16 {
+    fn from(command: Command) -> u16 {
+        // op code (bits 11-13), ack value (bit 10), ack expected (bit 9), ack check enable (bit
+        // 8) and byte count (bits 0-7)
+        match command {
+            Command::Start => 0 << 11,
+            Command::Write { length } => 1 << 11 | 1 << 8 | length as u16,
+            Command::Read { length, ack } => 2 << 11 | (!ack as u16) << 10 | length as u16,
+            Command::Stop => 3 << 11,
+            Command::End => 4 << 11,
+        }
+    }
+}
+
+use private::Instance;
+
+/// I2C abstraction
+pub struct I2c<I2C: Instance, SDA: OutputPin + InputPin, SCL: OutputPin + InputPin> {
+    i2c: I2C,
+    pins: Pins<SDA, SCL>,
+    clock_control: crate::clock_control::ClockControlConfig,
+}
+
+impl<I2C: Instance, SDA: OutputPin + InputPin, SCL: OutputPin + InputPin> I2c<I2C, SDA, SCL> {
+    /// Create a new I2C driver in master mode
+    pub fn new(
+        i2c: I2C,
+        pins: Pins<SDA, SCL>,
+        config: config::Config,
+        clock_control: crate::clock_control::ClockControlConfig,
+        dport: &mut target::DPORT,
+    ) -> Result<Self, Error> {
+        let mut i2c = I2c {
+            i2c,
+            pins,
+            clock_control,
+        };
+
+        i2c.i2c.init_pins(&mut i2c.pins);
+        i2c.i2c.reset(dport).enable(dport);
+
+        i2c.i2c.ctr.write(|w| {
+            w.ms_mode()
+                .set_bit()
+                .sda_force_out()
+                .set_bit()
+                .scl_force_out()
+                .set_bit()
+                .clk_en()
+                .set_bit()
+        });
+
+        i2c.i2c.init_fifo_and_filters();
+
+        i2c.change_frequency(config.frequency)?
+            .change_timeout(config.timeout)?;
+
+        Ok(i2c)
+    }
+
+    /// Change the bus frequency
+    pub fn change_frequency<T: Into<Hertz> + Copy>(
+        &mut self,
+        frequency: T,
+    ) -> Result<&mut Self, Error> {
+        if frequency.into() > I2C_MAX_FREQUENCY {
+            return Err(Error::FrequencyTooHigh);
+        }
+        if frequency.into().0 == 0 {
+            return Err(Error::FrequencyTooLow);
+        }
+
+        // the bus is only active while the APB lock is held
+        let half_cycle = self.clock_control.apb_frequency_apb_locked() / frequency.into() / 2;
+
+        if half_cycle > (1 << 14) - 1 {
+            return Err(Error::FrequencyTooLow);
+        }
+
+        // the setup, hold and sample registers are only 10 bits wide
+        let setup_hold = core::cmp::min(half_cycle, (1 << 10) - 1);
+
+        unsafe {
+            self.i2c
+                .scl_low_period
+                .write(|w| w.period().bits(half_cycle as u16));
+            self.i2c
+                .scl_high_period
+                .write(|w| w.period().bits(half_cycle as u16));
+
+            self.i2c
+                .sda_hold
+                .write(|w| w.time().bits(setup_hold as u16 / 2));
+            self.i2c
+                .sda_sample
+                .write(|w| w.time().bits(setup_hold as u16 / 2));
+
+            self.i2c
+                .scl_start_hold
+                .write(|w| w.time().bits(setup_hold as u16));
+            self.i2c
+                .scl_rstart_setup
+                .write(|w| w.time().bits(setup_hold as u16));
+            self.i2c
+                .scl_stop_hold
+                .write(|w| w.time().bits(half_cycle as u16));
+            self.i2c
+                .scl_stop_setup
+                .write(|w| w.time().bits(setup_hold as u16));
+        }
+
+        Ok(self)
+    }
+
+    /// Returns the current bus frequency
+    pub fn frequency(&self) -> Hertz {
+        let period = self.i2c.scl_low_period.read().period().bits() as u32
+            + self.i2c.scl_high_period.read().period().bits() as u32;
+
+        self.clock_control.apb_frequency_apb_locked() / period
+    }
+
+    /// Change the maximum time between two SCL edges
+    ///
+    /// This limits the time a slave may stretch the clock.
+    pub fn change_timeout<T: Into<MicroSeconds> + Copy>(
+        &mut self,
+        timeout: T,
+    ) -> Result<&mut Self, Error> {
+        let ticks = self.clock_control.apb_frequency_apb_locked() * timeout.into();
+
+        if ticks.0 > (1 << 20) - 1 {
+            return Err(Error::TimeOutTooLong);
+        }
+
+        unsafe { self.i2c.to.write(|w| w.time_out_reg().bits(ticks.0)) };
+
+        Ok(self)
+    }
+
+    /// Return true if the bus is busy
+    pub fn is_bus_busy(&self) -> bool {
+        self.i2c.sr.read().bus_busy().bit_is_set()
+    }
+
+    /// Release the I2C and GPIO resources
+    pub fn release(self) -> (I2C, Pins<SDA, SCL>) {
+        (self.i2c, self.pins)
+    }
+
+    /// Add a command to the command list
+    fn add_command(&self, index: &mut usize, command: Command) {
+        // the 16 command registers are consecutive, but of different types in the PAC
+        unsafe {
+            let comd0 = &self.i2c.comd0 as *const _ as *mut u32;
+            core::ptr::write_volatile(comd0.add(*index), u16::from(command) as u32)
+        };
+        *index += 1;
+    }
+
+    /// Execute the command list and wait for completion
+    ///
+    /// `end` indicates whether the command list is terminated with an end command (more commands
+    /// follow) or a stop command.
+    fn execute(&self, index: &mut usize, end: bool) -> Result<(), Error> {
+        self.i2c.ctr.modify(|_, w| w.trans_start().set_bit());
+
+        let result = loop {
+            let int_raw = self.i2c.int_raw.read();
+
+            if int_raw.time_out_int_raw().bit_is_set() {
+                break Err(Error::TimeOut);
+            }
+            if int_raw.ack_err_int_raw().bit_is_set() {
+                break Err(Error::Nack);
+            }
+            if int_raw.arbitration_lost_int_raw().bit_is_set() {
+                break Err(Error::ArbitrationLost);
+            }
+            if (end && int_raw.end_detect_int_raw().bit_is_set())
+                || (!end && int_raw.trans_complete_int_raw().bit_is_set())
+            {
+                break Ok(());
+            }
+        };
+
+        if result.is_err() {
+            self.i2c.reset_fifo();
+        }
+        self.i2c.clear_interrupts();
+        *index = 0;
+
+        result
+    }
+
+    /// Queue the address and data writes
+    ///
+    /// Data not fitting in the FIFO is sent in chunks. The command list of the last chunk is
+    /// left to be completed and executed by the caller.
+    fn write_bytes(&self, index: &mut usize, address: u8, bytes: &[u8]) -> Result<(), Error> {
+        let mut data = core::iter::once(address)
+            .chain(bytes.iter().cloned())
+            .peekable();
+
+        loop {
+            let length = data.by_ref().take(I2C_FIFO_SIZE).fold(0, |length, byte| {
+                self.i2c.write_fifo(byte);
+                length + 1
+            });
+
+            self.add_command(index, Command::Write { length });
+
+            if data.peek().is_none() {
+                return Ok(());
+            }
+
+            self.add_command(index, Command::End);
+            self.execute(index, true)?;
+        }
+    }
+
+    /// Queue and execute the reads, terminating the transaction with a stop
+    fn read_bytes(&self, index: &mut usize, buffer: &mut [u8]) -> Result<(), Error> {
+        if buffer.is_empty() {
+            self.add_command(index, Command::Stop);
+            return self.execute(index, false);
+        }
+
+        let chunks = (buffer.len() + I2C_FIFO_SIZE - 1) / I2C_FIFO_SIZE;
+
+        for (chunk_index, chunk) in buffer.chunks_mut(I2C_FIFO_SIZE).enumerate() {
+            if chunk_index == chunks - 1 {
+                // last byte of the transaction is not acknowledged
+                if chunk.len() > 1 {
+                    self.add_command(
+                        index,
+                        Command::Read {
+                            length: chunk.len() as u8 - 1,
+                            ack: true,
+                        },
+                    );
+                }
+                self.add_command(
+                    index,
+                    Command::Read {
+                        length: 1,
+                        ack: false,
+                    },
+                );
+                self.add_command(index, Command::Stop);
+                self.execute(index, false)?;
+            } else {
+                self.add_command(
+                    index,
+                    Command::Read {
+                        length: chunk.len() as u8,
+                        ack: true,
+                    },
+                );
+                self.add_command(index, Command::End);
+                self.execute(index, true)?;
+            }
+
+            for byte in chunk.iter_mut() {
+                *byte = self.i2c.read_fifo();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prepare for a new transaction
+    fn start(&self, index: &mut usize) {
+        self.i2c.reset_fifo();
+        self.i2c.clear_interrupts();
+        self.add_command(index, Command::Start);
+    }
+}
+
+impl<I2C: Instance, SDA: OutputPin + InputPin, SCL: OutputPin + InputPin> i2c::Write
+    for I2c<I2C, SDA, SCL>
+{
+    type Error = Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        let _apb_lock = self.clock_control.lock_apb_frequency();
+        let mut index = 0;
+
+        self.start(&mut index);
+        self.write_bytes(&mut index, address_byte(address, false)?, bytes)?;
+        self.add_command(&mut index, Command::Stop);
+        self.execute(&mut index, false)
+    }
+}
+
+impl<I2C: Instance, SDA: OutputPin + InputPin, SCL: OutputPin + InputPin> i2c::Read
+    for I2c<I2C, SDA, SCL>
+{
+    type Error = Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let _apb_lock = self.clock_control.lock_apb_frequency();
+        let mut index = 0;
+
+        self.start(&mut index);
+        self.write_bytes(&mut index, address_byte(address, true)?, &[])?;
+        self.read_bytes(&mut index, buffer)
+    }
+}
+
+impl<I2C: Instance, SDA: OutputPin + InputPin, SCL: OutputPin + InputPin> i2c::WriteRead
+    for I2c<I2C, SDA, SCL>
+{
+    type Error = Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let _apb_lock = self.clock_control.lock_apb_frequency();
+        let mut index = 0;
+
+        self.start(&mut index);
+        self.write_bytes(&mut index, address_byte(address, false)?, bytes)?;
+        self.add_command(&mut index, Command::End);
+        self.execute(&mut index, true)?;
+
+        // repeated start
+        self.add_command(&mut index, Command::Start);
+        self.write_bytes(&mut index, address_byte(address, true)?, &[])?;
+        self.read_bytes(&mut index, buffer)
+    }
+}
+
+/// First byte of a transaction with the 7-bit address and the read/write bit
+fn address_byte(address: u8, read: bool) -> Result<u8, Error> {
+    if address > 0x7f {
+        return Err(Error::InvalidAddress);
+    }
+    Ok(address << 1 | read as u8)
+}
+
+/// I2C slave abstraction
+pub struct I2cSlave<I2C: Instance, SDA: OutputPin + InputPin, SCL: OutputPin + InputPin> {
+    i2c: I2C,
+    pins: Pins<SDA, SCL>,
+    _apb_lock: crate::clock_control::dfs::LockAPB,
+}
+
+impl<I2C: Instance, SDA: OutputPin + InputPin, SCL: OutputPin + InputPin> I2cSlave<I2C, SDA, SCL> {
+    /// Create a new I2C driver in slave mode
+    pub fn new(
+        i2c: I2C,
+        pins: Pins<SDA, SCL>,
+        config: config::SlaveConfig,
+        clock_control: crate::clock_control::ClockControlConfig,
+        dport: &mut target::DPORT,
+    ) -> Result<Self, Error> {
+        if config.rx_threshold as usize >= I2C_FIFO_SIZE
+            || config.tx_threshold as usize >= I2C_FIFO_SIZE
+        {
+            return Err(Error::InvalidThreshold);
+        }
+
+        let mut slave = I2cSlave {
+            i2c,
+            pins,
+            _apb_lock: clock_control.lock_apb_frequency(),
+        };
+
+        slave.i2c.init_pins(&mut slave.pins);
+        slave.i2c.reset(dport).enable(dport);
+
+        slave.i2c.ctr.write(|w| {
+            w.ms_mode()
+                .clear_bit()
+                .sda_force_out()
+                .set_bit()
+                .scl_force_out()
+                .set_bit()
+                .clk_en()
+                .set_bit()
+        });
+
+        slave.i2c.init_fifo_and_filters();
+
+        unsafe {
+            slave.i2c.fifo_conf.modify(|_, w| {
+                w.rxfifo_full_thrhd()
+                    .bits(config.rx_threshold)
+                    .txfifo_empty_thrhd()
+                    .bits(config.tx_threshold)
+            });
+
+            slave.i2c.sda_hold.write(|w| w.time().bits(10));
+            slave.i2c.sda_sample.write(|w| w.time().bits(10));
+            slave.i2c.to.write(|w| w.time_out_reg().bits((1 << 20) - 1));
+        }
+
+        slave.change_address(config.address)?;
+
+        Ok(slave)
+    }
+
+    /// Change the own address of the slave
+    pub fn change_address(&mut self, address: config::SlaveAddress) -> Result<&mut Self, Error> {
+        let (address, ten_bit) = match address {
+            config::SlaveAddress::SevenBit(address) if address < 1 << 7 => (address as u16, false),
+            config::SlaveAddress::TenBit(address) if address < 1 << 10 => (address, true),
+            _ => return Err(Error::InvalidAddress),
+        };
+
+        self.i2c
+            .slave_addr
+            .write(|w| unsafe { w.slave_addr().bits(address).addr_10bit_en().bit(ten_bit) });
+
+        Ok(self)
+    }
+
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: Event) {
+        self.enable_interrupt(event, true);
+    }
+
+    /// Stop listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        self.enable_interrupt(event, false);
+    }
+
+    /// Returns true if the interrupt for the event is set
+    pub fn is_interrupt_set(&self, event: Event) -> bool {
+        let int_status = self.i2c.int_status.read();
+        match event {
+            Event::Received => int_status.rxfifo_full_int_st().bit_is_set(),
+            Event::ReadRequest => int_status.txfifo_empty_int_st().bit_is_set(),
+            Event::TransactionComplete => int_status.trans_complete_int_st().bit_is_set(),
+        }
+    }
+
+    /// Clear the interrupt for the event
+    ///
+    /// *Note: the [Received][Event::Received] and [ReadRequest][Event::ReadRequest] interrupts
+    /// will fire again as long as the FIFO level is beyond the threshold.*
+    pub fn clear_interrupt(&mut self, event: Event) {
+        self.i2c.int_clr.write(|w| match event {
+            Event::Received => w.rxfifo_full_int_clr().set_bit(),
+            Event::ReadRequest => w.txfifo_empty_int_clr().set_bit(),
+            Event::TransactionComplete => w.trans_complete_int_clr().set_bit(),
+        });
+    }
+
+    /// Returns true if the slave is addressed by the master for reading
+    pub fn is_read_request(&self) -> bool {
+        let sr = self.i2c.sr.read();
+        sr.slave_addressed().bit_is_set() && sr.slave_rw().bit_is_set()
+    }
+
+    /// Get count of bytes in the receive FIFO
+    pub fn rx_count(&self) -> u8 {
+        self.i2c.sr.read().rxfifo_cnt().bits()
+    }
+
+    /// Get count of bytes in the transmit FIFO
+    pub fn tx_count(&self) -> u8 {
+        self.i2c.sr.read().txfifo_cnt().bits()
+    }
+
+    /// Read the received bytes into the buffer
+    ///
+    /// Returns the number of bytes read.
+    pub fn read(&mut self, buffer: &mut [u8]) -> usize {
+        let count = core::cmp::min(self.rx_count() as usize, buffer.len());
+        for byte in buffer[..count].iter_mut() {
+            *byte = self.i2c.read_fifo();
+        }
+        count
+    }
+
+    /// Preload bytes into the TX FIFO to be read by the master
+    ///
+    /// Returns the number of bytes written, which is less than the length of `bytes` when the
+    /// FIFO is full.
+    pub fn preload(&mut self, bytes: &[u8]) -> usize {
+        let count = core::cmp::min(I2C_FIFO_SIZE - self.tx_count() as usize, bytes.len());
+        for byte in bytes[..count].iter() {
+            self.i2c.write_fifo(*byte);
+        }
+        count
+    }
+
+    /// Discard all bytes in the RX and TX FIFO
+    pub fn reset_fifo(&mut self) {
+        self.i2c.reset_fifo();
+    }
+
+    /// Release the I2C and GPIO resources
+    pub fn release(self) -> (I2C, Pins<SDA, SCL>) {
+        (self.i2c, self.pins)
+    }
+
+    fn enable_interrupt(&mut self, event: Event, enable: bool) {
+        self.i2c.int_ena.modify(|_, w| match event {
+            Event::Received => w.rxfifo_full_int_ena().bit(enable),
+            Event::ReadRequest => w.txfifo_empty_int_ena().bit(enable),
+            Event::TransactionComplete => w.trans_complete_int_ena().bit(enable),
+        });
+    }
+}
+
+mod private {
+    use super::Pins;
+    use crate::gpio::{InputPin, InputSignal, OutputPin, OutputSignal};
+    use crate::target::{self, i2c, I2C0, I2C1};
+    use core::ops::Deref;
+
+    pub trait Instance: Deref<Target = i2c::RegisterBlock> {
+        fn ptr() -> *const i2c::RegisterBlock;
+        /// Pointer to the FIFO via the AHB bus
+        fn fifo_ptr() -> *mut u32;
+        /// Enable peripheral
+        fn enable(&mut self, dport: &mut target::DPORT) -> &mut Self;
+        /// Disable peripheral
+        fn disable(&mut self, dport: &mut target::DPORT) -> &mut Self;
+        /// Reset peripheral
+        fn reset(&mut self, dport: &mut target::DPORT) -> &mut Self;
+
+        /// Initialize pins
+        fn init_pins<SDA: OutputPin + InputPin, SCL: OutputPin + InputPin>(
+            &mut self,
+            pins: &mut Pins<SDA, SCL>,
+        ) -> &mut Self;
+
+        /// Configure FIFO mode and glitch filters, disable and clear all interrupts
+        fn init_fifo_and_filters(&self) {
+            // use the FIFO instead of direct memory access
+            self.fifo_conf
+                .modify(|_, w| w.nonfifo_en().clear_bit().fifo_addr_cfg_en().clear_bit());
+
+            // filter out glitches shorter than 7 APB cycles
+            unsafe {
+                self.scl_filter_cfg
+                    .write(|w| w.scl_filter_en().set_bit().scl_filter_thres().bits(7));
+                self.sda_filter_cfg
+                    .write(|w| w.sda_filter_en().set_bit().sda_filter_thres().bits(7));
+            }
+
+            self.int_ena.write(|w| unsafe { w.bits(0) });
+            self.reset_fifo();
+            self.clear_interrupts();
+        }
+
+        /// Write a byte into the TX FIFO
+        fn write_fifo(&self, byte: u8) {
+            // work around for errata 3.20: the FIFO needs to be written via the AHB address
+            unsafe { core::ptr::write_volatile(Self::fifo_ptr(), byte as u32) };
+        }
+
+        /// Read a byte from the RX FIFO
+        fn read_fifo(&self) -> u8 {
+            self.data.read().fifo_rdata().bits()
+        }
+
+        /// Reset the RX and TX FIFO
+        fn reset_fifo(&self) {
+            self.fifo_conf
+                .modify(|_, w| w.tx_fifo_rst().set_bit().rx_fifo_rst().set_bit());
+            self.fifo_conf
+                .modify(|_, w| w.tx_fifo_rst().clear_bit().rx_fifo_rst().clear_bit());
+        }
+
+        /// Clear all interrupts
+        fn clear_interrupts(&self) {
+            self.int_clr.write(|w| unsafe { w.bits(0x1fff) });
+        }
+    }
+
+    macro_rules! halI2c {
+        ($(
+            $I2CX:ident: ($i2cX:ident, $fifo:expr, $sda:ident, $scl:ident),
+        )+) => {
+            $(
+                impl Instance for $I2CX {
+                    fn ptr() -> *const i2c::RegisterBlock {
+                        $I2CX::ptr()
+                    }
+
+                    fn fifo_ptr() -> *mut u32 {
+                        $fifo as *mut u32
+                    }
+
+                    fn reset(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                        dport.perip_rst_en.modify(|_, w| w.$i2cX().set_bit());
+                        dport.perip_rst_en.modify(|_, w| w.$i2cX().clear_bit());
+                        self
+                    }
+
+                    fn enable(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                        dport.perip_clk_en.modify(|_, w| w.$i2cX().set_bit());
+                        dport.perip_rst_en.modify(|_, w| w.$i2cX().clear_bit());
+                        self
+                    }
+
+                    fn disable(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                        dport.perip_clk_en.modify(|_, w| w.$i2cX().clear_bit());
+                        dport.perip_rst_en.modify(|_, w| w.$i2cX().set_bit());
+                        self
+                    }
+
+                    fn init_pins<SDA: OutputPin + InputPin, SCL: OutputPin + InputPin>(
+                        &mut self, pins: &mut Pins<SDA, SCL>
+                    ) -> &mut Self {
+                        pins
+                            .sda
+                            .set_to_open_drain_output()
+                            .internal_pull_up(true)
+                            .enable_input(true)
+                            .connect_peripheral_to_output(OutputSignal::$sda)
+                            .connect_input_to_peripheral(InputSignal::$sda);
+
+                        pins
+                            .scl
+                            .set_to_open_drain_output()
+                            .internal_pull_up(true)
+                            .enable_input(true)
+                            .connect_peripheral_to_output(OutputSignal::$scl)
+                            .connect_input_to_peripheral(InputSignal::$scl);
+
+                        self
+                    }
+                }
+            )+
+        }
+    }
+
+    halI2c! {
+        I2C0: (i2c0, 0x6001_301c, I2CEXT0_SDA, I2CEXT0_SCL),
+        I2C1: (i2c1, 0x6002_701c, I2CEXT1_SDA, I2CEXT1_SCL),
+    }
+}
@@ -0,0 +1,893 @@
+//! I2S peripheral control
+//!
+//! Controls the 2 I2S peripherals (I2S0, I2S1) in master or slave mode for audio input and
+//! output. The data is streamed via circular DMA: while one half of a
+//! [CircularBuffer][crate::dma::CircularBuffer] is transferred, the other half can be processed
+//! (requires the `alloc` feature for the DMA descriptors).
+//!
+//! Samples with up to 16 bits occupy 16-bit words in memory, samples with more bits 32-bit
+//! words. The samples of the left and right channel are interleaved, starting with the left
+//! channel.
+//!
+//! In master mode the clock is derived from the 160MHz PLL_D2 clock (a PLL lock is held while
+//! the driver exists) or the audio PLL. The audio PLL must be running already, as it is not
+//! configured by this driver.
+//!
+//! The PAC only contains I2S0, so for I2S1 an [I2S1] instance is provided by this module.
+//!
+//! # Example
+//!
+//! Streaming samples to an I2S amplifier.
+//! ```
+//! #[repr(align(4))]
+//! struct Aligned([u8; 4096]);
+//!
+//! static mut BUFFER: Aligned = Aligned([0; 4096]);
+//!
+//! let i2s = I2s::new(
+//!     dp.I2S,
+//!     esp32_hal::i2s::Pins {
+//!         bck: pins.gpio26,
+//!         ws: pins.gpio25,
+//!         data_out: Some(pins.gpio22),
+//!         data_in: None,
+//!     },
+//!     esp32_hal::i2s::config::Config::default(),
+//!     clkcntrl_config,
+//!     &mut dport,
+//! )
+//! .unwrap();
+//!
+//! let buffer = CircularBuffer::new(unsafe { &mut BUFFER.0 }).unwrap();
+//! let mut stream = i2s.transmit(buffer);
+//!
+//! loop {
+//!     stream.process(|half, data| fill_with_samples(data));
+//! }
+//! ```
+//!
+//! # TODO
+//! - Simultaneous transmission and reception
+//! - Master clock output
+//! - Mono and PDM modes
+//! - Configuration of the audio PLL
+
+use core::marker::PhantomData;
+
+use crate::dma::CircularBuffer;
+use crate::gpio::{InputPin, InputSignal, OutputPin, OutputSignal};
+use crate::prelude::*;
+use crate::target;
+
+/// Maximum integer clock divider
+const I2S_MAX_CLOCK_DIVIDER: u32 = 255;
+/// Minimum integer clock divider
+const I2S_MIN_CLOCK_DIVIDER: u32 = 2;
+/// Maximum bit clock divider
+const I2S_MAX_BCK_DIVIDER: u32 = 63;
+/// Minimum bit clock divider
+const I2S_MIN_BCK_DIVIDER: u32 = 2;
+/// Maximum denominator of the fractional clock divider
+const I2S_MAX_FRACTIONAL_DENOMINATOR: u32 = 63;
+
+/// I2S error
+#[derive(Debug)]
+pub enum Error {
+    /// Sample rate too high for the clock source
+    SampleRateTooHigh,
+    /// Sample rate too low for the clock source
+    SampleRateTooLow,
+    /// Only 8, 16, 24 and 32 bits per sample are supported
+    InvalidBitsPerSample,
+    /// The audio PLL is not running
+    ApllNotRunning,
+}
+
+/// I2S interrupt event
+pub enum Event {
+    /// One half of the DMA buffer is transferred
+    HalfDone,
+}
+
+/// I2S configuration
+pub mod config {
+    use crate::units::*;
+
+    /// Master or slave mode
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum Mode {
+        /// Bit clock and word select are generated
+        Master,
+        /// Bit clock and word select are inputs
+        Slave,
+    }
+
+    /// Frame format
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum Format {
+        /// Standard Philips format, data delayed by one bit clock after word select
+        Philips,
+        /// MSB (left justified) format, data aligned with word select
+        MSB,
+        /// PCM format with a word select pulse of one bit clock
+        PCMShort,
+        /// PCM format with a word select pulse of one channel length
+        PCMLong,
+    }
+
+    /// Clock source in master mode
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum ClockSource {
+        /// 160MHz PLL_D2 clock
+        PllD2,
+        /// Audio PLL
+        Apll,
+    }
+
+    /// I2S configuration
+    #[derive(Copy, Clone, Debug)]
+    pub struct Config {
+        pub mode: Mode,
+        pub format: Format,
+        /// Bits per sample (8, 16, 24 or 32)
+        pub bits_per_sample: u8,
+        /// Sample rate (in master mode)
+        pub sample_rate: Hertz,
+        /// Clock source (in master mode)
+        pub clock_source: ClockSource,
+    }
+
+    impl Config {
+        pub fn mode(mut self, mode: Mode) -> Self {
+            self.mode = mode;
+            self
+        }
+
+        pub fn format(mut self, format: Format) -> Self {
+            self.format = format;
+            self
+        }
+
+        pub fn bits_per_sample(mut self, bits_per_sample: u8) -> Self {
+            self.bits_per_sample = bits_per_sample;
+            self
+        }
+
+        pub fn sample_rate(mut self, sample_rate: Hertz) -> Self {
+            self.sample_rate = sample_rate;
+            self
+        }
+
+        pub fn clock_source(mut self, clock_source: ClockSource) -> Self {
+            self.clock_source = clock_source;
+            self
+        }
+    }
+
+    impl Default for Config {
+        fn default() -> Config {
+            Config {
+                mode: Mode::Master,
+                format: Format::Philips,
+                bits_per_sample: 16,
+                sample_rate: Hertz(44_100),
+                clock_source: ClockSource::PllD2,
+            }
+        }
+    }
+}
+
+use config::{ClockSource, Config, Format, Mode};
+
+/// Pins used by the I2S peripheral
+///
+/// The bit clock and word select pins are outputs in master mode and inputs in slave mode.
+pub struct Pins<
+    BCK: InputPin + OutputPin,
+    WS: InputPin + OutputPin,
+    // default pins to allow type inference
+    DOUT: OutputPin = crate::gpio::Gpio22<crate::gpio::Output<crate::gpio::PushPull>>,
+    DIN: InputPin = crate::gpio::Gpio19<crate::gpio::Input<crate::gpio::Floating>>,
+> {
+    pub bck: BCK,
+    pub ws: WS,
+    pub data_out: Option<DOUT>,
+    pub data_in: Option<DIN>,
+}
+
+/// I2S1 peripheral
+///
+/// The PAC does not contain the I2S1 peripheral, which has the same registers as I2S0.
+pub struct I2S1 {
+    _marker: PhantomData<*const ()>,
+}
+
+unsafe impl Send for I2S1 {}
+
+impl I2S1 {
+    /// Returns a pointer to the register block
+    pub const fn ptr() -> *const target::i2s::RegisterBlock {
+        0x3ff6_d000 as *const _
+    }
+
+    /// Create the I2S1 peripheral
+    ///
+    /// # Safety
+    ///
+    /// Only one instance may exist at a time.
+    pub unsafe fn steal() -> Self {
+        I2S1 {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl core::ops::Deref for I2S1 {
+    type Target = target::i2s::RegisterBlock;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*I2S1::ptr() }
+    }
+}
+
+/// Clock dividers of the I2S clock
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub(crate) struct ClockDividers {
+    /// Integer part of the divider of the source clock
+    integer: u32,
+    /// Numerator of the fractional part of the divider of the source clock
+    numerator: u32,
+    /// Denominator of the fractional part of the divider of the source clock
+    denominator: u32,
+    /// Divider of the I2S clock to the bit clock
+    bck: u32,
+}
+
+/// Calculate the clock dividers for the sample rate
+///
+/// The bit clock is the sample rate times two channels of `bits` bits. The bit clock divider is
+/// chosen as small as possible, so the fractional divider of the source clock has the highest
+/// precision.
+pub(crate) fn clock_dividers(
+    source: Hertz,
+    sample_rate: Hertz,
+    bits: u8,
+) -> Result<ClockDividers, Error> {
+    let bck_frequency = sample_rate.0 as u64 * bits as u64 * 2;
+    if bck_frequency == 0 {
+        return Err(Error::SampleRateTooLow);
+    }
+
+    let source = source.0 as u64;
+
+    // smallest bit clock divider with the integer divider in range
+    let max_divided = bck_frequency * I2S_MAX_CLOCK_DIVIDER as u64;
+    let bck = core::cmp::max(
+        ((source + max_divided - 1) / max_divided) as u32,
+        I2S_MIN_BCK_DIVIDER,
+    );
+    if bck > I2S_MAX_BCK_DIVIDER {
+        return Err(Error::SampleRateTooLow);
+    }
+
+    let i2s_frequency = bck_frequency * bck as u64;
+    let integer = (source / i2s_frequency) as u32;
+    if integer < I2S_MIN_CLOCK_DIVIDER {
+        return Err(Error::SampleRateTooHigh);
+    }
+    let remainder = source % i2s_frequency;
+
+    // best approximation of the remainder with a fraction b/a
+    let mut best = (0, 1);
+    let mut best_error = remainder;
+    if remainder != 0 {
+        for denominator in 1..=I2S_MAX_FRACTIONAL_DENOMINATOR as u64 {
+            let numerator = (remainder * denominator + i2s_frequency / 2) / i2s_frequency;
+            if numerator >= denominator {
+                continue;
+            }
+
+            let error = (remainder * denominator).max(numerator * i2s_frequency)
+                - (remainder * denominator).min(numerator * i2s_frequency);
+            let error = error / denominator;
+            if error < best_error {
+                best = (numerator as u32, denominator as u32);
+                best_error = error;
+            }
+        }
+    }
+
+    Ok(ClockDividers {
+        integer,
+        numerator: best.0,
+        denominator: best.1,
+        bck,
+    })
+}
+
+/// Stop the transfers and reset the state machines, FIFOs and DMA
+pub(crate) fn reset_transfers(i2s: &target::i2s::RegisterBlock) {
+    i2s.conf
+        .modify(|_, w| w.tx_start().clear_bit().rx_start().clear_bit());
+    i2s.out_link.write(|w| w.outlink_stop().set_bit());
+    i2s.in_link.write(|w| w.inlink_stop().set_bit());
+
+    i2s.conf.modify(|_, w| {
+        w.tx_reset()
+            .set_bit()
+            .rx_reset()
+            .set_bit()
+            .tx_fifo_reset()
+            .set_bit()
+            .rx_fifo_reset()
+            .set_bit()
+    });
+    i2s.conf.modify(|_, w| {
+        w.tx_reset()
+            .clear_bit()
+            .rx_reset()
+            .clear_bit()
+            .tx_fifo_reset()
+            .clear_bit()
+            .rx_fifo_reset()
+            .clear_bit()
+    });
+    i2s.lc_conf.modify(|_, w| {
+        w.in_rst()
+            .set_bit()
+            .out_rst()
+            .set_bit()
+            .ahbm_rst()
+            .set_bit()
+            .ahbm_fifo_rst()
+            .set_bit()
+    });
+    i2s.lc_conf.modify(|_, w| {
+        w.in_rst()
+            .clear_bit()
+            .out_rst()
+            .clear_bit()
+            .ahbm_rst()
+            .clear_bit()
+            .ahbm_fifo_rst()
+            .clear_bit()
+    });
+
+    i2s.int_ena.write(|w| unsafe { w.bits(0) });
+    i2s.int_clr.write(|w| unsafe { w.bits(0x1ffff) });
+}
+
+/// Configure the clock dividers, with the audio PLL or PLL_D2 as source
+pub(crate) fn set_clock_dividers(
+    i2s: &target::i2s::RegisterBlock,
+    dividers: ClockDividers,
+    apll: bool,
+) {
+    i2s.clkm_conf.write(|w| unsafe {
+        w.clk_en()
+            .set_bit()
+            .clka_ena()
+            .bit(apll)
+            .clkm_div_num()
+            .bits(dividers.integer as u8)
+            .clkm_div_b()
+            .bits(dividers.numerator as u8)
+            .clkm_div_a()
+            .bits(dividers.denominator as u8)
+    });
+    i2s.sample_rate_conf.modify(|_, w| unsafe {
+        w.tx_bck_div_num()
+            .bits(dividers.bck as u8)
+            .rx_bck_div_num()
+            .bits(dividers.bck as u8)
+    });
+}
+
+/// I2S driver
+pub struct I2s<
+    I2S: Instance,
+    BCK: InputPin + OutputPin,
+    WS: InputPin + OutputPin,
+    DOUT: OutputPin = crate::gpio::Gpio22<crate::gpio::Output<crate::gpio::PushPull>>,
+    DIN: InputPin = crate::gpio::Gpio19<crate::gpio::Input<crate::gpio::Floating>>,
+> {
+    i2s: I2S,
+    pins: Pins<BCK, WS, DOUT, DIN>,
+    mode: Mode,
+    clock_control: crate::clock_control::ClockControlConfig,
+    pll_lock: Option<crate::clock_control::dfs::LockPllD2>,
+    _apb_lock: crate::clock_control::dfs::LockAPB,
+}
+
+impl<
+        I2S: Instance,
+        BCK: InputPin + OutputPin,
+        WS: InputPin + OutputPin,
+        DOUT: OutputPin,
+        DIN: InputPin,
+    > I2s<I2S, BCK, WS, DOUT, DIN>
+{
+    /// Create a new I2S driver
+    pub fn new(
+        i2s: I2S,
+        pins: Pins<BCK, WS, DOUT, DIN>,
+        config: Config,
+        clock_control: crate::clock_control::ClockControlConfig,
+        dport: &mut target::DPORT,
+    ) -> Result<Self, Error> {
+        let bits = config.bits_per_sample;
+        if bits != 8 && bits != 16 && bits != 24 && bits != 32 {
+            return Err(Error::InvalidBitsPerSample);
+        }
+
+        let mut i2s = I2s {
+            i2s,
+            pins,
+            mode: config.mode,
+            clock_control,
+            pll_lock: None,
+            // the DMA engine is clocked by the APB clock
+            _apb_lock: clock_control.lock_apb_frequency(),
+        };
+
+        i2s.i2s.reset(dport).enable(dport);
+        i2s.reset();
+
+        // plain I2S mode: no LCD/camera, no PDM, no PCM compression
+        i2s.i2s.conf2.write(|w| unsafe { w.bits(0) });
+        i2s.i2s.pdm_conf.modify(|_, w| {
+            w.tx_pdm_en()
+                .clear_bit()
+                .rx_pdm_en()
+                .clear_bit()
+                .pcm2pdm_conv_en()
+                .clear_bit()
+                .pdm2pcm_conv_en()
+                .clear_bit()
+        });
+        i2s.i2s.conf1.write(|w| {
+            w.tx_pcm_bypass()
+                .set_bit()
+                .rx_pcm_bypass()
+                .set_bit()
+                .tx_stop_en()
+                .set_bit()
+        });
+        i2s.i2s
+            .conf_chan
+            .write(|w| unsafe { w.tx_chan_mod().bits(0).rx_chan_mod().bits(0) });
+
+        // 16-bit or 32-bit words per sample, both channels
+        let fifo_mode = if bits <= 16 { 0 } else { 2 };
+        i2s.i2s.fifo_conf.write(|w| unsafe {
+            w.dscr_en()
+                .set_bit()
+                .tx_fifo_mod_force_en()
+                .set_bit()
+                .rx_fifo_mod_force_en()
+                .set_bit()
+                .tx_fifo_mod()
+                .bits(fifo_mode)
+                .rx_fifo_mod()
+                .bits(fifo_mode)
+                .tx_data_num()
+                .bits(32)
+                .rx_data_num()
+                .bits(32)
+        });
+
+        i2s.i2s.lc_conf.write(|w| {
+            w.out_eof_mode()
+                .set_bit()
+                .outdscr_burst_en()
+                .set_bit()
+                .out_data_burst_en()
+                .set_bit()
+                .indscr_burst_en()
+                .set_bit()
+        });
+
+        let slave = config.mode == Mode::Slave;
+        let (msb_shift, short_sync) = match config.format {
+            Format::Philips => (true, false),
+            Format::MSB => (false, false),
+            Format::PCMShort => (false, true),
+            Format::PCMLong => (false, false),
+        };
+        i2s.i2s.conf.modify(|_, w| {
+            w.tx_slave_mod()
+                .bit(slave)
+                .rx_slave_mod()
+                .bit(slave)
+                .tx_msb_shift()
+                .bit(msb_shift)
+                .rx_msb_shift()
+                .bit(msb_shift)
+                .tx_short_sync()
+                .bit(short_sync)
+                .rx_short_sync()
+                .bit(short_sync)
+                .tx_msb_right()
+                .set_bit()
+                .rx_msb_right()
+                .set_bit()
+                .tx_right_first()
+                .clear_bit()
+                .rx_right_first()
+                .clear_bit()
+                .tx_mono()
+                .clear_bit()
+                .rx_mono()
+                .clear_bit()
+                .sig_loopback()
+                .clear_bit()
+        });
+
+        i2s.i2s
+            .sample_rate_conf
+            .modify(|_, w| unsafe { w.tx_bits_mod().bits(bits).rx_bits_mod().bits(bits) });
+
+        if config.mode == Mode::Master {
+            i2s.change_sample_rate(config.sample_rate, config.clock_source)?;
+        } else {
+            i2s.i2s.clkm_conf.modify(|_, w| w.clk_en().set_bit());
+        }
+
+        Ok(i2s)
+    }
+
+    /// Change the sample rate (in master mode)
+    pub fn change_sample_rate(
+        &mut self,
+        sample_rate: Hertz,
+        clock_source: ClockSource,
+    ) -> Result<&mut Self, Error> {
+        let bits = self.i2s.sample_rate_conf.read().tx_bits_mod().bits();
+        let source = match clock_source {
+            ClockSource::PllD2 => self.clock_control.pll_d2_frequency(),
+            ClockSource::Apll => self.clock_control.apll_frequency(),
+        };
+        if source.0 == 0 {
+            return Err(Error::ApllNotRunning);
+        }
+
+        let dividers = clock_dividers(source, sample_rate, bits)?;
+
+        self.pll_lock = match clock_source {
+            ClockSource::PllD2 => Some(
+                self.pll_lock
+                    .take()
+                    .unwrap_or_else(|| self.clock_control.lock_plld2()),
+            ),
+            ClockSource::Apll => None,
+        };
+
+        set_clock_dividers(&self.i2s, dividers, clock_source == ClockSource::Apll);
+
+        Ok(self)
+    }
+
+    /// Returns the actual sample rate (in master mode)
+    pub fn sample_rate(&self) -> Hertz {
+        let clkm = self.i2s.clkm_conf.read();
+        let source = if clkm.clka_ena().bit_is_set() {
+            self.clock_control.apll_frequency()
+        } else {
+            self.clock_control.pll_d2_frequency()
+        };
+
+        let integer = clkm.clkm_div_num().bits() as u64;
+        let numerator = clkm.clkm_div_b().bits() as u64;
+        let denominator = core::cmp::max(clkm.clkm_div_a().bits() as u64, 1);
+
+        let sample_rate_conf = self.i2s.sample_rate_conf.read();
+        let bck = sample_rate_conf.tx_bck_div_num().bits() as u64;
+        let bits = sample_rate_conf.tx_bits_mod().bits() as u64;
+
+        let divider = (integer * denominator + numerator) * bck * bits * 2;
+        if divider == 0 {
+            return Hertz(0);
+        }
+        Hertz((source.0 as u64 * denominator / divider) as u32)
+    }
+
+    /// Start transmitting the buffer continuously
+    ///
+    /// The buffer should be filled with the first samples before the transmission is started.
+    pub fn transmit(mut self, buffer: CircularBuffer) -> Stream<I2S, BCK, WS, DOUT, DIN> {
+        if let Some(data_out) = &mut self.pins.data_out {
+            data_out
+                .set_to_push_pull_output()
+                .connect_peripheral_to_output(I2S::DATA_OUT_SIGNAL);
+        }
+        self.connect_clock_pins(true);
+        self.reset();
+
+        unsafe {
+            self.i2s.out_link.write(|w| {
+                w.outlink_addr()
+                    .bits(buffer.descriptors.address())
+                    .outlink_start()
+                    .set_bit()
+            });
+        }
+        self.i2s.conf.modify(|_, w| w.tx_start().set_bit());
+
+        Stream {
+            i2s: Some(self),
+            buffer: Some(buffer),
+            direction: Direction::Transmit,
+        }
+    }
+
+    /// Start receiving into the buffer continuously
+    pub fn receive(mut self, buffer: CircularBuffer) -> Stream<I2S, BCK, WS, DOUT, DIN> {
+        if let Some(data_in) = &mut self.pins.data_in {
+            data_in
+                .set_to_input()
+                .connect_input_to_peripheral(I2S::DATA_IN_SIGNAL);
+        }
+        self.connect_clock_pins(false);
+        self.reset();
+
+        unsafe {
+            // end of frame after each half of the buffer (in words)
+            self.i2s
+                .rxeof_num
+                .write(|w| w.rx_eof_num().bits(buffer.half_len() as u32 / 4));
+            self.i2s.in_link.write(|w| {
+                w.inlink_addr()
+                    .bits(buffer.descriptors.address())
+                    .inlink_start()
+                    .set_bit()
+            });
+        }
+        self.i2s.conf.modify(|_, w| w.rx_start().set_bit());
+
+        Stream {
+            i2s: Some(self),
+            buffer: Some(buffer),
+            direction: Direction::Receive,
+        }
+    }
+
+    /// Release the I2S and GPIO resources
+    pub fn release(self) -> (I2S, Pins<BCK, WS, DOUT, DIN>) {
+        (self.i2s, self.pins)
+    }
+
+    /// Route the bit clock and word select pins to the transmitter or receiver
+    fn connect_clock_pins(&mut self, transmit: bool) {
+        let signals = if transmit {
+            I2S::TX_CLOCK_SIGNALS
+        } else {
+            I2S::RX_CLOCK_SIGNALS
+        };
+
+        match self.mode {
+            Mode::Master => {
+                self.pins
+                    .bck
+                    .set_to_push_pull_output()
+                    .connect_peripheral_to_output(signals.0);
+                self.pins
+                    .ws
+                    .set_to_push_pull_output()
+                    .connect_peripheral_to_output(signals.1);
+            }
+            Mode::Slave => {
+                self.pins
+                    .bck
+                    .set_to_input()
+                    .connect_input_to_peripheral(signals.2);
+                self.pins
+                    .ws
+                    .set_to_input()
+                    .connect_input_to_peripheral(signals.3);
+            }
+        }
+    }
+
+    /// Stop the transfers and reset the state machines, FIFOs and DMA
+    fn reset(&mut self) {
+        reset_transfers(&self.i2s);
+    }
+}
+
+/// Direction of a stream
+#[derive(PartialEq, Eq, Copy, Clone)]
+enum Direction {
+    Transmit,
+    Receive,
+}
+
+/// Continuous DMA transfer
+///
+/// Dropping the stream stops the transfer.
+pub struct Stream<
+    I2S: Instance,
+    BCK: InputPin + OutputPin,
+    WS: InputPin + OutputPin,
+    DOUT: OutputPin,
+    DIN: InputPin,
+> {
+    i2s: Option<I2s<I2S, BCK, WS, DOUT, DIN>>,
+    buffer: Option<CircularBuffer>,
+    direction: Direction,
+}
+
+impl<
+        I2S: Instance,
+        BCK: InputPin + OutputPin,
+        WS: InputPin + OutputPin,
+        DOUT: OutputPin,
+        DIN: InputPin,
+    > Stream<I2S, BCK, WS, DOUT, DIN>
+{
+    /// Returns the half (0 or 1) of the buffer which was transferred completely, if any
+    ///
+    /// When transmitting this half can be refilled, when receiving it contains new samples. The
+    /// half needs to be processed before the transfer of the other half is complete. The event
+    /// is cleared.
+    pub fn completed_half(&mut self) -> Option<usize> {
+        let i2s = &self.i2s.as_ref()?.i2s;
+
+        let address = match self.direction {
+            Direction::Transmit => {
+                if i2s.int_raw.read().out_eof_int_raw().bit_is_clear() {
+                    return None;
+                }
+                i2s.int_clr.write(|w| w.out_eof_int_clr().set_bit());
+                i2s.out_eof_des_addr.read().bits()
+            }
+            Direction::Receive => {
+                if i2s.int_raw.read().in_suc_eof_int_raw().bit_is_clear() {
+                    return None;
+                }
+                i2s.int_clr.write(|w| w.in_suc_eof_int_clr().set_bit());
+                i2s.in_eof_des_addr.read().bits()
+            }
+        };
+
+        // the eof descriptor address is a full address in DRAM
+        Some(self.buffer.as_ref()?.descriptors.half_of(address))
+    }
+
+    /// Call the callback with the completed half of the buffer, if any
+    ///
+    /// Returns true if the callback was called.
+    pub fn process<F: FnOnce(usize, &mut [u8])>(&mut self, f: F) -> bool {
+        match (self.completed_half(), self.buffer.as_mut()) {
+            (Some(half), Some(buffer)) => {
+                f(half, buffer.half_mut(half));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Starts listening for an interrupt event
+    ///
+    /// The interrupt needs to be routed via [interrupt::enable][crate::interrupt] (`I2S0_INTR`
+    /// or `I2S1_INTR`).
+    pub fn listen(&mut self, event: Event) {
+        self.enable_interrupt(event, true);
+    }
+
+    /// Stop listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        self.enable_interrupt(event, false);
+    }
+
+    /// Stop the transfer and return the driver and buffer
+    pub fn stop(mut self) -> (I2s<I2S, BCK, WS, DOUT, DIN>, CircularBuffer) {
+        let mut i2s = self.i2s.take().unwrap();
+        i2s.reset();
+        (i2s, self.buffer.take().unwrap())
+    }
+
+    fn enable_interrupt(&mut self, event: Event, enable: bool) {
+        let i2s = match self.i2s.as_ref() {
+            Some(i2s) => &i2s.i2s,
+            None => return,
+        };
+
+        match (event, self.direction) {
+            (Event::HalfDone, Direction::Transmit) => {
+                i2s.int_ena.modify(|_, w| w.out_eof_int_ena().bit(enable))
+            }
+            (Event::HalfDone, Direction::Receive) => i2s
+                .int_ena
+                .modify(|_, w| w.in_suc_eof_int_ena().bit(enable)),
+        }
+    }
+}
+
+impl<
+        I2S: Instance,
+        BCK: InputPin + OutputPin,
+        WS: InputPin + OutputPin,
+        DOUT: OutputPin,
+        DIN: InputPin,
+    > Drop for Stream<I2S, BCK, WS, DOUT, DIN>
+{
+    fn drop(&mut self) {
+        // the descriptors are freed together with the buffer, so the DMA engine must be stopped
+        if let Some(i2s) = self.i2s.as_mut() {
+            i2s.reset();
+        }
+    }
+}
+
+pub trait Instance:
+    core::ops::Deref<Target = target::i2s::RegisterBlock> + private::Sealed
+{
+    /// Bit clock and word select of the transmitter (output in master, input in slave mode)
+    const TX_CLOCK_SIGNALS: (OutputSignal, OutputSignal, InputSignal, InputSignal);
+    /// Bit clock and word select of the receiver (output in master, input in slave mode)
+    const RX_CLOCK_SIGNALS: (OutputSignal, OutputSignal, InputSignal, InputSignal);
+    const DATA_OUT_SIGNAL: OutputSignal;
+    const DATA_IN_SIGNAL: InputSignal;
+
+    /// Enable peripheral
+    fn enable(&mut self, dport: &mut target::DPORT) -> &mut Self;
+    /// Disable peripheral
+    fn disable(&mut self, dport: &mut target::DPORT) -> &mut Self;
+    /// Reset peripheral
+    fn reset(&mut self, dport: &mut target::DPORT) -> &mut Self;
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! halI2s {
+    ($(
+        $I2SX:ty: ($i2sX:ident, $bck_out:ident, $ws_out:ident, $bck_in:ident, $ws_in:ident,
+            $data_out:ident, $data_in:ident),
+    )+) => {
+        $(
+            impl private::Sealed for $I2SX {}
+
+            impl Instance for $I2SX {
+                const TX_CLOCK_SIGNALS: (OutputSignal, OutputSignal, InputSignal, InputSignal) = (
+                    OutputSignal::$bck_out,
+                    OutputSignal::$ws_out,
+                    InputSignal::$bck_out,
+                    InputSignal::$ws_out,
+                );
+                const RX_CLOCK_SIGNALS: (OutputSignal, OutputSignal, InputSignal, InputSignal) = (
+                    OutputSignal::$bck_in,
+                    OutputSignal::$ws_in,
+                    InputSignal::$bck_in,
+                    InputSignal::$ws_in,
+                );
+                const DATA_OUT_SIGNAL: OutputSignal = OutputSignal::$data_out;
+                const DATA_IN_SIGNAL: InputSignal = InputSignal::$data_in;
+
+                fn reset(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                    dport.perip_rst_en.modify(|_, w| w.$i2sX().set_bit());
+                    dport.perip_rst_en.modify(|_, w| w.$i2sX().clear_bit());
+                    self
+                }
+
+                fn enable(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                    dport.perip_clk_en.modify(|_, w| w.$i2sX().set_bit());
+                    dport.perip_rst_en.modify(|_, w| w.$i2sX().clear_bit());
+                    self
+                }
+
+                fn disable(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                    dport.perip_clk_en.modify(|_, w| w.$i2sX().clear_bit());
+                    dport.perip_rst_en.modify(|_, w| w.$i2sX().set_bit());
+                    self
+                }
+            }
+        )+
+    }
+}
+
+halI2s! {
+    target::I2S: (i2s0, I2S0O_BCK, I2S0O_WS, I2S0I_BCK, I2S0I_WS, I2S0O_DATA_23, I2S0I_DATA_15),
+    I2S1: (i2s1, I2S1O_BCK, I2S1O_WS, I2S1I_BCK, I2S1I_WS, I2S1O_DATA_23, I2S1I_DATA_15),
+}
@@ -0,0 +1,816 @@
+//! LED PWM controller (LEDC)
+//!
+//! The LED PWM controller has 8 high speed and 8 low speed channels, each of which is driven by
+//! one of 4 high speed or 4 low speed timers respectively. A timer determines the frequency and
+//! the resolution of the duty cycle of the channels connected to it.
+//!
+//! The timers are clocked either by the reference clock (REF_TICK, 1MHz) or by the APB clock.
+//! When possible the reference clock is used, because this is constant when the clock
+//! source/frequency changes. When a higher frequency or resolution is needed the APB clock is
+//! used and an APB lock is held as long as the timer is configured this way.
+//!
+//! The low speed channels and timers only take over a new configuration at the next overflow of
+//! the timer, while the high speed channels and timers are updated immediately.
+//!
+//! # Example
+//!
+//! Dim a led connected to gpio2.
+//! ```
+//! let mut ledc = Ledc::new(dp.LEDC, clkcntrl_config, &mut dport);
+//!
+//! ledc.hstimer0
+//!     .configure(esp32_hal::ledc::config::TimerConfig {
+//!         frequency: 1.kHz().into(),
+//!         resolution: 10,
+//!     })
+//!     .unwrap();
+//!
+//! let mut pwm = ledc.hschannel0.connect(pins.gpio2, &ledc.hstimer0);
+//! pwm.set_duty(pwm.get_max_duty() / 4);
+//! pwm.enable();
+//! ```
+//!
+//! Hardware fading changes the duty cycle gradually without CPU load.
+//! ```
+//! pwm.start_fade(0, 100, 10).unwrap();
+//! pwm.wait_fade();
+//! ```
+//!
+//! # TODO
+//! - Release of the peripheral
+//! - Phase shifting via hpoint
+//! - RTC8M clock for low speed timers (to keep running in light sleep)
+
+use core::marker::PhantomData;
+
+use crate::gpio::OutputPin;
+use crate::prelude::*;
+use crate::target;
+
+use embedded_hal::PwmPin;
+
+/// Maximum resolution of the duty cycle in bits
+const LEDC_MAX_RESOLUTION: u8 = 20;
+
+/// Minimum timer divider (1.0 in 10.8 fixed point)
+const LEDC_MIN_DIVIDER: u64 = 1 << 8;
+/// Maximum timer divider (10.8 fixed point)
+const LEDC_MAX_DIVIDER: u64 = (1 << 18) - 1;
+
+/// Maximum number of steps, cycles per step and duty change per step of a fade
+const LEDC_MAX_FADE_VALUE: u16 = (1 << 10) - 1;
+
+/// LEDC error
+#[derive(Debug)]
+pub enum Error {
+    /// Frequency too high for the resolution
+    FrequencyTooHigh,
+    /// Frequency too low for the resolution
+    FrequencyTooLow,
+    /// Resolution not between 1 and 20 bits
+    InvalidResolution,
+    /// Fade steps, cycles per step or duty change per step out of range
+    InvalidFade,
+}
+
+/// Interrupt event
+pub enum Event {
+    /// Fade is complete
+    FadeDone,
+}
+
+/// LEDC configuration
+pub mod config {
+    use crate::units::*;
+
+    /// Timer configuration
+    #[derive(Copy, Clone)]
+    pub struct TimerConfig {
+        /// PWM frequency
+        pub frequency: Hertz,
+        /// Resolution of the duty cycle in bits (1-20)
+        pub resolution: u8,
+    }
+
+    impl TimerConfig {
+        pub fn frequency(mut self, frequency: Hertz) -> Self {
+            self.frequency = frequency;
+            self
+        }
+
+        pub fn resolution(mut self, resolution: u8) -> Self {
+            self.resolution = resolution;
+            self
+        }
+    }
+
+    impl Default for TimerConfig {
+        fn default() -> TimerConfig {
+            TimerConfig {
+                frequency: Hertz(5_000),
+                resolution: 13,
+            }
+        }
+    }
+}
+
+/// High speed channels and timers
+pub struct HighSpeed;
+/// Low speed channels and timers
+pub struct LowSpeed;
+
+use private::Speed;
+
+/// LED PWM controller split into its timers and channels
+pub struct Ledc {
+    pub hstimer0: Timer<HighSpeed>,
+    pub hstimer1: Timer<HighSpeed>,
+    pub hstimer2: Timer<HighSpeed>,
+    pub hstimer3: Timer<HighSpeed>,
+    pub lstimer0: Timer<LowSpeed>,
+    pub lstimer1: Timer<LowSpeed>,
+    pub lstimer2: Timer<LowSpeed>,
+    pub lstimer3: Timer<LowSpeed>,
+    pub hschannel0: Channel<HighSpeed>,
+    pub hschannel1: Channel<HighSpeed>,
+    pub hschannel2: Channel<HighSpeed>,
+    pub hschannel3: Channel<HighSpeed>,
+    pub hschannel4: Channel<HighSpeed>,
+    pub hschannel5: Channel<HighSpeed>,
+    pub hschannel6: Channel<HighSpeed>,
+    pub hschannel7: Channel<HighSpeed>,
+    pub lschannel0: Channel<LowSpeed>,
+    pub lschannel1: Channel<LowSpeed>,
+    pub lschannel2: Channel<LowSpeed>,
+    pub lschannel3: Channel<LowSpeed>,
+    pub lschannel4: Channel<LowSpeed>,
+    pub lschannel5: Channel<LowSpeed>,
+    pub lschannel6: Channel<LowSpeed>,
+    pub lschannel7: Channel<LowSpeed>,
+}
+
+impl Ledc {
+    /// Enable the LED PWM controller and split it into its timers and channels
+    pub fn new(
+        ledc: target::LEDC,
+        clock_control: crate::clock_control::ClockControlConfig,
+        dport: &mut target::DPORT,
+    ) -> Self {
+        dport.perip_rst_en.modify(|_, w| w.led_pwm().set_bit());
+        dport.perip_clk_en.modify(|_, w| w.led_pwm().set_bit());
+        dport.perip_rst_en.modify(|_, w| w.led_pwm().clear_bit());
+
+        // the slow clock of the low speed timers is the APB clock
+        ledc.conf.write(|w| w.apb_clk_sel().set_bit());
+
+        Ledc {
+            hstimer0: Timer::new(0, clock_control),
+            hstimer1: Timer::new(1, clock_control),
+            hstimer2: Timer::new(2, clock_control),
+            hstimer3: Timer::new(3, clock_control),
+            lstimer0: Timer::new(0, clock_control),
+            lstimer1: Timer::new(1, clock_control),
+            lstimer2: Timer::new(2, clock_control),
+            lstimer3: Timer::new(3, clock_control),
+            hschannel0: Channel::new(0),
+            hschannel1: Channel::new(1),
+            hschannel2: Channel::new(2),
+            hschannel3: Channel::new(3),
+            hschannel4: Channel::new(4),
+            hschannel5: Channel::new(5),
+            hschannel6: Channel::new(6),
+            hschannel7: Channel::new(7),
+            lschannel0: Channel::new(0),
+            lschannel1: Channel::new(1),
+            lschannel2: Channel::new(2),
+            lschannel3: Channel::new(3),
+            lschannel4: Channel::new(4),
+            lschannel5: Channel::new(5),
+            lschannel6: Channel::new(6),
+            lschannel7: Channel::new(7),
+        }
+    }
+}
+
+/// LEDC timer
+pub struct Timer<S: Speed> {
+    number: u8,
+    clock_control: crate::clock_control::ClockControlConfig,
+    apb_lock: Option<crate::clock_control::dfs::LockAPB>,
+    _speed: PhantomData<S>,
+}
+
+impl<S: Speed> Timer<S> {
+    fn new(number: u8, clock_control: crate::clock_control::ClockControlConfig) -> Self {
+        Timer {
+            number,
+            clock_control,
+            apb_lock: None,
+            _speed: PhantomData,
+        }
+    }
+
+    /// Configure frequency and resolution and start the timer
+    pub fn configure(&mut self, config: config::TimerConfig) -> Result<&mut Self, Error> {
+        self.change_frequency(config.frequency, config.resolution)
+    }
+
+    /// Change the frequency and resolution
+    ///
+    /// Will automatically select the clock source. When possible the reference clock (1MHz) will
+    /// be used, because this is constant when the clock source/frequency changes.
+    /// However if the reference clock is not stable or if the frequency multiplied by the
+    /// number of duty cycle steps is above the reference clock then use the APB clock.
+    pub fn change_frequency<T: Into<Hertz> + Copy>(
+        &mut self,
+        frequency: T,
+        resolution: u8,
+    ) -> Result<&mut Self, Error> {
+        let use_apb_frequency = !self.clock_control.is_ref_clock_stable()
+            || clock_divider(
+                self.clock_control.ref_frequency(),
+                frequency.into(),
+                resolution,
+            )
+            .is_err();
+
+        self.change_frequency_force_clock(frequency, resolution, use_apb_frequency)
+    }
+
+    /// Change the frequency and resolution choosing the reference or APB clock manually
+    pub fn change_frequency_force_clock<T: Into<Hertz> + Copy>(
+        &mut self,
+        frequency: T,
+        resolution: u8,
+        use_apb_frequency: bool,
+    ) -> Result<&mut Self, Error> {
+        let source_frequency = if use_apb_frequency {
+            if let None = self.apb_lock {
+                self.apb_lock = Some(self.clock_control.lock_apb_frequency());
+            }
+            self.clock_control.apb_frequency_apb_locked()
+        } else {
+            self.clock_control.ref_frequency()
+        };
+
+        let divider = match clock_divider(source_frequency, frequency.into(), resolution) {
+            Ok(divider) => divider,
+            Err(error) => {
+                if !self.is_clock_apb() {
+                    self.apb_lock = None;
+                }
+                return Err(error);
+            }
+        };
+
+        S::configure_timer(self.number, divider, resolution, use_apb_frequency);
+
+        if !use_apb_frequency {
+            self.apb_lock = None;
+        }
+
+        Ok(self)
+    }
+
+    /// Returns if the reference or APB clock is used
+    pub fn is_clock_apb(&self) -> bool {
+        S::is_timer_clock_apb(self.number)
+    }
+
+    /// Returns the current frequency
+    pub fn frequency(&self) -> Hertz {
+        let source_frequency = if self.is_clock_apb() {
+            self.clock_control.apb_frequency()
+        } else {
+            self.clock_control.ref_frequency()
+        };
+
+        let divider = (S::timer_divider(self.number) as u64) << self.resolution();
+        if divider == 0 {
+            return Hertz(0);
+        }
+
+        Hertz((((source_frequency.0 as u64) << 8) / divider) as u32)
+    }
+
+    /// Returns the resolution of the duty cycle in bits
+    pub fn resolution(&self) -> u8 {
+        S::timer_resolution(self.number)
+    }
+
+    /// Pause the timer, the outputs of the connected channels stay at their current level
+    pub fn pause(&mut self) {
+        S::pause_timer(self.number, true);
+    }
+
+    /// Resume the timer
+    pub fn resume(&mut self) {
+        S::pause_timer(self.number, false);
+    }
+}
+
+/// Calculate the timer divider (10.8 fixed point) for the requested frequency and resolution
+fn clock_divider(source: Hertz, frequency: Hertz, resolution: u8) -> Result<u32, Error> {
+    if resolution == 0 || resolution > LEDC_MAX_RESOLUTION {
+        return Err(Error::InvalidResolution);
+    }
+
+    let steps = (frequency.0 as u64) << resolution;
+    if steps == 0 {
+        return Err(Error::FrequencyTooLow);
+    }
+
+    let divider = (((source.0 as u64) << 8) + steps / 2) / steps;
+
+    if divider < LEDC_MIN_DIVIDER {
+        return Err(Error::FrequencyTooHigh);
+    }
+    if divider > LEDC_MAX_DIVIDER {
+        return Err(Error::FrequencyTooLow);
+    }
+
+    Ok(divider as u32)
+}
+
+/// LEDC channel which is not connected to an output
+pub struct Channel<S: Speed> {
+    number: u8,
+    _speed: PhantomData<S>,
+}
+
+impl<S: Speed> Channel<S> {
+    fn new(number: u8) -> Self {
+        Channel {
+            number,
+            _speed: PhantomData,
+        }
+    }
+
+    /// Connect the channel to a timer and an output pin
+    ///
+    /// The output is disabled until [enable][embedded_hal::PwmPin::enable] is called.
+    pub fn connect<PIN: OutputPin>(self, mut pin: PIN, timer: &Timer<S>) -> Pwm<S, PIN> {
+        S::configure_channel(self.number, timer.number);
+
+        pin.set_to_push_pull_output()
+            .connect_peripheral_to_output(S::SIGNALS[self.number as usize]);
+
+        Pwm { channel: self, pin }
+    }
+}
+
+/// LEDC channel connected to a timer and an output pin
+pub struct Pwm<S: Speed, PIN: OutputPin> {
+    channel: Channel<S>,
+    pin: PIN,
+}
+
+impl<S: Speed, PIN: OutputPin> Pwm<S, PIN> {
+    /// Connect the channel to another timer
+    pub fn change_timer(&mut self, timer: &Timer<S>) -> &mut Self {
+        S::configure_channel(self.channel.number, timer.number);
+        self
+    }
+
+    /// Start a hardware fade from the current duty to the target duty
+    ///
+    /// The duty is changed in `steps` equal steps (1-1023), each lasting `cycles_per_step` PWM
+    /// cycles (1-1023). The number of steps is reduced if the duty difference is smaller than
+    /// the number of steps. Completion is signalled by the [FadeDone][Event::FadeDone] event.
+    pub fn start_fade(
+        &mut self,
+        target_duty: u32,
+        steps: u16,
+        cycles_per_step: u16,
+    ) -> Result<&mut Self, Error> {
+        if cycles_per_step == 0 || cycles_per_step > LEDC_MAX_FADE_VALUE {
+            return Err(Error::InvalidFade);
+        }
+
+        let target_duty = core::cmp::min(target_duty, self.get_max_duty());
+        let fade = fade_parameters(self.get_duty(), target_duty, steps)?;
+
+        S::clear_fade_interrupt(self.channel.number);
+        S::set_duty(
+            self.channel.number,
+            fade.start_duty,
+            fade.increase,
+            fade.steps,
+            cycles_per_step,
+            fade.scale,
+        );
+
+        Ok(self)
+    }
+
+    /// Returns true if the fade (or the last duty change) is complete
+    pub fn is_fade_done(&self) -> bool {
+        S::is_fade_done(self.channel.number)
+    }
+
+    /// Wait for the fade to complete
+    pub fn wait_fade(&mut self) {
+        while !self.is_fade_done() {}
+    }
+
+    /// Starts listening for an interrupt event
+    ///
+    /// The interrupt of all channels is `LEDC_INTR`.
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::FadeDone => S::enable_fade_interrupt(self.channel.number, true),
+        }
+    }
+
+    /// Stop listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        match event {
+            Event::FadeDone => S::enable_fade_interrupt(self.channel.number, false),
+        }
+    }
+
+    /// Returns true if the interrupt for the event is set
+    pub fn is_interrupt_set(&self, event: Event) -> bool {
+        match event {
+            Event::FadeDone => self.is_fade_done(),
+        }
+    }
+
+    /// Clear the interrupt for the event
+    pub fn clear_interrupt(&mut self, event: Event) {
+        match event {
+            Event::FadeDone => S::clear_fade_interrupt(self.channel.number),
+        }
+    }
+
+    /// Disconnect the channel from the output and release the resources
+    pub fn release(mut self) -> (Channel<S>, PIN) {
+        self.unlisten(Event::FadeDone);
+        self.disable();
+        (self.channel, self.pin)
+    }
+}
+
+/// Register settings of a fade
+struct Fade {
+    start_duty: u32,
+    increase: bool,
+    steps: u16,
+    scale: u16,
+}
+
+/// Calculate the register settings to fade from the current to the target duty
+///
+/// The start duty is adjusted (by less than one step) so the fade ends exactly at the target.
+fn fade_parameters(current_duty: u32, target_duty: u32, steps: u16) -> Result<Fade, Error> {
+    if steps == 0 || steps > LEDC_MAX_FADE_VALUE {
+        return Err(Error::InvalidFade);
+    }
+
+    let increase = target_duty >= current_duty;
+    let difference = if increase {
+        target_duty - current_duty
+    } else {
+        current_duty - target_duty
+    };
+
+    if difference == 0 {
+        return Ok(Fade {
+            start_duty: target_duty,
+            increase,
+            steps: 1,
+            scale: 0,
+        });
+    }
+
+    let steps = core::cmp::min(steps as u32, difference);
+    let scale = difference / steps;
+    if scale > LEDC_MAX_FADE_VALUE as u32 {
+        return Err(Error::InvalidFade);
+    }
+
+    let start_duty = if increase {
+        target_duty - scale * steps
+    } else {
+        target_duty + scale * steps
+    };
+
+    Ok(Fade {
+        start_duty,
+        increase,
+        steps: steps as u16,
+        scale: scale as u16,
+    })
+}
+
+impl<S: Speed, PIN: OutputPin> PwmPin for Pwm<S, PIN> {
+    type Duty = u32;
+
+    /// Disable the output, the output stays low
+    fn disable(&mut self) {
+        S::enable_output(self.channel.number, false);
+    }
+
+    fn enable(&mut self) {
+        S::enable_output(self.channel.number, true);
+    }
+
+    fn get_duty(&self) -> Self::Duty {
+        S::duty(self.channel.number)
+    }
+
+    /// Returns the maximum duty, which equals 100% (output always high)
+    fn get_max_duty(&self) -> Self::Duty {
+        1 << S::timer_resolution(S::channel_timer(self.channel.number))
+    }
+
+    /// Set the duty cycle, values above the maximum duty are clamped
+    fn set_duty(&mut self, duty: Self::Duty) {
+        let duty = core::cmp::min(duty, self.get_max_duty());
+        S::set_duty(self.channel.number, duty, true, 1, 1, 0);
+    }
+}
+
+mod private {
+    use super::{HighSpeed, LowSpeed};
+    use crate::gpio::OutputSignal;
+    use crate::prelude::*;
+    use crate::target::{ledc, LEDC};
+
+    pub trait Speed {
+        /// Output signals of the channels
+        const SIGNALS: [OutputSignal; 8];
+
+        /// Set divider, resolution and clock source of the timer and (re)start it
+        fn configure_timer(timer: u8, divider: u32, resolution: u8, use_apb_frequency: bool);
+        /// Divider of the timer (10.8 fixed point)
+        fn timer_divider(timer: u8) -> u32;
+        /// Resolution of the timer in bits
+        fn timer_resolution(timer: u8) -> u8;
+        /// Returns true if the timer is clocked by the APB clock
+        fn is_timer_clock_apb(timer: u8) -> bool;
+        /// Pause or resume the timer
+        fn pause_timer(timer: u8, pause: bool);
+
+        /// Connect the channel to the timer
+        fn configure_channel(channel: u8, timer: u8);
+        /// Timer the channel is connected to
+        fn channel_timer(channel: u8) -> u8;
+        /// Enable/disable the output of the channel
+        fn enable_output(channel: u8, enable: bool);
+        /// Set the duty of the channel and change it by scale every cycles for steps times
+        fn set_duty(channel: u8, duty: u32, increase: bool, steps: u16, cycles: u16, scale: u16);
+        /// Current duty of the channel
+        fn duty(channel: u8) -> u32;
+
+        /// Bit of the first channel in the interrupt registers
+        const INTERRUPT_OFFSET: u8;
+
+        /// Enable/disable the fade complete interrupt of the channel
+        fn enable_fade_interrupt(channel: u8, enable: bool) {
+            let mask = 1 << (Self::INTERRUPT_OFFSET + channel);
+            let ledc = unsafe { &*LEDC::ptr() };
+
+            (&LEDC_INTERRUPT_LOCK).lock(|_| {
+                ledc.int_ena.modify(|r, w| unsafe {
+                    w.bits(if enable {
+                        r.bits() | mask
+                    } else {
+                        r.bits() & !mask
+                    })
+                })
+            });
+        }
+
+        /// Returns true if the fade of the channel is complete
+        fn is_fade_done(channel: u8) -> bool {
+            let ledc = unsafe { &*LEDC::ptr() };
+            ledc.int_raw.read().bits() & (1 << (Self::INTERRUPT_OFFSET + channel)) != 0
+        }
+
+        /// Clear the fade complete interrupt of the channel
+        fn clear_fade_interrupt(channel: u8) {
+            let ledc = unsafe { &*LEDC::ptr() };
+            ledc.int_clr
+                .write(|w| unsafe { w.bits(1 << (Self::INTERRUPT_OFFSET + channel)) });
+        }
+    }
+
+    static LEDC_INTERRUPT_LOCK: CriticalSectionSpinLockMutex<()> =
+        CriticalSectionSpinLockMutex::new(());
+
+    /// Offset between the registers of consecutive channels
+    const CHANNEL_STRIDE: usize = 0x14;
+    /// Offset between the registers of consecutive timers
+    const TIMER_STRIDE: usize = 0x8;
+
+    macro_rules! halLedc {
+        ($(
+            $Speed:ident: {
+                channels: ($ChannelRegisters:ident, $channel_offset:expr,
+                    $CONF0:ident, $HPOINT:ident, $DUTY:ident, $CONF1:ident, $DUTY_R:ident),
+                channel_fields: ($idle_lv:ident, $sig_out_en:ident, $timer_sel:ident,
+                    $hpoint:ident, $duty:ident, $duty_start:ident, $duty_inc:ident,
+                    $duty_num:ident, $duty_cycle:ident, $duty_scale:ident),
+                timers: ($TimerRegisters:ident, $timer_offset:expr, $TIMER_CONF:ident,
+                    $TIMER_VALUE:ident),
+                timer_fields: ($tick_sel:ident, $rst:ident, $pause:ident, $div_num:ident,
+                    $lim:ident),
+                signals: [$($signal:ident),+],
+                interrupt_offset: $interrupt_offset:expr,
+            },
+        )+) => {
+            $(
+                /// Registers of a channel (same layout for all channels)
+                #[allow(dead_code)]
+                #[repr(C)]
+                struct $ChannelRegisters {
+                    conf0: ledc::$CONF0,
+                    hpoint: ledc::$HPOINT,
+                    duty: ledc::$DUTY,
+                    conf1: ledc::$CONF1,
+                    duty_r: ledc::$DUTY_R,
+                }
+
+                /// Registers of a timer (same layout for all timers)
+                #[allow(dead_code)]
+                #[repr(C)]
+                struct $TimerRegisters {
+                    conf: ledc::$TIMER_CONF,
+                    value: ledc::$TIMER_VALUE,
+                }
+
+                impl $Speed {
+                    fn channel(channel: u8) -> &'static $ChannelRegisters {
+                        unsafe {
+                            &*((LEDC::ptr() as usize
+                                + $channel_offset
+                                + channel as usize * CHANNEL_STRIDE)
+                                as *const $ChannelRegisters)
+                        }
+                    }
+
+                    fn timer(timer: u8) -> &'static $TimerRegisters {
+                        unsafe {
+                            &*((LEDC::ptr() as usize
+                                + $timer_offset
+                                + timer as usize * TIMER_STRIDE)
+                                as *const $TimerRegisters)
+                        }
+                    }
+                }
+
+                impl Speed for $Speed {
+                    const SIGNALS: [OutputSignal; 8] = [$(OutputSignal::$signal),+];
+                    const INTERRUPT_OFFSET: u8 = $interrupt_offset;
+
+                    fn configure_timer(
+                        timer: u8,
+                        divider: u32,
+                        resolution: u8,
+                        use_apb_frequency: bool,
+                    ) {
+                        let registers = Self::timer(timer);
+                        registers.conf.modify(|_, w| unsafe {
+                            w.$tick_sel()
+                                .bit(use_apb_frequency)
+                                .$div_num()
+                                .bits(divider)
+                                .$lim()
+                                .bits(resolution)
+                                .$pause()
+                                .clear_bit()
+                        });
+                        registers.conf.modify(|_, w| w.$rst().set_bit());
+                        registers.conf.modify(|_, w| w.$rst().clear_bit());
+                        Self::update_timer(timer);
+                    }
+
+                    fn timer_divider(timer: u8) -> u32 {
+                        Self::timer(timer).conf.read().$div_num().bits()
+                    }
+
+                    fn timer_resolution(timer: u8) -> u8 {
+                        Self::timer(timer).conf.read().$lim().bits()
+                    }
+
+                    fn is_timer_clock_apb(timer: u8) -> bool {
+                        Self::timer(timer).conf.read().$tick_sel().bit_is_set()
+                    }
+
+                    fn pause_timer(timer: u8, pause: bool) {
+                        Self::timer(timer).conf.modify(|_, w| w.$pause().bit(pause));
+                        Self::update_timer(timer);
+                    }
+
+                    fn configure_channel(channel: u8, timer: u8) {
+                        let registers = Self::channel(channel);
+                        unsafe {
+                            registers.hpoint.write(|w| w.$hpoint().bits(0));
+                            registers
+                                .conf0
+                                .modify(|_, w| w.$timer_sel().bits(timer).$idle_lv().clear_bit());
+                        }
+                        Self::update_channel(channel);
+                    }
+
+                    fn channel_timer(channel: u8) -> u8 {
+                        Self::channel(channel).conf0.read().$timer_sel().bits()
+                    }
+
+                    fn enable_output(channel: u8, enable: bool) {
+                        Self::channel(channel)
+                            .conf0
+                            .modify(|_, w| w.$sig_out_en().bit(enable));
+                        Self::update_channel(channel);
+                    }
+
+                    fn set_duty(
+                        channel: u8,
+                        duty: u32,
+                        increase: bool,
+                        steps: u16,
+                        cycles: u16,
+                        scale: u16,
+                    ) {
+                        let registers = Self::channel(channel);
+                        unsafe {
+                            // the duty has 4 fractional bits
+                            registers.duty.write(|w| w.$duty().bits(duty << 4));
+                            registers.conf1.write(|w| {
+                                w.$duty_start()
+                                    .set_bit()
+                                    .$duty_inc()
+                                    .bit(increase)
+                                    .$duty_num()
+                                    .bits(steps)
+                                    .$duty_cycle()
+                                    .bits(cycles)
+                                    .$duty_scale()
+                                    .bits(scale)
+                            });
+                        }
+                        Self::update_channel(channel);
+                    }
+
+                    fn duty(channel: u8) -> u32 {
+                        Self::channel(channel).duty_r.read().$duty().bits() >> 4
+                    }
+                }
+            )+
+        }
+    }
+
+    halLedc! {
+        HighSpeed: {
+            channels: (HighSpeedChannelRegisters, 0x00,
+                HSCH0_CONF0, HSCH0_HPOINT, HSCH0_DUTY, HSCH0_CONF1, HSCH0_DUTY_R),
+            channel_fields: (idle_lv_hsch0, sig_out_en_hsch0, timer_sel_hsch0, hpoint_hsch0,
+                duty_hsch0, duty_start_hsch0, duty_inc_hsch0, duty_num_hsch0, duty_cycle_hsch0,
+                duty_scale_hsch0),
+            timers: (HighSpeedTimerRegisters, 0x140, HSTIMER0_CONF, HSTIMER0_VALUE),
+            timer_fields: (tick_sel_hstimer0, hstimer0_rst, hstimer0_pause, div_num_hstimer0,
+                hstimer0_lim),
+            signals: [LEDC_HS_SIG_0, LEDC_HS_SIG_1, LEDC_HS_SIG_2, LEDC_HS_SIG_3,
+                LEDC_HS_SIG_4, LEDC_HS_SIG_5, LEDC_HS_SIG_6, LEDC_HS_SIG_7],
+            interrupt_offset: 8,
+        },
+        LowSpeed: {
+            channels: (LowSpeedChannelRegisters, 0xa0,
+                LSCH0_CONF0, LSCH0_HPOINT, LSCH0_DUTY, LSCH0_CONF1, LSCH0_DUTY_R),
+            channel_fields: (idle_lv_lsch0, sig_out_en_lsch0, timer_sel_lsch0, hpoint_lsch0,
+                duty_lsch0, duty_start_lsch0, duty_inc_lsch0, duty_num_lsch0, duty_cycle_lsch0,
+                duty_scale_lsch0),
+            timers: (LowSpeedTimerRegisters, 0x160, LSTIMER0_CONF, LSTIMER0_VALUE),
+            timer_fields: (tick_sel_lstimer0, lstimer0_rst, lstimer0_pause, div_num_lstimer0,
+                lstimer0_lim),
+            signals: [LEDC_LS_SIG_0, LEDC_LS_SIG_1, LEDC_LS_SIG_2, LEDC_LS_SIG_3,
+                LEDC_LS_SIG_4, LEDC_LS_SIG_5, LEDC_LS_SIG_6, LEDC_LS_SIG_7],
+            interrupt_offset: 16,
+        },
+    }
+
+    impl HighSpeed {
+        /// High speed timers are updated immediately
+        fn update_timer(_timer: u8) {}
+
+        /// High speed channels are updated immediately
+        fn update_channel(_channel: u8) {}
+    }
+
+    impl LowSpeed {
+        /// Take over the new timer configuration at the next overflow
+        fn update_timer(timer: u8) {
+            Self::timer(timer)
+                .conf
+                .modify(|_, w| w.lstimer0_para_up().set_bit());
+        }
+
+        /// Take over the new channel configuration at the next overflow
+        fn update_channel(channel: u8) {
+            Self::channel(channel)
+                .conf0
+                .modify(|_, w| w.para_up_lsch0().set_bit());
+        }
+    }
+}
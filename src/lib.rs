@@ -9,7 +9,7 @@
 //!         the ROM bootloader and eases debugging
 //! - `alloc`
 //!     - Enables support for dynamic memory allocations via a GlobalAllocator
-//!         and/or AllocRef
+//!         and/or AllocRef and for DMA transfers (DMA descriptors are allocated in DRAM)
 //! - `mem`
 //!     - Include customized memcpy, memset, etc. which use word (4-byte) sized and aligned
 //!         instructions to support IRAM usage and as optimization
@@ -28,16 +28,30 @@ pub use proc_macros::ram;
 
 pub mod analog;
 pub mod clock_control;
+#[cfg(feature = "alloc")]
+pub mod dma;
 pub mod dport;
 pub mod efuse;
 #[cfg(feature = "external_ram")]
 pub mod external_ram;
 pub mod gpio;
+pub mod i2c;
+#[cfg(feature = "alloc")]
+pub mod i2s;
 #[cfg(feature = "rt")]
 pub mod interrupt;
+pub mod ledc;
+pub mod mcpwm;
+pub mod pcnt;
+pub mod persistent;
 pub mod prelude;
+pub mod reset;
+pub mod rmt;
 pub mod serial;
+pub mod sleep;
+pub mod spi;
 pub mod timer;
+pub mod touch;
 pub mod units;
 
 #[cfg(feature = "alloc")]
@@ -0,0 +1,1369 @@
+//! Motor control PWM (MCPWM0, MCPWM1)
+//!
+//! Each of the two motor control PWM peripherals (`PWM0` and `PWM1` in the PAC) consists of:
+//! - 3 [Timer]s determining the PWM frequency, which can be synchronized to each other or to
+//!   external [SyncInput]s
+//! - 3 [Operator]s, each driving 2 outputs (A and B). An operator is connected to one of the
+//!   timers and contains:
+//!     - 2 generators (A and B) switching the outputs on timer and compare events
+//!     - dead time insertion for complementary outputs
+//!     - a fault handler, forcing the outputs to a safe state when a fault is detected
+//! - 3 [FaultInput]s used by the fault handlers of the operators
+//! - 3 [CaptureChannel]s timestamping edges of external signals (e.g. hall sensors)
+//!
+//! The PWM timers are clocked by the 160MHz PLL clock, so a PLL lock is held while a timer is
+//! configured. The capture timer is clocked by the APB clock, so an APB lock is held while a
+//! capture channel is enabled.
+//!
+//! # Example
+//!
+//! Complementary outputs with dead time for a half bridge, switched off by a fault input.
+//! ```
+//! let mut mcpwm = Mcpwm::new(dp.PWM0, clkcntrl_config, &mut dport);
+//!
+//! mcpwm
+//!     .timer0
+//!     .configure(TimerConfig::default().frequency(20.kHz().into()))
+//!     .unwrap();
+//!
+//! let fault = mcpwm.fault0.enable(pins.gpio4, true);
+//!
+//! let mut bridge = mcpwm
+//!     .operator0
+//!     .connect(&mcpwm.timer0, pins.gpio25, pins.gpio26);
+//! bridge.set_dead_time(DeadTimeConfig {
+//!     mode: DeadTimeMode::ActiveHighComplementary,
+//!     rising_edge_delay: 160,
+//!     falling_edge_delay: 160,
+//! });
+//! bridge.configure_fault_handler(FaultHandlerConfig {
+//!     inputs: [Some(FaultMode::OneShot), None, None],
+//!     action_a: Action::Low,
+//!     action_b: Action::Low,
+//! });
+//! bridge.set_duty(Generator::A, bridge.get_max_duty() / 2);
+//!
+//! mcpwm.timer0.start();
+//! ```
+//!
+//! # TODO
+//! - Carrier modulation
+//! - Timer event triggered (t0/t1) generator actions
+//! - Release of the peripheral
+
+use core::marker::PhantomData;
+
+use crate::gpio::{InputPin, OutputPin};
+use crate::prelude::*;
+use crate::target;
+
+/// Frequency of the clock of the PWM timers (PLL_F160M)
+const MCPWM_SOURCE_FREQUENCY: Hertz = Hertz(160_000_000);
+
+/// Maximum timer prescaler
+const MCPWM_MAX_PRESCALER: u32 = 256;
+/// Maximum timer period in ticks
+const MCPWM_MAX_PERIOD: u32 = 1 << 16;
+
+/// MCPWM error
+#[derive(Debug)]
+pub enum Error {
+    /// PWM frequency too high
+    FrequencyTooHigh,
+    /// PWM frequency too low
+    FrequencyTooLow,
+}
+
+/// Timer interrupt event
+pub enum TimerEvent {
+    /// Timer stopped
+    Stop,
+    /// Timer reached zero
+    Zero,
+    /// Timer reached the period
+    Period,
+}
+
+/// Operator interrupt event
+pub enum OperatorEvent {
+    /// Timer reached the compare value of generator A
+    CompareA,
+    /// Timer reached the compare value of generator B
+    CompareB,
+    /// Cycle-by-cycle trip by the fault handler
+    CycleByCycleTrip,
+    /// One-shot trip by the fault handler
+    OneShotTrip,
+}
+
+/// Fault input interrupt event
+pub enum FaultEvent {
+    /// Fault became active
+    Active,
+    /// Fault was cleared
+    Cleared,
+}
+
+/// Capture interrupt event
+pub enum CaptureEvent {
+    /// Edge captured
+    Captured,
+}
+
+/// MCPWM configuration
+pub mod config {
+    use crate::units::*;
+
+    /// Counting mode of a timer
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum CountMode {
+        /// Count up from zero to the period (asymmetric PWM)
+        Up,
+        /// Count down from the period to zero (asymmetric PWM)
+        Down,
+        /// Count up from zero to the period and back down (symmetric PWM)
+        UpDown,
+    }
+
+    /// Timer configuration
+    #[derive(Copy, Clone)]
+    pub struct TimerConfig {
+        /// PWM frequency
+        pub frequency: Hertz,
+        pub mode: CountMode,
+    }
+
+    impl TimerConfig {
+        pub fn frequency(mut self, frequency: Hertz) -> Self {
+            self.frequency = frequency;
+            self
+        }
+
+        pub fn mode(mut self, mode: CountMode) -> Self {
+            self.mode = mode;
+            self
+        }
+    }
+
+    impl Default for TimerConfig {
+        fn default() -> TimerConfig {
+            TimerConfig {
+                frequency: Hertz(20_000),
+                mode: CountMode::Up,
+            }
+        }
+    }
+
+    /// Action of a generator on its output
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum Action {
+        /// Keep the output unchanged
+        None = 0,
+        /// Set the output low
+        Low = 1,
+        /// Set the output high
+        High = 2,
+        /// Toggle the output
+        Toggle = 3,
+    }
+
+    /// Actions of a generator on timer and compare events
+    #[derive(Copy, Clone, Debug)]
+    pub struct GeneratorActions {
+        pub up_zero: Action,
+        pub up_period: Action,
+        pub up_compare_a: Action,
+        pub up_compare_b: Action,
+        pub down_zero: Action,
+        pub down_period: Action,
+        pub down_compare_a: Action,
+        pub down_compare_b: Action,
+    }
+
+    impl GeneratorActions {
+        /// Output high from zero until the compare value of the generator
+        ///
+        /// This is the default of the generators.
+        pub fn active_high(generator: super::Generator) -> Self {
+            let mut actions = GeneratorActions {
+                up_zero: Action::High,
+                down_period: Action::Low,
+                ..GeneratorActions::default()
+            };
+
+            match generator {
+                super::Generator::A => {
+                    actions.up_compare_a = Action::Low;
+                    actions.down_compare_a = Action::High;
+                }
+                super::Generator::B => {
+                    actions.up_compare_b = Action::Low;
+                    actions.down_compare_b = Action::High;
+                }
+            }
+
+            actions
+        }
+
+        /// Register value of the actions
+        pub(crate) fn bits(&self) -> u32 {
+            (self.up_zero as u32)
+                | (self.up_period as u32) << 2
+                | (self.up_compare_a as u32) << 4
+                | (self.up_compare_b as u32) << 6
+                | (self.down_zero as u32) << 12
+                | (self.down_period as u32) << 14
+                | (self.down_compare_a as u32) << 16
+                | (self.down_compare_b as u32) << 18
+        }
+    }
+
+    impl Default for GeneratorActions {
+        fn default() -> GeneratorActions {
+            GeneratorActions {
+                up_zero: Action::None,
+                up_period: Action::None,
+                up_compare_a: Action::None,
+                up_compare_b: Action::None,
+                down_zero: Action::None,
+                down_period: Action::None,
+                down_compare_a: Action::None,
+                down_compare_b: Action::None,
+            }
+        }
+    }
+
+    /// Dead time mode
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum DeadTimeMode {
+        /// No dead time, the generator outputs are used directly
+        Bypass,
+        /// Output A: generator A with rising edge delay, output B: generator A with falling edge
+        /// delay
+        ActiveHigh,
+        /// As [ActiveHigh][DeadTimeMode::ActiveHigh], but both outputs inverted
+        ActiveLow,
+        /// As [ActiveHigh][DeadTimeMode::ActiveHigh], but output B inverted
+        ActiveHighComplementary,
+        /// As [ActiveHigh][DeadTimeMode::ActiveHigh], but output A inverted
+        ActiveLowComplementary,
+    }
+
+    /// Dead time configuration
+    ///
+    /// The delays are in ticks of the 160MHz PWM clock.
+    #[derive(Copy, Clone, Debug)]
+    pub struct DeadTimeConfig {
+        pub mode: DeadTimeMode,
+        pub rising_edge_delay: u16,
+        pub falling_edge_delay: u16,
+    }
+
+    /// Reaction of a fault handler to a fault
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum FaultMode {
+        /// Trip until the trip is cleared by software
+        OneShot,
+        /// Trip until the timer reaches zero after the fault is gone
+        CycleByCycle,
+    }
+
+    /// Fault handler configuration
+    #[derive(Copy, Clone, Debug)]
+    pub struct FaultHandlerConfig {
+        /// Reaction to each of the fault inputs
+        pub inputs: [Option<FaultMode>; 3],
+        /// Action on output A when tripped
+        pub action_a: Action,
+        /// Action on output B when tripped
+        pub action_b: Action,
+    }
+
+    /// Edge triggering a capture
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum CaptureEdge {
+        Rising,
+        Falling,
+        Both,
+    }
+}
+
+use config::{
+    CaptureEdge, CountMode, DeadTimeConfig, DeadTimeMode, FaultHandlerConfig, FaultMode,
+    GeneratorActions,
+};
+
+/// Generator (output) of an operator
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Generator {
+    A,
+    B,
+}
+
+/// Synchronization source of a timer
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum SyncSource {
+    /// Sync output of timer 0
+    Timer0 = 1,
+    /// Sync output of timer 1
+    Timer1 = 2,
+    /// Sync output of timer 2
+    Timer2 = 3,
+    /// External sync input 0
+    Input0 = 4,
+    /// External sync input 1
+    Input1 = 5,
+    /// External sync input 2
+    Input2 = 6,
+}
+
+/// Synchronization output of a timer
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum SyncOutput {
+    /// Pass through the sync input of the timer
+    SyncInput = 0,
+    /// Timer reaches zero
+    Zero = 1,
+    /// Timer reaches the period
+    Period = 2,
+    /// No sync output
+    None = 3,
+}
+
+use private::Instance;
+
+/// Motor control PWM peripheral split into its parts
+pub struct Mcpwm<PWM: Instance> {
+    pub timer0: Timer<PWM>,
+    pub timer1: Timer<PWM>,
+    pub timer2: Timer<PWM>,
+    pub operator0: Operator<PWM>,
+    pub operator1: Operator<PWM>,
+    pub operator2: Operator<PWM>,
+    pub sync0: SyncInput<PWM>,
+    pub sync1: SyncInput<PWM>,
+    pub sync2: SyncInput<PWM>,
+    pub fault0: FaultInput<PWM>,
+    pub fault1: FaultInput<PWM>,
+    pub fault2: FaultInput<PWM>,
+    pub capture0: CaptureChannel<PWM>,
+    pub capture1: CaptureChannel<PWM>,
+    pub capture2: CaptureChannel<PWM>,
+}
+
+impl<PWM: Instance> Mcpwm<PWM> {
+    /// Enable the motor control PWM peripheral and split it into its parts
+    pub fn new(
+        mut pwm: PWM,
+        clock_control: crate::clock_control::ClockControlConfig,
+        dport: &mut target::DPORT,
+    ) -> Self {
+        pwm.reset(dport).enable(dport);
+
+        pwm.clk.write(|w| w.clk_en().set_bit());
+        // use the 160MHz clock directly, the timers have their own prescaler
+        pwm.clk_cfg.write(|w| unsafe { w.clk_prescale().bits(0) });
+        pwm.update_cfg.write(|w| {
+            w.global_up_en()
+                .set_bit()
+                .op0_up_en()
+                .set_bit()
+                .op1_up_en()
+                .set_bit()
+                .op2_up_en()
+                .set_bit()
+        });
+        pwm.cap_timer_cfg.write(|w| w.cap_timer_en().set_bit());
+        pwm.mcmcpwm_int_ena_mcpwm.write(|w| unsafe { w.bits(0) });
+        pwm.mcmcpwm_int_clr_mcpwm
+            .write(|w| unsafe { w.bits(0x3fff_ffff) });
+
+        Mcpwm {
+            timer0: Timer::new(0, clock_control),
+            timer1: Timer::new(1, clock_control),
+            timer2: Timer::new(2, clock_control),
+            operator0: Operator::new(0),
+            operator1: Operator::new(1),
+            operator2: Operator::new(2),
+            sync0: SyncInput::new(0),
+            sync1: SyncInput::new(1),
+            sync2: SyncInput::new(2),
+            fault0: FaultInput::new(0),
+            fault1: FaultInput::new(1),
+            fault2: FaultInput::new(2),
+            capture0: CaptureChannel::new(0, clock_control),
+            capture1: CaptureChannel::new(1, clock_control),
+            capture2: CaptureChannel::new(2, clock_control),
+        }
+    }
+}
+
+/// PWM timer
+pub struct Timer<PWM: Instance> {
+    number: u8,
+    clock_control: crate::clock_control::ClockControlConfig,
+    pll_lock: Option<crate::clock_control::dfs::LockPllD2>,
+    _pwm: PhantomData<PWM>,
+}
+
+impl<PWM: Instance> Timer<PWM> {
+    fn new(number: u8, clock_control: crate::clock_control::ClockControlConfig) -> Self {
+        Timer {
+            number,
+            clock_control,
+            pll_lock: None,
+            _pwm: PhantomData,
+        }
+    }
+
+    /// Configure frequency and counting mode
+    ///
+    /// The timer is not started, see [Timer::start].
+    pub fn configure(&mut self, config: config::TimerConfig) -> Result<&mut Self, Error> {
+        let (prescaler, period) =
+            timer_divider(MCPWM_SOURCE_FREQUENCY, config.frequency, config.mode)?;
+
+        if let None = self.pll_lock {
+            self.pll_lock = Some(self.clock_control.lock_plld2());
+        }
+
+        let timer = PWM::timer(self.number);
+        timer.cfg0.write(|w| unsafe {
+            w.timer0_prescale()
+                .bits((prescaler - 1) as u8)
+                .timer0_period()
+                .bits(period as u16)
+                .timer0_period_upmethod()
+                .bits(0)
+        });
+        timer.cfg1.modify(|_, w| unsafe {
+            w.timer0_mod().bits(match config.mode {
+                CountMode::Up => 1,
+                CountMode::Down => 2,
+                CountMode::UpDown => 3,
+            })
+        });
+
+        Ok(self)
+    }
+
+    /// Start the timer (free running)
+    pub fn start(&mut self) {
+        PWM::timer(self.number)
+            .cfg1
+            .modify(|_, w| unsafe { w.timer0_start().bits(2) });
+    }
+
+    /// Stop the timer when it reaches zero
+    pub fn stop(&mut self) {
+        PWM::timer(self.number)
+            .cfg1
+            .modify(|_, w| unsafe { w.timer0_start().bits(0) });
+    }
+
+    /// Returns the counting mode
+    pub fn mode(&self) -> CountMode {
+        timer_mode(PWM::timer(self.number).cfg1.read().timer0_mod().bits())
+    }
+
+    /// Returns the period register value
+    pub fn period(&self) -> u16 {
+        PWM::timer(self.number).cfg0.read().timer0_period().bits()
+    }
+
+    /// Returns the current timer value
+    pub fn value(&self) -> u16 {
+        PWM::timer(self.number).status.read().timer0_value().bits()
+    }
+
+    /// Returns the current PWM frequency
+    pub fn frequency(&self) -> Hertz {
+        let cfg0 = PWM::timer(self.number).cfg0.read();
+        let prescaler = cfg0.timer0_prescale().bits() as u32 + 1;
+        let period = cfg0.timer0_period().bits() as u32;
+
+        let ticks = match self.mode() {
+            CountMode::UpDown => 2 * period,
+            _ => period + 1,
+        };
+
+        if ticks == 0 {
+            return Hertz(0);
+        }
+        MCPWM_SOURCE_FREQUENCY / (prescaler * ticks)
+    }
+
+    /// Load the phase into the timer on a sync event of the source
+    pub fn enable_sync(&mut self, source: SyncSource, phase: u16) -> &mut Self {
+        let number = self.number;
+        PWM::lock(|| {
+            PWM::registers().timer_synci_cfg.modify(|r, w| unsafe {
+                w.bits(r.bits() & !(0x7 << (number * 3)) | (source as u32) << (number * 3))
+            });
+        });
+
+        PWM::timer(self.number).sync.modify(|_, w| unsafe {
+            w.timer0_phase()
+                .bits(phase as u32)
+                .timer0_synci_en()
+                .set_bit()
+        });
+        self
+    }
+
+    /// Ignore sync events
+    pub fn disable_sync(&mut self) -> &mut Self {
+        PWM::timer(self.number)
+            .sync
+            .modify(|_, w| w.timer0_synci_en().clear_bit());
+        self
+    }
+
+    /// Select the sync output used by other timers
+    pub fn set_sync_output(&mut self, output: SyncOutput) -> &mut Self {
+        PWM::timer(self.number)
+            .sync
+            .modify(|_, w| unsafe { w.timer0_synco_sel().bits(output as u8) });
+        self
+    }
+
+    /// Trigger a sync event by software, loading the phase into the timer
+    pub fn software_sync(&mut self) {
+        // a sync is triggered by toggling the bit
+        PWM::timer(self.number)
+            .sync
+            .modify(|r, w| w.timer0_sync_sw().bit(!r.timer0_sync_sw().bit()));
+    }
+
+    /// Starts listening for an interrupt event
+    ///
+    /// The interrupt of all parts is `PWM0_INTR` or `PWM1_INTR`.
+    pub fn listen(&mut self, event: TimerEvent) {
+        PWM::enable_interrupt(self.interrupt_bit(event), true);
+    }
+
+    /// Stop listening for an interrupt event
+    pub fn unlisten(&mut self, event: TimerEvent) {
+        PWM::enable_interrupt(self.interrupt_bit(event), false);
+    }
+
+    /// Returns true if the interrupt for the event is set
+    pub fn is_interrupt_set(&self, event: TimerEvent) -> bool {
+        PWM::is_interrupt_set(self.interrupt_bit(event))
+    }
+
+    /// Clear the interrupt for the event
+    pub fn clear_interrupt(&mut self, event: TimerEvent) {
+        PWM::clear_interrupt(self.interrupt_bit(event));
+    }
+
+    fn interrupt_bit(&self, event: TimerEvent) -> u8 {
+        self.number
+            + match event {
+                TimerEvent::Stop => 0,
+                TimerEvent::Zero => 3,
+                TimerEvent::Period => 6,
+            }
+    }
+}
+
+/// Calculate the timer prescaler and period register value for the frequency
+fn timer_divider(source: Hertz, frequency: Hertz, mode: CountMode) -> Result<(u32, u32), Error> {
+    if frequency.0 == 0 {
+        return Err(Error::FrequencyTooLow);
+    }
+
+    // number of timer ticks during one PWM period
+    let ticks = source / frequency;
+    // ticks needed in the period register
+    let required = match mode {
+        CountMode::UpDown => ticks / 2,
+        _ => ticks,
+    };
+
+    let prescaler = core::cmp::max((required + MCPWM_MAX_PERIOD - 1) / MCPWM_MAX_PERIOD, 1);
+    if prescaler > MCPWM_MAX_PRESCALER {
+        return Err(Error::FrequencyTooLow);
+    }
+
+    let ticks = (source / prescaler + frequency / 2) / frequency;
+    let period = match mode {
+        CountMode::UpDown => ticks / 2,
+        _ => ticks.saturating_sub(1),
+    };
+
+    if period < 1 {
+        return Err(Error::FrequencyTooHigh);
+    }
+
+    Ok((prescaler, core::cmp::min(period, MCPWM_MAX_PERIOD - 1)))
+}
+
+/// Counting mode from the register value
+fn timer_mode(bits: u8) -> CountMode {
+    match bits {
+        2 => CountMode::Down,
+        3 => CountMode::UpDown,
+        _ => CountMode::Up,
+    }
+}
+
+/// Operator which is not connected to outputs
+pub struct Operator<PWM: Instance> {
+    number: u8,
+    _pwm: PhantomData<PWM>,
+}
+
+impl<PWM: Instance> Operator<PWM> {
+    fn new(number: u8) -> Self {
+        Operator {
+            number,
+            _pwm: PhantomData,
+        }
+    }
+
+    /// Connect the operator to a timer and the output pins
+    ///
+    /// Both generators are configured as [active high][GeneratorActions::active_high] with a
+    /// duty of 0 and dead time is bypassed.
+    pub fn connect<A: OutputPin, B: OutputPin>(
+        self,
+        timer: &Timer<PWM>,
+        mut pin_a: A,
+        mut pin_b: B,
+    ) -> OperatorOutputs<PWM, A, B> {
+        pin_a
+            .set_to_push_pull_output()
+            .connect_peripheral_to_output(PWM::OUTPUT_SIGNALS[self.number as usize * 2]);
+        pin_b
+            .set_to_push_pull_output()
+            .connect_peripheral_to_output(PWM::OUTPUT_SIGNALS[self.number as usize * 2 + 1]);
+
+        let mut outputs = OperatorOutputs {
+            operator: self,
+            pin_a,
+            pin_b,
+        };
+
+        let operator = PWM::operator(outputs.operator.number);
+        unsafe {
+            // update compare values when the timer reaches zero
+            operator
+                .gen_stmp_cfg
+                .write(|w| w.gen0_a_upmethod().bits(1).gen0_b_upmethod().bits(1));
+            operator.gen_tstmp_a.write(|w| w.gen0_a().bits(0));
+            operator.gen_tstmp_b.write(|w| w.gen0_b().bits(0));
+            operator.gen_cfg0.write(|w| w.bits(0));
+            operator.gen_force.write(|w| w.bits(0));
+            operator.carrier_cfg.write(|w| w.bits(0));
+        }
+
+        outputs
+            .change_timer(timer)
+            .set_actions(Generator::A, GeneratorActions::active_high(Generator::A))
+            .set_actions(Generator::B, GeneratorActions::active_high(Generator::B))
+            .set_dead_time(DeadTimeConfig {
+                mode: DeadTimeMode::Bypass,
+                rising_edge_delay: 0,
+                falling_edge_delay: 0,
+            });
+
+        outputs
+    }
+}
+
+/// Operator connected to a timer and output pins
+pub struct OperatorOutputs<PWM: Instance, A: OutputPin, B: OutputPin> {
+    operator: Operator<PWM>,
+    pin_a: A,
+    pin_b: B,
+}
+
+impl<PWM: Instance, A: OutputPin, B: OutputPin> OperatorOutputs<PWM, A, B> {
+    /// Connect the operator to another timer
+    pub fn change_timer(&mut self, timer: &Timer<PWM>) -> &mut Self {
+        let number = self.operator.number;
+        PWM::lock(|| {
+            PWM::registers().operator_timersel.modify(|r, w| unsafe {
+                w.bits(r.bits() & !(0x3 << (number * 2)) | (timer.number as u32) << (number * 2))
+            });
+        });
+        self
+    }
+
+    /// Returns the compare value which equals 100% duty with active high generators
+    pub fn get_max_duty(&self) -> u32 {
+        let timer = PWM::timer(self.timer_number());
+        let period = timer.cfg0.read().timer0_period().bits() as u32;
+
+        match timer_mode(timer.cfg1.read().timer0_mod().bits()) {
+            CountMode::UpDown => period,
+            _ => period + 1,
+        }
+    }
+
+    /// Returns the compare value of the generator
+    pub fn get_duty(&self, generator: Generator) -> u32 {
+        let operator = PWM::operator(self.operator.number);
+        match generator {
+            Generator::A => operator.gen_tstmp_a.read().gen0_a().bits() as u32,
+            Generator::B => operator.gen_tstmp_b.read().gen0_b().bits() as u32,
+        }
+    }
+
+    /// Set the compare value of the generator
+    ///
+    /// The value is taken over when the timer reaches zero. Values above the maximum duty are
+    /// clamped.
+    pub fn set_duty(&mut self, generator: Generator, duty: u32) -> &mut Self {
+        let duty = core::cmp::min(core::cmp::min(duty, self.get_max_duty()), 0xffff) as u16;
+
+        let operator = PWM::operator(self.operator.number);
+        unsafe {
+            match generator {
+                Generator::A => operator.gen_tstmp_a.write(|w| w.gen0_a().bits(duty)),
+                Generator::B => operator.gen_tstmp_b.write(|w| w.gen0_b().bits(duty)),
+            }
+        }
+        self
+    }
+
+    /// Set the actions of the generator on timer and compare events
+    pub fn set_actions(&mut self, generator: Generator, actions: GeneratorActions) -> &mut Self {
+        let operator = PWM::operator(self.operator.number);
+        unsafe {
+            match generator {
+                Generator::A => operator.gen_a.write(|w| w.bits(actions.bits())),
+                Generator::B => operator.gen_b.write(|w| w.bits(actions.bits())),
+            }
+        }
+        self
+    }
+
+    /// Force the output of the generator continuously low (`Some(false)`), high (`Some(true)`)
+    /// or release it (`None`)
+    pub fn force(&mut self, generator: Generator, level: Option<bool>) -> &mut Self {
+        let mode = match level {
+            None => 0,
+            Some(false) => 1,
+            Some(true) => 2,
+        };
+
+        PWM::operator(self.operator.number)
+            .gen_force
+            .modify(|_, w| unsafe {
+                match generator {
+                    Generator::A => w.gen0_a_cntuforce_mode().bits(mode),
+                    Generator::B => w.gen0_b_cntuforce_mode().bits(mode),
+                }
+                .gen0_cntuforce_upmethod()
+                .bits(0)
+            });
+        self
+    }
+
+    /// Configure the dead time insertion
+    pub fn set_dead_time(&mut self, config: DeadTimeConfig) -> &mut Self {
+        let bypass = config.mode == DeadTimeMode::Bypass;
+        let (invert_a, invert_b) = match config.mode {
+            DeadTimeMode::Bypass | DeadTimeMode::ActiveHigh => (false, false),
+            DeadTimeMode::ActiveLow => (true, true),
+            DeadTimeMode::ActiveHighComplementary => (false, true),
+            DeadTimeMode::ActiveLowComplementary => (true, false),
+        };
+
+        let operator = PWM::operator(self.operator.number);
+        unsafe {
+            operator
+                .dt_red_cfg
+                .write(|w| w.dt0_red().bits(config.rising_edge_delay));
+            operator
+                .dt_fed_cfg
+                .write(|w| w.dt0_fed().bits(config.falling_edge_delay));
+        }
+
+        // both delays use generator A as input, output A is delayed on the rising edge and
+        // output B on the falling edge
+        operator.dt_cfg.write(|w| unsafe {
+            w.dt0_fed_upmethod()
+                .bits(0)
+                .dt0_red_upmethod()
+                .bits(0)
+                .dt0_deb_mode()
+                .clear_bit()
+                .dt0_a_outswap()
+                .clear_bit()
+                .dt0_b_outswap()
+                .clear_bit()
+                .dt0_red_insel()
+                .clear_bit()
+                .dt0_fed_insel()
+                .clear_bit()
+                .dt0_red_outinvert()
+                .bit(invert_a)
+                .dt0_fed_outinvert()
+                .bit(invert_b)
+                .dt0_a_outbypass()
+                .bit(bypass)
+                .dt0_b_outbypass()
+                .bit(bypass)
+                .dt0_clk_sel()
+                .clear_bit()
+        });
+        self
+    }
+
+    /// Configure the fault handler
+    pub fn configure_fault_handler(&mut self, config: FaultHandlerConfig) -> &mut Self {
+        let one_shot = |index: usize| config.inputs[index] == Some(FaultMode::OneShot);
+        let cycle_by_cycle = |index: usize| config.inputs[index] == Some(FaultMode::CycleByCycle);
+        let action_a = config.action_a as u8;
+        let action_b = config.action_b as u8;
+
+        let operator = PWM::operator(self.operator.number);
+        operator.fh_cfg0.write(|w| unsafe {
+            w.fh0_f0_ost()
+                .bit(one_shot(0))
+                .fh0_f1_ost()
+                .bit(one_shot(1))
+                .fh0_f2_ost()
+                .bit(one_shot(2))
+                .fh0_f0_cbc()
+                .bit(cycle_by_cycle(0))
+                .fh0_f1_cbc()
+                .bit(cycle_by_cycle(1))
+                .fh0_f2_cbc()
+                .bit(cycle_by_cycle(2))
+                // software trips are always enabled
+                .fh0_sw_ost()
+                .set_bit()
+                .fh0_sw_cbc()
+                .set_bit()
+                .fh0_a_ost_u()
+                .bits(action_a)
+                .fh0_a_ost_d()
+                .bits(action_a)
+                .fh0_a_cbc_u()
+                .bits(action_a)
+                .fh0_a_cbc_d()
+                .bits(action_a)
+                .fh0_b_ost_u()
+                .bits(action_b)
+                .fh0_b_ost_d()
+                .bits(action_b)
+                .fh0_b_cbc_u()
+                .bits(action_b)
+                .fh0_b_cbc_d()
+                .bits(action_b)
+        });
+        // cycle-by-cycle trips end when the timer reaches zero
+        operator
+            .fh_cfg1
+            .modify(|_, w| unsafe { w.fh0_cbcpulse().bits(1) });
+        self
+    }
+
+    /// Trip the fault handler by software
+    pub fn software_trip(&mut self, mode: FaultMode) {
+        // a trip is triggered by toggling the bit
+        let operator = PWM::operator(self.operator.number);
+        match mode {
+            FaultMode::OneShot => operator
+                .fh_cfg1
+                .modify(|r, w| w.fh0_force_ost().bit(!r.fh0_force_ost().bit())),
+            FaultMode::CycleByCycle => operator
+                .fh_cfg1
+                .modify(|r, w| w.fh0_force_cbc().bit(!r.fh0_force_cbc().bit())),
+        }
+    }
+
+    /// Clear a one-shot trip
+    pub fn clear_one_shot_trip(&mut self) {
+        let operator = PWM::operator(self.operator.number);
+        operator.fh_cfg1.modify(|_, w| w.fh0_clr_ost().set_bit());
+        operator.fh_cfg1.modify(|_, w| w.fh0_clr_ost().clear_bit());
+    }
+
+    /// Returns true if the fault handler is tripped in the mode
+    pub fn is_tripped(&self, mode: FaultMode) -> bool {
+        let status = PWM::operator(self.operator.number).fh_status.read();
+        match mode {
+            FaultMode::OneShot => status.fh0_ost_on().bit_is_set(),
+            FaultMode::CycleByCycle => status.fh0_cbc_on().bit_is_set(),
+        }
+    }
+
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: OperatorEvent) {
+        PWM::enable_interrupt(self.interrupt_bit(event), true);
+    }
+
+    /// Stop listening for an interrupt event
+    pub fn unlisten(&mut self, event: OperatorEvent) {
+        PWM::enable_interrupt(self.interrupt_bit(event), false);
+    }
+
+    /// Returns true if the interrupt for the event is set
+    pub fn is_interrupt_set(&self, event: OperatorEvent) -> bool {
+        PWM::is_interrupt_set(self.interrupt_bit(event))
+    }
+
+    /// Clear the interrupt for the event
+    pub fn clear_interrupt(&mut self, event: OperatorEvent) {
+        PWM::clear_interrupt(self.interrupt_bit(event));
+    }
+
+    /// Disconnect the operator from the outputs and release the resources
+    ///
+    /// The outputs are forced low before they are released.
+    pub fn release(mut self) -> (Operator<PWM>, A, B) {
+        self.force(Generator::A, Some(false))
+            .force(Generator::B, Some(false));
+        (self.operator, self.pin_a, self.pin_b)
+    }
+
+    fn timer_number(&self) -> u8 {
+        (PWM::registers().operator_timersel.read().bits() >> (self.operator.number * 2) & 0x3) as u8
+    }
+
+    fn interrupt_bit(&self, event: OperatorEvent) -> u8 {
+        self.operator.number
+            + match event {
+                OperatorEvent::CompareA => 15,
+                OperatorEvent::CompareB => 18,
+                OperatorEvent::CycleByCycleTrip => 21,
+                OperatorEvent::OneShotTrip => 24,
+            }
+    }
+}
+
+/// External sync input which is not connected to a pin
+pub struct SyncInput<PWM: Instance> {
+    number: u8,
+    _pwm: PhantomData<PWM>,
+}
+
+impl<PWM: Instance> SyncInput<PWM> {
+    fn new(number: u8) -> Self {
+        SyncInput {
+            number,
+            _pwm: PhantomData,
+        }
+    }
+
+    /// Connect the sync input to a pin, a sync event is triggered on the rising edge (falling
+    /// edge if inverted)
+    pub fn enable<PIN: InputPin>(self, mut pin: PIN, invert: bool) -> ExternalSync<PWM, PIN> {
+        pin.set_to_input()
+            .connect_input_to_peripheral(PWM::SYNC_SIGNALS[self.number as usize]);
+
+        let mask = 1 << (9 + self.number);
+        PWM::lock(|| {
+            PWM::registers().timer_synci_cfg.modify(|r, w| unsafe {
+                w.bits(if invert {
+                    r.bits() | mask
+                } else {
+                    r.bits() & !mask
+                })
+            });
+        });
+
+        ExternalSync { input: self, pin }
+    }
+}
+
+/// External sync input connected to a pin
+pub struct ExternalSync<PWM: Instance, PIN: InputPin> {
+    input: SyncInput<PWM>,
+    pin: PIN,
+}
+
+impl<PWM: Instance, PIN: InputPin> ExternalSync<PWM, PIN> {
+    /// Release the resources
+    pub fn release(self) -> (SyncInput<PWM>, PIN) {
+        (self.input, self.pin)
+    }
+}
+
+/// Fault input which is not connected to a pin
+pub struct FaultInput<PWM: Instance> {
+    number: u8,
+    _pwm: PhantomData<PWM>,
+}
+
+impl<PWM: Instance> FaultInput<PWM> {
+    fn new(number: u8) -> Self {
+        FaultInput {
+            number,
+            _pwm: PhantomData,
+        }
+    }
+
+    /// Connect the fault input to a pin and enable fault detection
+    pub fn enable<PIN: InputPin>(self, mut pin: PIN, active_high: bool) -> Fault<PWM, PIN> {
+        pin.set_to_input()
+            .connect_input_to_peripheral(PWM::FAULT_SIGNALS[self.number as usize]);
+
+        self.configure(true, active_high);
+
+        Fault { input: self, pin }
+    }
+
+    /// Enable (bits 0-2) and polarity (bits 3-5) in the fault detect register
+    fn configure(&self, enable: bool, active_high: bool) {
+        let enable_mask = 1 << self.number;
+        let pole_mask = 1 << (3 + self.number);
+        PWM::lock(|| {
+            PWM::registers().fault_detect.modify(|r, w| unsafe {
+                let mut bits = r.bits() & !(enable_mask | pole_mask);
+                if enable {
+                    bits |= enable_mask;
+                }
+                if active_high {
+                    bits |= pole_mask;
+                }
+                w.bits(bits)
+            });
+        });
+    }
+}
+
+/// Fault input connected to a pin
+pub struct Fault<PWM: Instance, PIN: InputPin> {
+    input: FaultInput<PWM>,
+    pin: PIN,
+}
+
+impl<PWM: Instance, PIN: InputPin> Fault<PWM, PIN> {
+    /// Returns true if the fault is active
+    pub fn is_active(&self) -> bool {
+        PWM::registers().fault_detect.read().bits() & (1 << (6 + self.input.number)) != 0
+    }
+
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: FaultEvent) {
+        PWM::enable_interrupt(self.interrupt_bit(event), true);
+    }
+
+    /// Stop listening for an interrupt event
+    pub fn unlisten(&mut self, event: FaultEvent) {
+        PWM::enable_interrupt(self.interrupt_bit(event), false);
+    }
+
+    /// Returns true if the interrupt for the event is set
+    pub fn is_interrupt_set(&self, event: FaultEvent) -> bool {
+        PWM::is_interrupt_set(self.interrupt_bit(event))
+    }
+
+    /// Clear the interrupt for the event
+    pub fn clear_interrupt(&mut self, event: FaultEvent) {
+        PWM::clear_interrupt(self.interrupt_bit(event));
+    }
+
+    /// Disable fault detection and release the resources
+    pub fn release(mut self) -> (FaultInput<PWM>, PIN) {
+        self.unlisten(FaultEvent::Active);
+        self.unlisten(FaultEvent::Cleared);
+        self.input.configure(false, false);
+        (self.input, self.pin)
+    }
+
+    fn interrupt_bit(&self, event: FaultEvent) -> u8 {
+        self.input.number
+            + match event {
+                FaultEvent::Active => 9,
+                FaultEvent::Cleared => 12,
+            }
+    }
+}
+
+/// Capture channel which is not connected to a pin
+pub struct CaptureChannel<PWM: Instance> {
+    number: u8,
+    clock_control: crate::clock_control::ClockControlConfig,
+    _pwm: PhantomData<PWM>,
+}
+
+impl<PWM: Instance> CaptureChannel<PWM> {
+    fn new(number: u8, clock_control: crate::clock_control::ClockControlConfig) -> Self {
+        CaptureChannel {
+            number,
+            clock_control,
+            _pwm: PhantomData,
+        }
+    }
+
+    /// Connect the capture channel to a pin and start capturing the edges
+    pub fn enable<PIN: InputPin>(self, mut pin: PIN, edge: CaptureEdge) -> Capture<PWM, PIN> {
+        pin.set_to_input()
+            .connect_input_to_peripheral(PWM::CAPTURE_SIGNALS[self.number as usize]);
+
+        let mode = match edge {
+            CaptureEdge::Falling => 1,
+            CaptureEdge::Rising => 2,
+            CaptureEdge::Both => 3,
+        };
+
+        PWM::capture_config(self.number).write(|w| unsafe {
+            w.cap0_mode()
+                .bits(mode)
+                .cap0_prescale()
+                .bits(0)
+                .cap0_en()
+                .set_bit()
+        });
+
+        Capture {
+            _apb_lock: self.clock_control.lock_apb_frequency(),
+            channel: self,
+            pin,
+        }
+    }
+}
+
+/// Capture channel connected to a pin
+pub struct Capture<PWM: Instance, PIN: InputPin> {
+    channel: CaptureChannel<PWM>,
+    pin: PIN,
+    _apb_lock: crate::clock_control::dfs::LockAPB,
+}
+
+impl<PWM: Instance, PIN: InputPin> Capture<PWM, PIN> {
+    /// Returns the value of the capture timer at the last captured edge
+    pub fn value(&self) -> u32 {
+        PWM::capture_value(self.channel.number)
+            .read()
+            .cap0_value()
+            .bits()
+    }
+
+    /// Returns the last captured edge (rising or falling)
+    pub fn last_edge(&self) -> CaptureEdge {
+        if PWM::registers().cap_status.read().bits() & (1 << self.channel.number) != 0 {
+            CaptureEdge::Falling
+        } else {
+            CaptureEdge::Rising
+        }
+    }
+
+    /// Returns the frequency of the capture timer (APB clock)
+    pub fn timer_frequency(&self) -> Hertz {
+        self.channel.clock_control.apb_frequency_apb_locked()
+    }
+
+    /// Capture the current value of the capture timer by software
+    pub fn software_capture(&mut self) {
+        PWM::capture_config(self.channel.number).modify(|_, w| w.cap0_sw().set_bit());
+    }
+
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: CaptureEvent) {
+        PWM::enable_interrupt(self.interrupt_bit(event), true);
+    }
+
+    /// Stop listening for an interrupt event
+    pub fn unlisten(&mut self, event: CaptureEvent) {
+        PWM::enable_interrupt(self.interrupt_bit(event), false);
+    }
+
+    /// Returns true if the interrupt for the event is set
+    pub fn is_interrupt_set(&self, event: CaptureEvent) -> bool {
+        PWM::is_interrupt_set(self.interrupt_bit(event))
+    }
+
+    /// Clear the interrupt for the event
+    pub fn clear_interrupt(&mut self, event: CaptureEvent) {
+        PWM::clear_interrupt(self.interrupt_bit(event));
+    }
+
+    /// Stop capturing and release the resources
+    pub fn release(mut self) -> (CaptureChannel<PWM>, PIN) {
+        self.unlisten(CaptureEvent::Captured);
+        PWM::capture_config(self.channel.number).modify(|_, w| w.cap0_en().clear_bit());
+        (self.channel, self.pin)
+    }
+
+    fn interrupt_bit(&self, event: CaptureEvent) -> u8 {
+        self.channel.number
+            + match event {
+                CaptureEvent::Captured => 27,
+            }
+    }
+}
+
+mod private {
+    use crate::gpio::{InputSignal, OutputSignal};
+    use crate::prelude::*;
+    use crate::target::{self, mcpwm, PWM0, PWM1};
+    use core::ops::Deref;
+
+    /// Registers of a timer (same layout for all timers)
+    #[repr(C)]
+    pub struct TimerRegisters {
+        pub cfg0: mcpwm::TIMER0_CFG0,
+        pub cfg1: mcpwm::TIMER0_CFG1,
+        pub sync: mcpwm::TIMER0_SYNC,
+        pub status: mcpwm::TIMER0_STATUS,
+    }
+
+    /// Registers of an operator (same layout for all operators)
+    #[repr(C)]
+    pub struct OperatorRegisters {
+        pub gen_stmp_cfg: mcpwm::GEN0_STMP_CFG,
+        pub gen_tstmp_a: mcpwm::GEN0_TSTMP_A,
+        pub gen_tstmp_b: mcpwm::GEN0_TSTMP_B,
+        pub gen_cfg0: mcpwm::GEN0_CFG0,
+        pub gen_force: mcpwm::GEN0_FORCE,
+        pub gen_a: mcpwm::GEN0_A,
+        pub gen_b: mcpwm::GEN0_B,
+        pub dt_cfg: mcpwm::DT0_CFG,
+        pub dt_fed_cfg: mcpwm::DT0_FED_CFG,
+        pub dt_red_cfg: mcpwm::DT0_RED_CFG,
+        pub carrier_cfg: mcpwm::CARRIER0_CFG,
+        pub fh_cfg0: mcpwm::FH0_CFG0,
+        pub fh_cfg1: mcpwm::FH0_CFG1,
+        pub fh_status: mcpwm::FH0_STATUS,
+    }
+
+    /// Offset of the registers of timer 0
+    const TIMER_OFFSET: usize = 0x04;
+    /// Offset between the registers of consecutive timers
+    const TIMER_STRIDE: usize = 0x10;
+    /// Offset of the registers of operator 0
+    const OPERATOR_OFFSET: usize = 0x3c;
+    /// Offset between the registers of consecutive operators
+    const OPERATOR_STRIDE: usize = 0x38;
+    /// Offset of the configuration register of capture channel 0
+    const CAPTURE_CONFIG_OFFSET: usize = 0xf0;
+    /// Offset of the value register of capture channel 0
+    const CAPTURE_VALUE_OFFSET: usize = 0xfc;
+
+    static MCPWM_LOCK: CriticalSectionSpinLockMutex<()> = CriticalSectionSpinLockMutex::new(());
+
+    pub trait Instance: Deref<Target = mcpwm::RegisterBlock> {
+        /// Output signals (operator 0 A, operator 0 B, operator 1 A, ...)
+        const OUTPUT_SIGNALS: [OutputSignal; 6];
+        /// Sync input signals
+        const SYNC_SIGNALS: [InputSignal; 3];
+        /// Fault input signals
+        const FAULT_SIGNALS: [InputSignal; 3];
+        /// Capture input signals
+        const CAPTURE_SIGNALS: [InputSignal; 3];
+
+        fn ptr() -> *const mcpwm::RegisterBlock;
+        /// Enable peripheral
+        fn enable(&mut self, dport: &mut target::DPORT) -> &mut Self;
+        /// Disable peripheral
+        fn disable(&mut self, dport: &mut target::DPORT) -> &mut Self;
+        /// Reset peripheral
+        fn reset(&mut self, dport: &mut target::DPORT) -> &mut Self;
+
+        fn registers() -> &'static mcpwm::RegisterBlock {
+            unsafe { &*Self::ptr() }
+        }
+
+        fn timer(timer: u8) -> &'static TimerRegisters {
+            unsafe {
+                &*((Self::ptr() as usize + TIMER_OFFSET + timer as usize * TIMER_STRIDE)
+                    as *const TimerRegisters)
+            }
+        }
+
+        fn operator(operator: u8) -> &'static OperatorRegisters {
+            unsafe {
+                &*((Self::ptr() as usize + OPERATOR_OFFSET + operator as usize * OPERATOR_STRIDE)
+                    as *const OperatorRegisters)
+            }
+        }
+
+        fn capture_config(channel: u8) -> &'static mcpwm::CAP_CH0_CFG {
+            unsafe {
+                &*((Self::ptr() as usize + CAPTURE_CONFIG_OFFSET + channel as usize * 4)
+                    as *const mcpwm::CAP_CH0_CFG)
+            }
+        }
+
+        fn capture_value(channel: u8) -> &'static mcpwm::CAP_CH0 {
+            unsafe {
+                &*((Self::ptr() as usize + CAPTURE_VALUE_OFFSET + channel as usize * 4)
+                    as *const mcpwm::CAP_CH0)
+            }
+        }
+
+        /// Modify registers shared between the parts
+        fn lock<F: FnOnce()>(f: F) {
+            (&MCPWM_LOCK).lock(|_| f());
+        }
+
+        fn enable_interrupt(bit: u8, enable: bool) {
+            Self::lock(|| {
+                Self::registers()
+                    .mcmcpwm_int_ena_mcpwm
+                    .modify(|r, w| unsafe {
+                        w.bits(if enable {
+                            r.bits() | 1 << bit
+                        } else {
+                            r.bits() & !(1 << bit)
+                        })
+                    });
+            });
+        }
+
+        fn is_interrupt_set(bit: u8) -> bool {
+            Self::registers().mcmcpwm_int_raw_mcpwm.read().bits() & (1 << bit) != 0
+        }
+
+        fn clear_interrupt(bit: u8) {
+            Self::registers()
+                .mcmcpwm_int_clr_mcpwm
+                .write(|w| unsafe { w.bits(1 << bit) });
+        }
+    }
+
+    macro_rules! halMcpwm {
+        ($(
+            $PWMX:ident: ($pwmX:ident, [$($output:ident),+], [$($sync:ident),+],
+                [$($fault:ident),+], [$($capture:ident),+]),
+        )+) => {
+            $(
+                impl Instance for $PWMX {
+                    const OUTPUT_SIGNALS: [OutputSignal; 6] = [$(OutputSignal::$output),+];
+                    const SYNC_SIGNALS: [InputSignal; 3] = [$(InputSignal::$sync),+];
+                    const FAULT_SIGNALS: [InputSignal; 3] = [$(InputSignal::$fault),+];
+                    const CAPTURE_SIGNALS: [InputSignal; 3] = [$(InputSignal::$capture),+];
+
+                    fn ptr() -> *const mcpwm::RegisterBlock {
+                        $PWMX::ptr()
+                    }
+
+                    fn reset(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                        dport.perip_rst_en.modify(|_, w| w.$pwmX().set_bit());
+                        dport.perip_rst_en.modify(|_, w| w.$pwmX().clear_bit());
+                        self
+                    }
+
+                    fn enable(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                        dport.perip_clk_en.modify(|_, w| w.$pwmX().set_bit());
+                        dport.perip_rst_en.modify(|_, w| w.$pwmX().clear_bit());
+                        self
+                    }
+
+                    fn disable(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                        dport.perip_clk_en.modify(|_, w| w.$pwmX().clear_bit());
+                        dport.perip_rst_en.modify(|_, w| w.$pwmX().set_bit());
+                        self
+                    }
+                }
+            )+
+        }
+    }
+
+    halMcpwm! {
+        PWM0: (pwm0, [PWM0_0A, PWM0_0B, PWM0_1A, PWM0_1B, PWM0_2A, PWM0_2B],
+            [PWM0_SYNC0, PWM0_SYNC1, PWM0_SYNC2], [PWM0_F0, PWM0_F1, PWM0_F2],
+            [PWM0_CAP0, PWM0_CAP1, PWM0_CAP2]),
+        PWM1: (pwm1, [PWM1_0A, PWM1_0B, PWM1_1A, PWM1_1B, PWM1_2A, PWM1_2B],
+            [PWM1_SYNC0, PWM1_SYNC1, PWM1_SYNC2], [PWM1_F0, PWM1_F1, PWM1_F2],
+            [PWM1_CAP0, PWM1_CAP1, PWM1_CAP2]),
+    }
+}
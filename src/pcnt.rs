@@ -0,0 +1,696 @@
+//! Pulse counter (PCNT)
+//!
+//! The pulse counter has 8 units with a signed 16-bit counter. Each unit has 2 channels, which
+//! count the edges of their signal input. The direction of counting can be changed by the level
+//! of the control input of the channel.
+//!
+//! The counter is reset to zero when it reaches the high or low limit. Reaching a limit, zero
+//! or one of the two thresholds generates an event, which can trigger the `PCNT_INTR`
+//! interrupt (see [interrupt::enable][crate::interrupt]).
+//!
+//! For rotary encoders [QuadratureEncoder] counts all edges of both signals and extends the
+//! counter to an i64 position.
+//!
+//! # Example
+//!
+//! Counting the rising edges of a flow meter, generating an event every 1000 pulses.
+//! ```
+//! let pcnt = Pcnt::new(dp.PCNT, clkcntrl_config, &mut dport);
+//!
+//! let mut flow = pcnt
+//!     .unit0
+//!     .into_counter(
+//!         Pins {
+//!             signal0: pins.gpio4,
+//!             control0: None,
+//!             signal1: None,
+//!             control1: None,
+//!         },
+//!         UnitConfig {
+//!             high_limit: 1000,
+//!             filter: Some(100),
+//!             ..UnitConfig::default()
+//!         },
+//!     )
+//!     .unwrap();
+//!
+//! flow.listen(Event::HighLimit);
+//! flow.resume();
+//! ```
+//!
+//! # TODO
+//! - Release of the peripheral
+
+use crate::gpio::{InputPin, InputSignal};
+use crate::prelude::*;
+use crate::target;
+
+/// Number of units
+const PCNT_UNITS: usize = 8;
+/// Maximum value of the glitch filter in APB cycles
+const PCNT_MAX_FILTER: u16 = 1023;
+
+/// Offset between the configuration registers of consecutive units
+const CONF_STRIDE: usize = 0x0c;
+/// Offset of the counter register of unit 0
+const COUNT_OFFSET: usize = 0x60;
+/// Offset of the status register of unit 0
+const STATUS_OFFSET: usize = 0x90;
+
+/// Input signals of the units (signal 0, control 0, signal 1, control 1)
+const INPUT_SIGNALS: [[InputSignal; 4]; PCNT_UNITS] = [
+    [
+        InputSignal::PCNT_SIG_CH0_0,
+        InputSignal::PCNT_CTRL_CH0_0,
+        InputSignal::PCNT_SIG_CH1_0,
+        InputSignal::PCNT_CTRL_CH1_0,
+    ],
+    [
+        InputSignal::PCNT_SIG_CH0_1,
+        InputSignal::PCNT_CTRL_CH0_1,
+        InputSignal::PCNT_SIG_CH1_1,
+        InputSignal::PCNT_CTRL_CH1_1,
+    ],
+    [
+        InputSignal::PCNT_SIG_CH0_2,
+        InputSignal::PCNT_CTRL_CH0_2,
+        InputSignal::PCNT_SIG_CH1_2,
+        InputSignal::PCNT_CTRL_CH1_2,
+    ],
+    [
+        InputSignal::PCNT_SIG_CH0_3,
+        InputSignal::PCNT_CTRL_CH0_3,
+        InputSignal::PCNT_SIG_CH1_3,
+        InputSignal::PCNT_CTRL_CH1_3,
+    ],
+    [
+        InputSignal::PCNT_SIG_CH0_4,
+        InputSignal::PCNT_CTRL_CH0_4,
+        InputSignal::PCNT_SIG_CH1_4,
+        InputSignal::PCNT_CTRL_CH1_4,
+    ],
+    [
+        InputSignal::PCNT_SIG_CH0_5,
+        InputSignal::PCNT_CTRL_CH0_5,
+        InputSignal::PCNT_SIG_CH1_5,
+        InputSignal::PCNT_CTRL_CH1_5,
+    ],
+    [
+        InputSignal::PCNT_SIG_CH0_6,
+        InputSignal::PCNT_CTRL_CH0_6,
+        InputSignal::PCNT_SIG_CH1_6,
+        InputSignal::PCNT_CTRL_CH1_6,
+    ],
+    [
+        InputSignal::PCNT_SIG_CH0_7,
+        InputSignal::PCNT_CTRL_CH0_7,
+        InputSignal::PCNT_SIG_CH1_7,
+        InputSignal::PCNT_CTRL_CH1_7,
+    ],
+];
+
+static PCNT_LOCK: CriticalSectionSpinLockMutex<()> = CriticalSectionSpinLockMutex::new(());
+
+/// Pulse counter error
+#[derive(Debug)]
+pub enum Error {
+    /// Glitch filter above 1023 APB cycles
+    InvalidFilter,
+    /// High limit not positive or low limit not negative
+    InvalidLimits,
+}
+
+/// Pulse counter event
+///
+/// All events of a unit share one interrupt.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Event {
+    /// Counter reached threshold 0
+    Threshold0,
+    /// Counter reached threshold 1
+    Threshold1,
+    /// Counter reached the low limit
+    LowLimit,
+    /// Counter reached the high limit
+    HighLimit,
+    /// Counter reached zero
+    Zero,
+}
+
+/// Pulse counter configuration
+pub mod config {
+    /// Action on an edge of the signal input
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum EdgeAction {
+        /// Don't count
+        Hold = 0,
+        /// Increment the counter
+        Increment = 1,
+        /// Decrement the counter
+        Decrement = 2,
+    }
+
+    /// Modification of the edge action by the level of the control input
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum LevelAction {
+        /// Keep the edge action
+        Keep = 0,
+        /// Reverse the edge action (increment becomes decrement and vice versa)
+        Reverse = 1,
+        /// Don't count
+        Hold = 2,
+    }
+
+    /// Channel configuration
+    #[derive(Copy, Clone, Debug)]
+    pub struct ChannelConfig {
+        pub rising_edge: EdgeAction,
+        pub falling_edge: EdgeAction,
+        /// Action when the control input is high
+        pub control_high: LevelAction,
+        /// Action when the control input is low (or not connected)
+        pub control_low: LevelAction,
+    }
+
+    impl ChannelConfig {
+        /// Channel which does not count
+        pub const DISABLED: ChannelConfig = ChannelConfig {
+            rising_edge: EdgeAction::Hold,
+            falling_edge: EdgeAction::Hold,
+            control_high: LevelAction::Keep,
+            control_low: LevelAction::Keep,
+        };
+    }
+
+    impl Default for ChannelConfig {
+        /// Count rising edges up
+        fn default() -> ChannelConfig {
+            ChannelConfig {
+                rising_edge: EdgeAction::Increment,
+                falling_edge: EdgeAction::Hold,
+                control_high: LevelAction::Keep,
+                control_low: LevelAction::Keep,
+            }
+        }
+    }
+
+    /// Unit configuration
+    #[derive(Copy, Clone, Debug)]
+    pub struct UnitConfig {
+        pub channel0: ChannelConfig,
+        pub channel1: ChannelConfig,
+        /// Pulses shorter than the filter in APB cycles are ignored (max. 1023)
+        pub filter: Option<u16>,
+        /// Counter is reset when reaching the high limit (must be positive)
+        pub high_limit: i16,
+        /// Counter is reset when reaching the low limit (must be negative)
+        pub low_limit: i16,
+        pub threshold0: i16,
+        pub threshold1: i16,
+    }
+
+    impl Default for UnitConfig {
+        fn default() -> UnitConfig {
+            UnitConfig {
+                channel0: ChannelConfig::default(),
+                channel1: ChannelConfig::DISABLED,
+                filter: None,
+                high_limit: i16::MAX,
+                low_limit: i16::MIN,
+                threshold0: 0,
+                threshold1: 0,
+            }
+        }
+    }
+}
+
+use config::{ChannelConfig, EdgeAction, LevelAction, UnitConfig};
+
+/// Pins of a unit
+pub struct Pins<
+    SIG0: InputPin,
+    // default pins to allow type inference
+    CTRL0: InputPin = crate::gpio::Gpio19<crate::gpio::Input<crate::gpio::Floating>>,
+    SIG1: InputPin = crate::gpio::Gpio19<crate::gpio::Input<crate::gpio::Floating>>,
+    CTRL1: InputPin = crate::gpio::Gpio19<crate::gpio::Input<crate::gpio::Floating>>,
+> {
+    pub signal0: SIG0,
+    pub control0: Option<CTRL0>,
+    pub signal1: Option<SIG1>,
+    pub control1: Option<CTRL1>,
+}
+
+/// Pulse counter peripheral split into its units
+pub struct Pcnt {
+    pub unit0: Unit,
+    pub unit1: Unit,
+    pub unit2: Unit,
+    pub unit3: Unit,
+    pub unit4: Unit,
+    pub unit5: Unit,
+    pub unit6: Unit,
+    pub unit7: Unit,
+}
+
+impl Pcnt {
+    /// Enable the pulse counter and split it into its units
+    pub fn new(
+        pcnt: target::PCNT,
+        clock_control: crate::clock_control::ClockControlConfig,
+        dport: &mut target::DPORT,
+    ) -> Self {
+        dport.perip_rst_en.modify(|_, w| w.pulse_cnt().set_bit());
+        dport.perip_clk_en.modify(|_, w| w.pulse_cnt().set_bit());
+        dport.perip_rst_en.modify(|_, w| w.pulse_cnt().clear_bit());
+
+        // all counters paused and in reset
+        pcnt.ctrl.write(|w| unsafe { w.bits(0xffff) });
+        pcnt.int_ena.write(|w| unsafe { w.bits(0) });
+        pcnt.int_clr.write(|w| unsafe { w.bits(0xff) });
+
+        Pcnt {
+            unit0: Unit::new(0, clock_control),
+            unit1: Unit::new(1, clock_control),
+            unit2: Unit::new(2, clock_control),
+            unit3: Unit::new(3, clock_control),
+            unit4: Unit::new(4, clock_control),
+            unit5: Unit::new(5, clock_control),
+            unit6: Unit::new(6, clock_control),
+            unit7: Unit::new(7, clock_control),
+        }
+    }
+}
+
+/// Unit which is not connected to pins
+pub struct Unit {
+    number: u8,
+    clock_control: crate::clock_control::ClockControlConfig,
+}
+
+impl Unit {
+    fn new(number: u8, clock_control: crate::clock_control::ClockControlConfig) -> Self {
+        Unit {
+            number,
+            clock_control,
+        }
+    }
+
+    /// Connect the unit to the pins and configure it
+    ///
+    /// The counter is cleared and paused, see [Counter::resume].
+    pub fn into_counter<SIG0: InputPin, CTRL0: InputPin, SIG1: InputPin, CTRL1: InputPin>(
+        self,
+        mut pins: Pins<SIG0, CTRL0, SIG1, CTRL1>,
+        config: UnitConfig,
+    ) -> Result<Counter<SIG0, CTRL0, SIG1, CTRL1>, Error> {
+        let signals = &INPUT_SIGNALS[self.number as usize];
+
+        pins.signal0
+            .set_to_input()
+            .connect_input_to_peripheral(signals[0]);
+        if let Some(control0) = &mut pins.control0 {
+            control0
+                .set_to_input()
+                .connect_input_to_peripheral(signals[1]);
+        }
+        if let Some(signal1) = &mut pins.signal1 {
+            signal1
+                .set_to_input()
+                .connect_input_to_peripheral(signals[2]);
+        }
+        if let Some(control1) = &mut pins.control1 {
+            control1
+                .set_to_input()
+                .connect_input_to_peripheral(signals[3]);
+        }
+
+        let mut counter = Counter {
+            unit: self,
+            pins,
+            apb_lock: None,
+        };
+        counter.configure(config)?;
+
+        Ok(counter)
+    }
+}
+
+/// Unit connected to pins
+pub struct Counter<
+    SIG0: InputPin,
+    CTRL0: InputPin = crate::gpio::Gpio19<crate::gpio::Input<crate::gpio::Floating>>,
+    SIG1: InputPin = crate::gpio::Gpio19<crate::gpio::Input<crate::gpio::Floating>>,
+    CTRL1: InputPin = crate::gpio::Gpio19<crate::gpio::Input<crate::gpio::Floating>>,
+> {
+    unit: Unit,
+    pins: Pins<SIG0, CTRL0, SIG1, CTRL1>,
+    apb_lock: Option<crate::clock_control::dfs::LockAPB>,
+}
+
+impl<SIG0: InputPin, CTRL0: InputPin, SIG1: InputPin, CTRL1: InputPin>
+    Counter<SIG0, CTRL0, SIG1, CTRL1>
+{
+    /// Change the configuration
+    ///
+    /// The counter is cleared and paused, see [Counter::resume].
+    pub fn configure(&mut self, config: UnitConfig) -> Result<&mut Self, Error> {
+        if config.high_limit <= 0 || config.low_limit >= 0 {
+            return Err(Error::InvalidLimits);
+        }
+
+        let filter = match config.filter {
+            Some(filter) if filter > PCNT_MAX_FILTER => return Err(Error::InvalidFilter),
+            Some(filter) => {
+                // the filter is clocked by the APB clock
+                if let None = self.apb_lock {
+                    self.apb_lock = Some(self.unit.clock_control.lock_apb_frequency());
+                }
+                filter
+            }
+            None => {
+                self.apb_lock = None;
+                0
+            }
+        };
+
+        self.pause();
+
+        let registers = self.registers();
+        let channel0 = config.channel0;
+        let channel1 = config.channel1;
+        registers.conf0.modify(|_, w| unsafe {
+            w.ch0_neg_mode_u0()
+                .bits(channel0.falling_edge as u8)
+                .ch0_pos_mode_u0()
+                .bits(channel0.rising_edge as u8)
+                .ch0_hctrl_mode_u0()
+                .bits(channel0.control_high as u8)
+                .ch0_lctrl_mode_u0()
+                .bits(channel0.control_low as u8)
+                .ch1_neg_mode_u0()
+                .bits(channel1.falling_edge as u8)
+                .ch1_pos_mode_u0()
+                .bits(channel1.rising_edge as u8)
+                .ch1_hctrl_mode_u0()
+                .bits(channel1.control_high as u8)
+                .ch1_lctrl_mode_u0()
+                .bits(channel1.control_low as u8)
+                .filter_en_u0()
+                .bit(config.filter.is_some())
+                .filter_thres_u0()
+                .bits(filter)
+        });
+        registers.conf1.write(|w| unsafe {
+            w.cnt_thres0_u0()
+                .bits(config.threshold0 as u16)
+                .cnt_thres1_u0()
+                .bits(config.threshold1 as u16)
+        });
+        registers.conf2.write(|w| unsafe {
+            w.cnt_h_lim_u0()
+                .bits(config.high_limit as u16)
+                .cnt_l_lim_u0()
+                .bits(config.low_limit as u16)
+        });
+
+        // the limits and thresholds are taken over on reset
+        self.clear();
+
+        Ok(self)
+    }
+
+    /// Change threshold 0 or 1
+    ///
+    /// The counter is cleared.
+    pub fn set_threshold(&mut self, threshold: u8, value: i16) -> &mut Self {
+        self.registers().conf1.modify(|_, w| unsafe {
+            if threshold == 0 {
+                w.cnt_thres0_u0().bits(value as u16)
+            } else {
+                w.cnt_thres1_u0().bits(value as u16)
+            }
+        });
+        self.clear();
+        self
+    }
+
+    /// Returns the counter value
+    pub fn count(&self) -> i16 {
+        self.count_register().read().plus_cnt_u0().bits() as i16
+    }
+
+    /// Reset the counter to zero
+    pub fn clear(&mut self) {
+        let mask = 1 << (self.unit.number * 2);
+        self.modify_control(mask, true);
+        self.modify_control(mask, false);
+    }
+
+    /// Stop counting
+    pub fn pause(&mut self) {
+        self.modify_control(1 << (self.unit.number * 2 + 1), true);
+    }
+
+    /// Start or continue counting
+    pub fn resume(&mut self) {
+        self.modify_control(1 << (self.unit.number * 2 + 1), false);
+    }
+
+    /// Returns true if the event caused the last interrupt
+    ///
+    /// Only one event is stored, so the status should be read in the interrupt handler.
+    pub fn is_event_set(&self, event: Event) -> bool {
+        let status = self.status_register().read().core_status_u0().bits();
+        status & event_status_mask(event) != 0
+    }
+
+    /// Enable the event to trigger the interrupt of the unit
+    pub fn listen(&mut self, event: Event) {
+        self.enable_event(event, true);
+        self.enable_interrupt(true);
+    }
+
+    /// Disable the event
+    ///
+    /// The interrupt of the unit is disabled, when no event is enabled anymore.
+    pub fn unlisten(&mut self, event: Event) {
+        self.enable_event(event, false);
+
+        // bits 11-15: zero, high limit, low limit, threshold 0, threshold 1
+        if self.registers().conf0.read().bits() & (0x1f << 11) == 0 {
+            self.enable_interrupt(false);
+        }
+    }
+
+    /// Returns true if the interrupt of the unit is set
+    pub fn is_interrupt_set(&self) -> bool {
+        let pcnt = unsafe { &*target::PCNT::ptr() };
+        pcnt.int_raw.read().bits() & (1 << self.unit.number) != 0
+    }
+
+    /// Clear the interrupt of the unit
+    pub fn clear_interrupt(&mut self) {
+        let pcnt = unsafe { &*target::PCNT::ptr() };
+        pcnt.int_clr
+            .write(|w| unsafe { w.bits(1 << self.unit.number) });
+    }
+
+    /// Stop counting and release the unit and pins
+    pub fn release(mut self) -> (Unit, Pins<SIG0, CTRL0, SIG1, CTRL1>) {
+        self.pause();
+        self.enable_interrupt(false);
+        (self.unit, self.pins)
+    }
+
+    fn enable_event(&mut self, event: Event, enable: bool) {
+        self.registers().conf0.modify(|_, w| match event {
+            Event::Threshold0 => w.thr_thres0_en_u0().bit(enable),
+            Event::Threshold1 => w.thr_thres1_en_u0().bit(enable),
+            Event::LowLimit => w.thr_l_lim_en_u0().bit(enable),
+            Event::HighLimit => w.thr_h_lim_en_u0().bit(enable),
+            Event::Zero => w.thr_zero_en_u0().bit(enable),
+        });
+    }
+
+    fn enable_interrupt(&mut self, enable: bool) {
+        let pcnt = unsafe { &*target::PCNT::ptr() };
+        let mask = 1 << self.unit.number;
+
+        (&PCNT_LOCK).lock(|_| {
+            pcnt.int_ena.modify(|r, w| unsafe {
+                w.bits(if enable {
+                    r.bits() | mask
+                } else {
+                    r.bits() & !mask
+                })
+            });
+        });
+    }
+
+    /// Modify the shared control register (reset and pause bits)
+    fn modify_control(&mut self, mask: u32, set: bool) {
+        let pcnt = unsafe { &*target::PCNT::ptr() };
+
+        (&PCNT_LOCK).lock(|_| {
+            pcnt.ctrl.modify(|r, w| unsafe {
+                w.bits(if set {
+                    r.bits() | mask
+                } else {
+                    r.bits() & !mask
+                })
+            });
+        });
+    }
+
+    fn registers(&self) -> &'static UnitRegisters {
+        unsafe {
+            &*((target::PCNT::ptr() as usize + self.unit.number as usize * CONF_STRIDE)
+                as *const UnitRegisters)
+        }
+    }
+
+    fn count_register(&self) -> &'static target::pcnt::U0_CNT {
+        unsafe {
+            &*((target::PCNT::ptr() as usize + COUNT_OFFSET + self.unit.number as usize * 4)
+                as *const target::pcnt::U0_CNT)
+        }
+    }
+
+    fn status_register(&self) -> &'static target::pcnt::U0_STATUS {
+        unsafe {
+            &*((target::PCNT::ptr() as usize + STATUS_OFFSET + self.unit.number as usize * 4)
+                as *const target::pcnt::U0_STATUS)
+        }
+    }
+}
+
+/// Configuration registers of a unit (same layout for all units)
+#[repr(C)]
+struct UnitRegisters {
+    conf0: target::pcnt::U0_CONF0,
+    conf1: target::pcnt::U0_CONF1,
+    conf2: target::pcnt::U0_CONF2,
+}
+
+/// Bit of the event in the status register
+fn event_status_mask(event: Event) -> u32 {
+    match event {
+        Event::Threshold1 => 1 << 2,
+        Event::Threshold0 => 1 << 3,
+        Event::LowLimit => 1 << 4,
+        Event::HighLimit => 1 << 5,
+        Event::Zero => 1 << 6,
+    }
+}
+
+/// Quadrature decoder for rotary encoders
+///
+/// Counts all edges of both signals (4 counts per cycle). The counter of the unit is extended to
+/// an i64 position by accumulating the limits on overflow. Either [QuadratureEncoder::update] or
+/// [QuadratureEncoder::position] needs to be called at least once per 16384 counts, e.g. from
+/// the `PCNT_INTR` interrupt handler after calling [QuadratureEncoder::listen].
+pub struct QuadratureEncoder<A: InputPin, B: InputPin> {
+    counter: Counter<A, B>,
+    /// Position at counter value zero
+    offset: i64,
+}
+
+/// Limit of the counter of the quadrature decoder
+const QUADRATURE_LIMIT: i16 = 16384;
+
+impl<A: InputPin, B: InputPin> QuadratureEncoder<A, B> {
+    /// Create a quadrature decoder on the pins
+    ///
+    /// Channel 0 counts the edges of A controlled by B, channel 1 the edges of B controlled by
+    /// A.
+    pub fn new(unit: Unit, a: A, b: B, filter: Option<u16>) -> Result<Self, Error> {
+        let mut counter = unit.into_counter(
+            Pins {
+                signal0: a,
+                control0: Some(b),
+                signal1: None,
+                control1: None,
+            },
+            UnitConfig {
+                channel0: ChannelConfig {
+                    rising_edge: EdgeAction::Decrement,
+                    falling_edge: EdgeAction::Increment,
+                    control_high: LevelAction::Keep,
+                    control_low: LevelAction::Reverse,
+                },
+                channel1: ChannelConfig {
+                    rising_edge: EdgeAction::Increment,
+                    falling_edge: EdgeAction::Decrement,
+                    control_high: LevelAction::Keep,
+                    control_low: LevelAction::Reverse,
+                },
+                filter,
+                high_limit: QUADRATURE_LIMIT,
+                low_limit: -QUADRATURE_LIMIT,
+                ..UnitConfig::default()
+            },
+        )?;
+
+        // the pins are routed to the inputs of channel 1 as well
+        let signals = &INPUT_SIGNALS[counter.unit.number as usize];
+        counter.pins.signal0.connect_input_to_peripheral(signals[3]);
+        if let Some(b) = &mut counter.pins.control0 {
+            b.connect_input_to_peripheral(signals[2]);
+        }
+
+        counter.enable_event(Event::HighLimit, true);
+        counter.enable_event(Event::LowLimit, true);
+        counter.clear_interrupt();
+        counter.resume();
+
+        Ok(QuadratureEncoder { counter, offset: 0 })
+    }
+
+    /// Returns the position in counts
+    pub fn position(&mut self) -> i64 {
+        loop {
+            self.update();
+            let count = self.counter.count();
+
+            // retry if the counter overflowed while reading
+            if !self.counter.is_interrupt_set() {
+                return self.offset + count as i64;
+            }
+        }
+    }
+
+    /// Set the current position
+    pub fn set_position(&mut self, position: i64) {
+        self.counter.clear();
+        self.counter.clear_interrupt();
+        self.offset = position;
+    }
+
+    /// Accumulate an overflow of the counter into the position
+    pub fn update(&mut self) {
+        if self.counter.is_interrupt_set() {
+            if self.counter.is_event_set(Event::HighLimit) {
+                self.offset += QUADRATURE_LIMIT as i64;
+            } else if self.counter.is_event_set(Event::LowLimit) {
+                self.offset -= QUADRATURE_LIMIT as i64;
+            }
+            self.counter.clear_interrupt();
+        }
+    }
+
+    /// Enable the interrupt on overflow of the counter
+    pub fn listen(&mut self) {
+        self.counter.enable_interrupt(true);
+    }
+
+    /// Disable the interrupt on overflow of the counter
+    pub fn unlisten(&mut self) {
+        self.counter.enable_interrupt(false);
+    }
+
+    /// Stop counting and release the unit and pins
+    pub fn release(self) -> (Unit, A, B) {
+        let (unit, pins) = self.counter.release();
+        (unit, pins.signal0, pins.control0.unwrap())
+    }
+}
@@ -0,0 +1,210 @@
+//! Persistent data in RTC memory
+//!
+//! Statics in RTC memory declared with `#[ram(rtc_slow, uninitialized)]` keep their contents
+//! across deep sleep and resets, but contain garbage after a power loss. [RtcPersistent] stores a
+//! magic number and a CRC32 checksum alongside the value, so it can be validated at boot and be
+//! reset to a default if the contents did not survive.
+//!
+//! The value needs to be plain data without padding and without pointers, as the checksum is
+//! calculated over its bytes. This is expressed by the [PlainData] trait, which is implemented
+//! for the integer types and arrays of them and can be implemented for e.g. `#[repr(C)]` structs
+//! of integers.
+//!
+//! # Example
+//!
+//! ```
+//! #[ram(rtc_slow, uninitialized)]
+//! static mut BOOT_COUNT: RtcPersistent<u32> = RtcPersistent::new(0);
+//!
+//! let boot_count = unsafe { &mut BOOT_COUNT };
+//! if !boot_count.validate(0) {
+//!     writeln!(serial, "RTC memory lost").unwrap();
+//! }
+//! boot_count.update(|count| *count += 1);
+//! ```
+
+use core::mem::size_of;
+
+/// Marker of initialized persistent data
+const MAGIC: u32 = 0x5254_4350;
+
+/// Types which can be accessed as bytes
+///
+/// # Safety
+///
+/// The type must not contain padding bytes, pointers or references, and every bit pattern must
+/// be a valid value of the type.
+pub unsafe trait PlainData: Copy {}
+
+macro_rules! impl_plain_data {
+    ($($type:ty),+) => {
+        $(
+            unsafe impl PlainData for $type {}
+        )+
+    };
+}
+
+impl_plain_data!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+macro_rules! impl_plain_data_array {
+    ($($size:literal),+) => {
+        $(
+            unsafe impl<T: PlainData> PlainData for [T; $size] {}
+        )+
+    };
+}
+
+impl_plain_data_array!(
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26,
+    27, 28, 29, 30, 31, 32, 64, 128, 256
+);
+
+/// CRC32 (IEEE 802.3) of the data
+pub fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(!0, data)
+}
+
+/// Continue a CRC32 calculation with the data
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// Checksum of a value with the given bytes
+///
+/// The size is included, so a change of the layout of the value invalidates the data.
+pub fn checksum(bytes: &[u8]) -> u32 {
+    let crc = crc32_update(!0, &(bytes.len() as u32).to_le_bytes());
+    !crc32_update(crc, bytes)
+}
+
+/// Value with magic number and checksum
+#[repr(C)]
+pub struct RtcPersistent<T: PlainData> {
+    magic: u32,
+    checksum: u32,
+    value: T,
+}
+
+impl<T: PlainData> RtcPersistent<T> {
+    /// Create the persistent value
+    ///
+    /// The value is only used for statics which are initialized at boot. For uninitialized
+    /// statics [validate][RtcPersistent::validate] sets the value.
+    pub const fn new(value: T) -> Self {
+        RtcPersistent {
+            magic: 0,
+            checksum: 0,
+            value,
+        }
+    }
+
+    /// Returns true if the magic number and checksum match the value
+    pub fn is_valid(&self) -> bool {
+        self.magic == MAGIC && self.checksum == checksum(self.bytes())
+    }
+
+    /// Validate the stored value and reset it to the default if it is not intact
+    ///
+    /// Returns true if the stored value was intact.
+    pub fn validate(&mut self, default: T) -> bool {
+        if self.is_valid() {
+            return true;
+        }
+
+        self.set(default);
+        false
+    }
+
+    /// Returns the value
+    pub fn get(&self) -> T {
+        self.value
+    }
+
+    /// Store a new value
+    pub fn set(&mut self, value: T) {
+        self.value = value;
+        self.seal();
+    }
+
+    /// Modify the value in place
+    pub fn update<F: FnOnce(&mut T)>(&mut self, f: F) {
+        f(&mut self.value);
+        self.seal();
+    }
+
+    /// Mark the value as invalid
+    pub fn invalidate(&mut self) {
+        self.magic = 0;
+    }
+
+    /// Update the magic number and the checksum
+    fn seal(&mut self) {
+        self.checksum = checksum(self.bytes());
+        self.magic = MAGIC;
+    }
+
+    /// Bytes of the value
+    fn bytes(&self) -> &[u8] {
+        // `PlainData` guarantees that all bytes of the value are initialized
+        unsafe { core::slice::from_raw_parts(&self.value as *const T as *const u8, size_of::<T>()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn checksum_includes_length() {
+        assert_ne!(checksum(&[]), checksum(&[0]));
+        assert_ne!(checksum(&[0; 4]), checksum(&[0; 8]));
+        assert_ne!(checksum(b"123456789"), crc32(b"123456789"));
+    }
+
+    #[test]
+    fn validate_uninitialized() {
+        let mut value = RtcPersistent::new(5u32);
+
+        assert!(!value.is_valid());
+        assert!(!value.validate(7));
+        assert_eq!(value.get(), 7);
+
+        assert!(value.is_valid());
+        assert!(value.validate(9));
+        assert_eq!(value.get(), 7);
+    }
+
+    #[test]
+    fn validate_corrupted() {
+        let mut value = RtcPersistent::new([0u16; 4]);
+        value.set([1, 2, 3, 4]);
+        value.update(|value| value[3] += 1);
+        assert!(value.validate([0; 4]));
+        assert_eq!(value.get(), [1, 2, 3, 5]);
+
+        value.value[0] = 0xffff;
+        assert!(!value.is_valid());
+        assert!(!value.validate([0; 4]));
+        assert_eq!(value.get(), [0; 4]);
+
+        value.invalidate();
+        assert!(!value.validate([6; 4]));
+        assert_eq!(value.get(), [6; 4]);
+    }
+}
@@ -0,0 +1,117 @@
+//! Reset reason and software reset
+//!
+//! The RTC controller keeps the cause of the last reset of each core. Together with the
+//! [wakeup cause][wakeup_cause] this allows to find out why the chip has (re)started, e.g. after a
+//! crash in the field.
+//!
+//! # Example
+//!
+//! ```
+//! match reset::reset_reason(Core::PRO) {
+//!     ResetReason::PowerOn => writeln!(serial, "Power on").unwrap(),
+//!     ResetReason::DeepSleep => writeln!(serial, "Wakeup: {:?}", reset::wakeup_cause()).unwrap(),
+//!     reason => writeln!(serial, "Reset: {:?}", reason).unwrap(),
+//! }
+//! ```
+
+use crate::target::RTCCNTL;
+use crate::Core;
+
+pub use crate::sleep::{wakeup_cause, WakeupCause};
+
+/// Cause of the last reset of a core
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ResetReason {
+    /// Power on reset
+    PowerOn,
+    /// Software reset of the whole system
+    Software,
+    /// Legacy watchdog reset of the digital core
+    LegacyWatchdog,
+    /// Wakeup from deep sleep
+    DeepSleep,
+    /// Reset by the SLC module of the digital core
+    Sdio,
+    /// Timer group 0 watchdog reset of the digital core
+    Timer0Watchdog,
+    /// Timer group 1 watchdog reset of the digital core
+    Timer1Watchdog,
+    /// RTC watchdog reset of the digital core
+    RtcWatchdog,
+    /// Intrusion test reset of the CPU
+    Intrusion,
+    /// Timer group watchdog reset of the CPU
+    TimerWatchdogCpu,
+    /// Software reset of the CPU
+    SoftwareCpu,
+    /// RTC watchdog reset of the CPU
+    RtcWatchdogCpu,
+    /// Reset of the APP CPU by the PRO CPU
+    ExternalCpu,
+    /// Brown-out reset of the whole system
+    BrownOut,
+    /// RTC watchdog reset of the whole system
+    RtcWatchdogSystem,
+    /// Unknown reset cause
+    Unknown(u8),
+}
+
+impl From<u8> for ResetReason {
+    /// Decode the reset cause field of the RTC controller
+    fn from(cause: u8) -> Self {
+        match cause {
+            1 => ResetReason::PowerOn,
+            3 => ResetReason::Software,
+            4 => ResetReason::LegacyWatchdog,
+            5 => ResetReason::DeepSleep,
+            6 => ResetReason::Sdio,
+            7 => ResetReason::Timer0Watchdog,
+            8 => ResetReason::Timer1Watchdog,
+            9 => ResetReason::RtcWatchdog,
+            10 => ResetReason::Intrusion,
+            11 => ResetReason::TimerWatchdogCpu,
+            12 => ResetReason::SoftwareCpu,
+            13 => ResetReason::RtcWatchdogCpu,
+            14 => ResetReason::ExternalCpu,
+            15 => ResetReason::BrownOut,
+            16 => ResetReason::RtcWatchdogSystem,
+            cause => ResetReason::Unknown(cause),
+        }
+    }
+}
+
+/// Returns the cause of the last reset of the core
+pub fn reset_reason(core: Core) -> ResetReason {
+    let reset_state = unsafe { &*RTCCNTL::ptr() }.reset_state.read();
+
+    match core {
+        Core::PRO => reset_state.reset_cause_procpu().bits(),
+        Core::APP => reset_state.reset_cause_appcpu().bits(),
+    }
+    .into()
+}
+
+/// Reset the whole system, including the RTC peripherals
+pub fn software_reset() -> ! {
+    let rtc_control = unsafe { &*RTCCNTL::ptr() };
+
+    rtc_control.options0.modify(|_, w| w.sw_sys_rst().set_bit());
+
+    loop {}
+}
+
+/// Reset a single core
+///
+/// When resetting the current core this function does not return.
+pub fn software_reset_core(core: Core) {
+    let rtc_control = unsafe { &*RTCCNTL::ptr() };
+
+    match core {
+        Core::PRO => rtc_control
+            .options0
+            .modify(|_, w| w.sw_procpu_rst().set_bit()),
+        Core::APP => rtc_control
+            .options0
+            .modify(|_, w| w.sw_appcpu_rst().set_bit()),
+    }
+}
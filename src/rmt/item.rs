@@ -0,0 +1,322 @@
+//! Encoding of pulses into RMT items
+//!
+//! An RMT item is a 32-bit word describing two consecutive pulses, each with a level and a
+//! duration of up to 32767 ticks. A duration of zero marks the end of a transmission or
+//! reception.
+//!
+//! This module does not access the hardware.
+//!
+//! # Example
+//!
+//! Encoding of the bits of a byte for WS2812 LEDs (with a 20MHz tick: 0.4us/0.85us for a 0,
+//! 0.8us/0.45us for a 1).
+//! ```
+//! let encoder = ByteEncoder {
+//!     zero: Item::new(Pulse::high(8), Pulse::low(17)),
+//!     one: Item::new(Pulse::high(16), Pulse::low(9)),
+//! };
+//!
+//! let mut items = [Item::END; 24];
+//! for (item, bit) in items.iter_mut().zip(encoder.encode(&[green, red, blue])) {
+//!     *item = bit;
+//! }
+//! ```
+
+/// Maximum duration of a pulse in ticks
+pub const MAX_DURATION: u16 = 0x7fff;
+
+/// Pulse with a level and a duration in ticks
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Pulse {
+    pub level: bool,
+    pub duration: u16,
+}
+
+impl Pulse {
+    /// Pulse with the level high
+    pub const fn high(duration: u16) -> Self {
+        Pulse {
+            level: true,
+            duration,
+        }
+    }
+
+    /// Pulse with the level low
+    pub const fn low(duration: u16) -> Self {
+        Pulse {
+            level: false,
+            duration,
+        }
+    }
+
+    /// Register representation of the pulse (16 bits)
+    ///
+    /// Durations above [MAX_DURATION] are truncated.
+    const fn bits(&self) -> u32 {
+        (self.level as u32) << 15 | (self.duration & MAX_DURATION) as u32
+    }
+
+    const fn from_bits(bits: u32) -> Self {
+        Pulse {
+            level: bits & (1 << 15) != 0,
+            duration: (bits as u16) & MAX_DURATION,
+        }
+    }
+}
+
+/// RMT item consisting of two pulses
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Item(u32);
+
+impl Item {
+    /// End marker
+    pub const END: Item = Item(0);
+
+    /// Create an item from two pulses
+    pub const fn new(first: Pulse, second: Pulse) -> Self {
+        Item(first.bits() | second.bits() << 16)
+    }
+
+    /// Create an item from the register representation
+    pub const fn from_bits(bits: u32) -> Self {
+        Item(bits)
+    }
+
+    /// Register representation of the item
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// First pulse of the item
+    pub const fn first(&self) -> Pulse {
+        Pulse::from_bits(self.0)
+    }
+
+    /// Second pulse of the item
+    pub const fn second(&self) -> Pulse {
+        Pulse::from_bits(self.0 >> 16)
+    }
+
+    /// Returns true if the item marks the end of the sequence
+    ///
+    /// This is the case if one of the durations is zero.
+    pub const fn is_end(&self) -> bool {
+        self.first().duration == 0 || self.second().duration == 0
+    }
+}
+
+/// Iterator encoding pulses into items
+///
+/// Pulses longer than [MAX_DURATION] are split into multiple pulses. An odd number of pulses
+/// is completed with a zero length pulse, which marks the end of the sequence.
+pub struct Encoder<I: Iterator<Item = Pulse>> {
+    pulses: I,
+    remainder: Option<Pulse>,
+}
+
+impl<I: Iterator<Item = Pulse>> Encoder<I> {
+    pub fn new<P: IntoIterator<IntoIter = I, Item = Pulse>>(pulses: P) -> Self {
+        Encoder {
+            pulses: pulses.into_iter(),
+            remainder: None,
+        }
+    }
+
+    /// Next pulse with a duration of at most [MAX_DURATION]
+    fn next_pulse(&mut self) -> Option<Pulse> {
+        loop {
+            let pulse = match self.remainder.take() {
+                Some(pulse) => pulse,
+                None => self.pulses.next()?,
+            };
+
+            if pulse.duration > MAX_DURATION {
+                self.remainder = Some(Pulse {
+                    level: pulse.level,
+                    duration: pulse.duration - MAX_DURATION,
+                });
+                return Some(Pulse {
+                    level: pulse.level,
+                    duration: MAX_DURATION,
+                });
+            }
+
+            // zero length pulses would end the sequence
+            if pulse.duration != 0 {
+                return Some(pulse);
+            }
+        }
+    }
+}
+
+impl<I: Iterator<Item = Pulse>> Iterator for Encoder<I> {
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Item> {
+        let first = self.next_pulse()?;
+        let second = self.next_pulse().unwrap_or(Pulse {
+            level: first.level,
+            duration: 0,
+        });
+
+        Some(Item::new(first, second))
+    }
+}
+
+/// Iterator decoding items into pulses
+///
+/// Stops at the end marker.
+pub struct Decoder<I: Iterator<Item = Item>> {
+    items: I,
+    second: Option<Pulse>,
+    done: bool,
+}
+
+impl<I: Iterator<Item = Item>> Decoder<I> {
+    pub fn new<P: IntoIterator<IntoIter = I, Item = Item>>(items: P) -> Self {
+        Decoder {
+            items: items.into_iter(),
+            second: None,
+            done: false,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Item>> Iterator for Decoder<I> {
+    type Item = Pulse;
+
+    fn next(&mut self) -> Option<Pulse> {
+        if let Some(second) = self.second.take() {
+            return Some(second);
+        }
+
+        if self.done {
+            return None;
+        }
+
+        let item = self.items.next()?;
+        let first = item.first();
+        let second = item.second();
+
+        if first.duration == 0 {
+            self.done = true;
+            return None;
+        }
+
+        if second.duration == 0 {
+            self.done = true;
+        } else {
+            self.second = Some(second);
+        }
+
+        Some(first)
+    }
+}
+
+/// Encoding of bytes into one item per bit, most significant bit first
+#[derive(Copy, Clone, Debug)]
+pub struct ByteEncoder {
+    /// Item for a zero bit
+    pub zero: Item,
+    /// Item for a one bit
+    pub one: Item,
+}
+
+impl ByteEncoder {
+    /// Iterator over the items of the bytes
+    pub fn encode<'a>(&self, data: &'a [u8]) -> impl Iterator<Item = Item> + 'a {
+        let (zero, one) = (self.zero, self.one);
+        data.iter().flat_map(move |byte| {
+            (0..8)
+                .rev()
+                .map(move |bit| if byte & (1 << bit) != 0 { one } else { zero })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn item_bits() {
+        let item = Item::new(Pulse::high(MAX_DURATION), Pulse::low(1));
+
+        assert_eq!(item.bits(), 0x0001_ffff);
+        assert_eq!(Item::from_bits(item.bits()), item);
+        assert_eq!(item.first(), Pulse::high(MAX_DURATION));
+        assert_eq!(item.second(), Pulse::low(1));
+        assert!(!item.is_end());
+    }
+
+    #[test]
+    fn end_marker() {
+        assert!(Item::END.is_end());
+        assert!(Item::new(Pulse::high(10), Pulse::low(0)).is_end());
+        assert!(Item::new(Pulse::low(0), Pulse::high(10)).is_end());
+    }
+
+    #[test]
+    fn round_trip() {
+        let even = [
+            Pulse::high(9000),
+            Pulse::low(4500),
+            Pulse::high(560),
+            Pulse::low(1690),
+        ];
+        let odd = [Pulse::high(9000), Pulse::low(4500), Pulse::high(560)];
+
+        assert_eq!(Encoder::new(even.iter().copied()).count(), 2);
+        assert!(Decoder::new(Encoder::new(even.iter().copied())).eq(even.iter().copied()));
+
+        // the last item is completed with the end marker
+        let last = Encoder::new(odd.iter().copied()).last().unwrap();
+        assert_eq!(last, Item::new(Pulse::high(560), Pulse::high(0)));
+        assert!(Decoder::new(Encoder::new(odd.iter().copied())).eq(odd.iter().copied()));
+    }
+
+    #[test]
+    fn long_and_zero_pulses() {
+        let pulses = [Pulse::high(40000), Pulse::low(0), Pulse::low(5)];
+        let expected = [
+            Pulse::high(MAX_DURATION),
+            Pulse::high(40000 - MAX_DURATION),
+            Pulse::low(5),
+        ];
+
+        assert!(Decoder::new(Encoder::new(pulses.iter().copied())).eq(expected.iter().copied()));
+    }
+
+    #[test]
+    fn decoder_stops_at_end_marker() {
+        let items = [
+            Item::new(Pulse::high(1), Pulse::low(2)),
+            Item::new(Pulse::high(3), Pulse::low(0)),
+            Item::new(Pulse::high(4), Pulse::low(5)),
+        ];
+        let expected = [Pulse::high(1), Pulse::low(2), Pulse::high(3)];
+        assert!(Decoder::new(items.iter().copied()).eq(expected.iter().copied()));
+
+        let items = [
+            Item::new(Pulse::high(1), Pulse::low(2)),
+            Item::END,
+            Item::new(Pulse::high(4), Pulse::low(5)),
+        ];
+        let expected = [Pulse::high(1), Pulse::low(2)];
+        assert!(Decoder::new(items.iter().copied()).eq(expected.iter().copied()));
+    }
+
+    #[test]
+    fn byte_encoder() {
+        let zero = Item::new(Pulse::high(8), Pulse::low(17));
+        let one = Item::new(Pulse::high(16), Pulse::low(9));
+        let encoder = ByteEncoder { zero, one };
+
+        let expected = [one, zero, zero, zero, zero, zero, one, one, zero];
+        assert!(encoder
+            .encode(&[0x83, 0x00])
+            .take(9)
+            .eq(expected.iter().copied()));
+        assert_eq!(encoder.encode(&[0x83, 0x00]).count(), 16);
+    }
+}
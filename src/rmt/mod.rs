@@ -0,0 +1,830 @@
+//! Remote control peripheral (RMT)
+//!
+//! The RMT peripheral has 8 channels, each of which can transmit or receive sequences of pulses
+//! (e.g. for WS2812 LEDs or IR remotes). The pulses are stored as [Item]s in the RMT RAM, see
+//! the [item] module for the encoding.
+//!
+//! Each channel owns a block of 64 items of the RMT RAM. A channel can use more blocks by
+//! taking the blocks of the following channels. A channel can't be configured while one of its
+//! blocks is used by another channel.
+//!
+//! The channels are clocked by the APB clock or the 1MHz REF_TICK clock with an 8-bit divider.
+//! An APB lock is held while a channel uses the APB clock.
+//!
+//! # Example
+//!
+//! Transmission of pulses with 1us resolution and a 38kHz carrier.
+//! ```
+//! let rmt = Rmt::new(dp.RMT, clkcntrl_config, &mut dport);
+//!
+//! let mut ir = rmt
+//!     .channel0
+//!     .into_transmitter(
+//!         pins.gpio4,
+//!         ChannelConfig::default()
+//!             .clock_source(ClockSource::RefTick)
+//!             .clock_divider(1)
+//!             .carrier(Some(CarrierConfig::default())),
+//!     )
+//!     .unwrap();
+//!
+//! ir.transmit_pulses(&[Pulse::high(9000), Pulse::low(4500), Pulse::high(560)])
+//!     .unwrap();
+//! ir.wait();
+//! ```
+//!
+//! # TODO
+//! - Transmission of sequences longer than the RAM (refill via the threshold interrupt)
+//! - Release of the peripheral
+
+use core::marker::PhantomData;
+
+use crate::gpio::{InputPin, InputSignal, OutputPin, OutputSignal};
+use crate::prelude::*;
+use crate::target;
+
+pub mod item;
+
+pub use item::{Item, Pulse};
+
+/// Address of the RMT RAM
+const RMT_RAM_ADDRESS: usize = 0x3ff5_6800;
+/// Number of items in a block of the RMT RAM
+const RMT_BLOCK_SIZE: usize = 64;
+/// Number of channels (and blocks)
+const RMT_CHANNELS: u8 = 8;
+
+/// Offset of the configuration registers of channel 0
+const CONF_OFFSET: usize = 0x20;
+/// Offset of the status register of channel 0
+const STATUS_OFFSET: usize = 0x60;
+/// Offset of the carrier register of channel 0
+const CARRIER_OFFSET: usize = 0xb0;
+
+/// Output signals of the channels
+const OUTPUT_SIGNALS: [OutputSignal; 8] = [
+    OutputSignal::RMT_SIG_0,
+    OutputSignal::RMT_SIG_1,
+    OutputSignal::RMT_SIG_2,
+    OutputSignal::RMT_SIG_3,
+    OutputSignal::RMT_SIG_4,
+    OutputSignal::RMT_SIG_5,
+    OutputSignal::RMT_SIG_6,
+    OutputSignal::RMT_SIG_7,
+];
+
+/// Input signals of the channels
+const INPUT_SIGNALS: [InputSignal; 8] = [
+    InputSignal::RMT_SIG_0,
+    InputSignal::RMT_SIG_1,
+    InputSignal::RMT_SIG_2,
+    InputSignal::RMT_SIG_3,
+    InputSignal::RMT_SIG_4,
+    InputSignal::RMT_SIG_5,
+    InputSignal::RMT_SIG_6,
+    InputSignal::RMT_SIG_7,
+];
+
+static RMT_LOCK: CriticalSectionSpinLockMutex<()> = CriticalSectionSpinLockMutex::new(());
+
+/// Memory blocks used by the configured channels
+static RMT_MEMORY_BLOCKS: CriticalSectionSpinLockMutex<u8> = CriticalSectionSpinLockMutex::new(0);
+
+/// RMT error
+#[derive(Debug)]
+pub enum Error {
+    /// Clock divider of 0
+    InvalidClockDivider,
+    /// Channel has not enough following channels for the memory blocks
+    InvalidMemoryBlocks,
+    /// Memory blocks are used by another channel
+    MemoryBlocksInUse,
+    /// Carrier frequency too high or too low for the clock source
+    InvalidCarrierFrequency,
+    /// More items than fit in the memory blocks of the channel
+    TooManyItems,
+    /// Received more items than fit in the memory blocks of the channel
+    Overflow,
+}
+
+/// RMT interrupt event
+pub enum Event {
+    /// Transmission complete
+    TransmitDone,
+    /// Reception complete (idle threshold reached)
+    ReceiveDone,
+    /// Error (e.g. RAM overflow while receiving)
+    Error,
+}
+
+/// RMT configuration
+pub mod config {
+    use crate::units::*;
+
+    /// Clock source of a channel
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum ClockSource {
+        /// APB clock (normally 80MHz)
+        APB,
+        /// REF_TICK clock (1MHz)
+        RefTick,
+    }
+
+    /// Carrier modulation of the high (or low) level of the transmitted pulses
+    #[derive(Copy, Clone, Debug)]
+    pub struct CarrierConfig {
+        pub frequency: Hertz,
+        /// Duty cycle in percent
+        pub duty_percent: u8,
+        /// Level of the pulses modulated by the carrier
+        pub level: bool,
+    }
+
+    impl Default for CarrierConfig {
+        fn default() -> CarrierConfig {
+            CarrierConfig {
+                frequency: Hertz(38_000),
+                duty_percent: 33,
+                level: true,
+            }
+        }
+    }
+
+    /// Channel configuration
+    #[derive(Copy, Clone, Debug)]
+    pub struct ChannelConfig {
+        pub clock_source: ClockSource,
+        /// Divider of the clock source (1-255), determines the tick of the items
+        pub clock_divider: u8,
+        /// Number of blocks of 64 items used by the channel
+        pub memory_blocks: u8,
+        /// Level of the output when not transmitting (None: output of the last item)
+        pub idle_level: Option<bool>,
+        /// Carrier modulation when transmitting
+        pub carrier: Option<CarrierConfig>,
+        /// Pulses shorter than the threshold in APB cycles are ignored when receiving
+        pub filter_threshold: Option<u8>,
+        /// Reception ends when no edge is detected for the threshold in ticks
+        pub idle_threshold: u16,
+    }
+
+    impl ChannelConfig {
+        pub fn clock_source(mut self, clock_source: ClockSource) -> Self {
+            self.clock_source = clock_source;
+            self
+        }
+
+        pub fn clock_divider(mut self, clock_divider: u8) -> Self {
+            self.clock_divider = clock_divider;
+            self
+        }
+
+        pub fn memory_blocks(mut self, memory_blocks: u8) -> Self {
+            self.memory_blocks = memory_blocks;
+            self
+        }
+
+        pub fn idle_level(mut self, idle_level: Option<bool>) -> Self {
+            self.idle_level = idle_level;
+            self
+        }
+
+        pub fn carrier(mut self, carrier: Option<CarrierConfig>) -> Self {
+            self.carrier = carrier;
+            self
+        }
+
+        pub fn filter_threshold(mut self, filter_threshold: Option<u8>) -> Self {
+            self.filter_threshold = filter_threshold;
+            self
+        }
+
+        pub fn idle_threshold(mut self, idle_threshold: u16) -> Self {
+            self.idle_threshold = idle_threshold;
+            self
+        }
+    }
+
+    impl Default for ChannelConfig {
+        fn default() -> ChannelConfig {
+            ChannelConfig {
+                clock_source: ClockSource::APB,
+                clock_divider: 80,
+                memory_blocks: 1,
+                idle_level: Some(false),
+                carrier: None,
+                filter_threshold: None,
+                idle_threshold: 12_000,
+            }
+        }
+    }
+}
+
+use config::{CarrierConfig, ChannelConfig, ClockSource};
+
+/// RMT peripheral split into its channels
+pub struct Rmt {
+    pub channel0: Channel<Channel0>,
+    pub channel1: Channel<Channel1>,
+    pub channel2: Channel<Channel2>,
+    pub channel3: Channel<Channel3>,
+    pub channel4: Channel<Channel4>,
+    pub channel5: Channel<Channel5>,
+    pub channel6: Channel<Channel6>,
+    pub channel7: Channel<Channel7>,
+}
+
+impl Rmt {
+    /// Enable the RMT peripheral and split it into its channels
+    pub fn new(
+        rmt: target::RMT,
+        clock_control: crate::clock_control::ClockControlConfig,
+        dport: &mut target::DPORT,
+    ) -> Self {
+        dport
+            .perip_rst_en
+            .modify(|_, w| w.remote_controller().set_bit());
+        dport
+            .perip_clk_en
+            .modify(|_, w| w.remote_controller().set_bit());
+        dport
+            .perip_rst_en
+            .modify(|_, w| w.remote_controller().clear_bit());
+
+        // access the RMT RAM directly instead of via the FIFO
+        rmt.apb_conf
+            .write(|w| w.apb_fifo_mask().set_bit().mem_tx_wrap_en().clear_bit());
+        rmt.int_ena.write(|w| unsafe { w.bits(0) });
+        rmt.int_clr.write(|w| unsafe { w.bits(0xffff_ffff) });
+
+        Rmt {
+            channel0: Channel::new(clock_control),
+            channel1: Channel::new(clock_control),
+            channel2: Channel::new(clock_control),
+            channel3: Channel::new(clock_control),
+            channel4: Channel::new(clock_control),
+            channel5: Channel::new(clock_control),
+            channel6: Channel::new(clock_control),
+            channel7: Channel::new(clock_control),
+        }
+    }
+}
+
+/// Channel which is not configured
+pub struct Channel<CH: ChannelInstance> {
+    clock_control: crate::clock_control::ClockControlConfig,
+    _channel: PhantomData<CH>,
+}
+
+impl<CH: ChannelInstance> Channel<CH> {
+    fn new(clock_control: crate::clock_control::ClockControlConfig) -> Self {
+        Channel {
+            clock_control,
+            _channel: PhantomData,
+        }
+    }
+
+    /// Configure the channel as transmitter on the pin
+    pub fn into_transmitter<PIN: OutputPin>(
+        self,
+        mut pin: PIN,
+        config: ChannelConfig,
+    ) -> Result<Transmitter<CH, PIN>, Error> {
+        let mut driver = Driver::new(self, config)?;
+
+        pin.set_to_push_pull_output()
+            .connect_peripheral_to_output(OUTPUT_SIGNALS[CH::NUMBER as usize]);
+
+        driver.set_idle_level(config.idle_level);
+        if let Err(error) = driver.set_carrier(config.carrier) {
+            driver.release();
+            return Err(error);
+        }
+
+        Ok(Transmitter { driver, pin })
+    }
+
+    /// Configure the channel as receiver on the pin
+    pub fn into_receiver<PIN: InputPin>(
+        self,
+        mut pin: PIN,
+        config: ChannelConfig,
+    ) -> Result<Receiver<CH, PIN>, Error> {
+        let driver = Driver::new(self, config)?;
+
+        pin.set_to_input()
+            .connect_input_to_peripheral(INPUT_SIGNALS[CH::NUMBER as usize]);
+
+        driver.conf1().modify(|_, w| unsafe {
+            w.rx_filter_en_ch0()
+                .bit(config.filter_threshold.is_some())
+                .rx_filter_thres_ch0()
+                .bits(config.filter_threshold.unwrap_or(0))
+        });
+
+        Ok(Receiver { driver, pin })
+    }
+}
+
+/// Configured channel, common part of transmitter and receiver
+struct Driver<CH: ChannelInstance> {
+    channel: Channel<CH>,
+    memory_blocks: u8,
+    _apb_lock: Option<crate::clock_control::dfs::LockAPB>,
+}
+
+impl<CH: ChannelInstance> Driver<CH> {
+    fn new(channel: Channel<CH>, config: ChannelConfig) -> Result<Self, Error> {
+        if config.clock_divider == 0 {
+            return Err(Error::InvalidClockDivider);
+        }
+        if config.memory_blocks == 0 || config.memory_blocks > RMT_CHANNELS - CH::NUMBER {
+            return Err(Error::InvalidMemoryBlocks);
+        }
+
+        let blocks = memory_block_mask::<CH>(config.memory_blocks);
+        (&RMT_MEMORY_BLOCKS).lock(|used| {
+            if *used & blocks != 0 {
+                return Err(Error::MemoryBlocksInUse);
+            }
+            *used |= blocks;
+            Ok(())
+        })?;
+
+        let apb = config.clock_source == ClockSource::APB;
+        let driver = Driver {
+            _apb_lock: if apb {
+                Some(channel.clock_control.lock_apb_frequency())
+            } else {
+                None
+            },
+            channel,
+            memory_blocks: config.memory_blocks,
+        };
+
+        driver.conf0().write(|w| unsafe {
+            w.clk_en()
+                .set_bit()
+                .mem_pd()
+                .clear_bit()
+                .carrier_en_ch0()
+                .clear_bit()
+                .mem_size_ch0()
+                .bits(config.memory_blocks)
+                .idle_thres_ch0()
+                .bits(config.idle_threshold)
+                .div_cnt_ch0()
+                .bits(config.clock_divider)
+        });
+        driver.conf1().write(|w| {
+            w.ref_always_on_ch0()
+                .bit(apb)
+                .ref_cnt_rst_ch0()
+                .set_bit()
+                .mem_rd_rst_ch0()
+                .set_bit()
+                .mem_wr_rst_ch0()
+                .set_bit()
+        });
+        driver.conf1().modify(|_, w| {
+            w.ref_cnt_rst_ch0()
+                .clear_bit()
+                .mem_rd_rst_ch0()
+                .clear_bit()
+                .mem_wr_rst_ch0()
+                .clear_bit()
+        });
+
+        Ok(driver)
+    }
+
+    /// Frequency of the clock source
+    fn source_frequency(&self) -> Hertz {
+        if self.conf1().read().ref_always_on_ch0().bit_is_set() {
+            self.channel.clock_control.apb_frequency_apb_locked()
+        } else {
+            self.channel.clock_control.ref_frequency()
+        }
+    }
+
+    /// Duration of a tick of the items
+    fn tick_frequency(&self) -> Hertz {
+        let divider = match self.conf0().read().div_cnt_ch0().bits() {
+            0 => 256,
+            divider => divider as u32,
+        };
+        self.source_frequency() / divider
+    }
+
+    /// Number of items fitting in the memory blocks of the channel
+    fn capacity(&self) -> usize {
+        self.memory_blocks as usize * RMT_BLOCK_SIZE
+    }
+
+    fn set_idle_level(&mut self, level: Option<bool>) {
+        self.conf1().modify(|_, w| {
+            w.idle_out_en_ch0()
+                .bit(level.is_some())
+                .idle_out_lv_ch0()
+                .bit(level.unwrap_or(false))
+        });
+    }
+
+    fn set_carrier(&mut self, carrier: Option<CarrierConfig>) -> Result<(), Error> {
+        match carrier {
+            None => {
+                self.conf0().modify(|_, w| w.carrier_en_ch0().clear_bit());
+            }
+            Some(carrier) => {
+                let (high, low) = carrier_duty(
+                    self.source_frequency(),
+                    carrier.frequency,
+                    carrier.duty_percent,
+                )?;
+
+                self.carrier_duty().write(|w| unsafe {
+                    w.carrier_high_ch0().bits(high).carrier_low_ch0().bits(low)
+                });
+                self.conf0().modify(|_, w| {
+                    w.carrier_en_ch0()
+                        .set_bit()
+                        .carrier_out_lv_ch0()
+                        .bit(carrier.level)
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Memory of the channel in the RMT RAM
+    fn memory(&self) -> *mut u32 {
+        (RMT_RAM_ADDRESS + CH::NUMBER as usize * RMT_BLOCK_SIZE * 4) as *mut u32
+    }
+
+    fn conf0(&self) -> &'static target::rmt::CH0CONF0 {
+        unsafe {
+            &*((target::RMT::ptr() as usize + CONF_OFFSET + CH::NUMBER as usize * 8)
+                as *const target::rmt::CH0CONF0)
+        }
+    }
+
+    fn conf1(&self) -> &'static target::rmt::CH0CONF1 {
+        unsafe {
+            &*((target::RMT::ptr() as usize + CONF_OFFSET + 4 + CH::NUMBER as usize * 8)
+                as *const target::rmt::CH0CONF1)
+        }
+    }
+
+    fn status(&self) -> &'static target::rmt::CH0STATUS {
+        unsafe {
+            &*((target::RMT::ptr() as usize + STATUS_OFFSET + CH::NUMBER as usize * 4)
+                as *const target::rmt::CH0STATUS)
+        }
+    }
+
+    fn carrier_duty(&self) -> &'static target::rmt::CH0CARRIER_DUTY {
+        unsafe {
+            &*((target::RMT::ptr() as usize + CARRIER_OFFSET + CH::NUMBER as usize * 4)
+                as *const target::rmt::CH0CARRIER_DUTY)
+        }
+    }
+
+    /// Interrupt bit of the event (3 bits per channel)
+    fn interrupt_mask(&self, event: Event) -> u32 {
+        1 << (CH::NUMBER * 3
+            + match event {
+                Event::TransmitDone => 0,
+                Event::ReceiveDone => 1,
+                Event::Error => 2,
+            })
+    }
+
+    fn enable_interrupt(&mut self, event: Event, enable: bool) {
+        let mask = self.interrupt_mask(event);
+        let rmt = unsafe { &*target::RMT::ptr() };
+
+        (&RMT_LOCK).lock(|_| {
+            rmt.int_ena.modify(|r, w| unsafe {
+                w.bits(if enable {
+                    r.bits() | mask
+                } else {
+                    r.bits() & !mask
+                })
+            });
+        });
+    }
+
+    fn is_interrupt_set(&self, event: Event) -> bool {
+        let rmt = unsafe { &*target::RMT::ptr() };
+        rmt.int_raw.read().bits() & self.interrupt_mask(event) != 0
+    }
+
+    fn clear_interrupt(&mut self, event: Event) {
+        let rmt = unsafe { &*target::RMT::ptr() };
+        let mask = self.interrupt_mask(event);
+        rmt.int_clr.write(|w| unsafe { w.bits(mask) });
+    }
+
+    /// Disable the channel clock and release the channel
+    fn release(mut self) -> Channel<CH> {
+        self.enable_interrupt(Event::TransmitDone, false);
+        self.enable_interrupt(Event::ReceiveDone, false);
+        self.enable_interrupt(Event::Error, false);
+        self.conf1()
+            .modify(|_, w| w.tx_start_ch0().clear_bit().rx_en_ch0().clear_bit());
+        self.conf0().modify(|_, w| w.clk_en().clear_bit());
+
+        let blocks = memory_block_mask::<CH>(self.memory_blocks);
+        (&RMT_MEMORY_BLOCKS).lock(|used| *used &= !blocks);
+
+        self.channel
+    }
+}
+
+/// Mask of the memory blocks used by the channel
+fn memory_block_mask<CH: ChannelInstance>(memory_blocks: u8) -> u8 {
+    (((1u16 << memory_blocks) - 1) << CH::NUMBER) as u8
+}
+
+/// Calculate the high and low time of the carrier in cycles of the clock source
+fn carrier_duty(source: Hertz, frequency: Hertz, duty_percent: u8) -> Result<(u16, u16), Error> {
+    if frequency.0 == 0 || duty_percent > 100 {
+        return Err(Error::InvalidCarrierFrequency);
+    }
+
+    let period = source / frequency;
+    let high = period * duty_percent as u32 / 100;
+    let low = period - high;
+
+    if high == 0 || low == 0 {
+        return Err(Error::InvalidCarrierFrequency);
+    }
+    if high > u16::MAX as u32 || low > u16::MAX as u32 {
+        return Err(Error::InvalidCarrierFrequency);
+    }
+
+    Ok((high as u16, low as u16))
+}
+
+/// Channel configured as transmitter
+pub struct Transmitter<CH: ChannelInstance, PIN: OutputPin> {
+    driver: Driver<CH>,
+    pin: PIN,
+}
+
+impl<CH: ChannelInstance, PIN: OutputPin> Transmitter<CH, PIN> {
+    /// Transmit the items once
+    ///
+    /// The transmission ends at the first end marker or after the last item. One item of the
+    /// memory is needed for the end marker.
+    pub fn transmit(&mut self, items: &[Item]) -> Result<(), Error> {
+        self.transmit_items(items.iter().copied(), false)
+    }
+
+    /// Transmit the pulses once
+    pub fn transmit_pulses(&mut self, pulses: &[Pulse]) -> Result<(), Error> {
+        self.transmit_items(item::Encoder::new(pulses.iter().copied()), false)
+    }
+
+    /// Transmit the items repeatedly until [Transmitter::stop] is called
+    ///
+    /// The transmit done event is set after each repetition.
+    pub fn transmit_loop(&mut self, items: &[Item]) -> Result<(), Error> {
+        self.transmit_items(items.iter().copied(), true)
+    }
+
+    /// Stop the transmission
+    ///
+    /// A looped transmission stops at the end of the current repetition.
+    pub fn stop(&mut self) {
+        self.driver
+            .conf1()
+            .modify(|_, w| w.tx_conti_mode_ch0().clear_bit());
+    }
+
+    /// Returns true if the transmission is complete
+    pub fn is_done(&self) -> bool {
+        self.driver.is_interrupt_set(Event::TransmitDone)
+    }
+
+    /// Wait until the transmission is complete
+    pub fn wait(&self) {
+        while !self.is_done() {}
+    }
+
+    /// Change the level of the output when not transmitting (None: output of the last item)
+    pub fn set_idle_level(&mut self, level: Option<bool>) -> &mut Self {
+        self.driver.set_idle_level(level);
+        self
+    }
+
+    /// Change the carrier modulation
+    pub fn set_carrier(&mut self, carrier: Option<CarrierConfig>) -> Result<&mut Self, Error> {
+        self.driver.set_carrier(carrier)?;
+        Ok(self)
+    }
+
+    /// Returns the frequency of the ticks of the items
+    pub fn tick_frequency(&self) -> Hertz {
+        self.driver.tick_frequency()
+    }
+
+    /// Starts listening for an interrupt event
+    ///
+    /// The interrupt of all channels is `RMT_INTR`.
+    pub fn listen(&mut self, event: Event) {
+        self.driver.enable_interrupt(event, true);
+    }
+
+    /// Stop listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        self.driver.enable_interrupt(event, false);
+    }
+
+    /// Returns true if the interrupt for the event is set
+    pub fn is_interrupt_set(&self, event: Event) -> bool {
+        self.driver.is_interrupt_set(event)
+    }
+
+    /// Clear the interrupt for the event
+    pub fn clear_interrupt(&mut self, event: Event) {
+        self.driver.clear_interrupt(event);
+    }
+
+    /// Release the channel and pin
+    pub fn release(self) -> (Channel<CH>, PIN) {
+        (self.driver.release(), self.pin)
+    }
+
+    fn transmit_items<I: Iterator<Item = Item>>(
+        &mut self,
+        items: I,
+        continuous: bool,
+    ) -> Result<(), Error> {
+        let capacity = self.driver.capacity();
+        let memory = self.driver.memory();
+
+        let mut count = 0;
+        for item in items {
+            // keep space for the end marker
+            if count >= capacity - 1 {
+                return Err(Error::TooManyItems);
+            }
+            unsafe { memory.add(count).write_volatile(item.bits()) };
+            count += 1;
+        }
+        unsafe { memory.add(count).write_volatile(Item::END.bits()) };
+
+        self.driver.clear_interrupt(Event::TransmitDone);
+        self.driver.conf1().modify(|_, w| {
+            w.mem_owner_ch0()
+                .clear_bit()
+                .tx_conti_mode_ch0()
+                .bit(continuous)
+                .mem_rd_rst_ch0()
+                .set_bit()
+        });
+        self.driver
+            .conf1()
+            .modify(|_, w| w.mem_rd_rst_ch0().clear_bit().tx_start_ch0().set_bit());
+
+        Ok(())
+    }
+}
+
+/// Channel configured as receiver
+pub struct Receiver<CH: ChannelInstance, PIN: InputPin> {
+    driver: Driver<CH>,
+    pin: PIN,
+}
+
+impl<CH: ChannelInstance, PIN: InputPin> Receiver<CH, PIN> {
+    /// Start receiving
+    ///
+    /// The reception starts at the first edge and ends when the idle threshold is reached.
+    pub fn start(&mut self) {
+        self.driver.clear_interrupt(Event::ReceiveDone);
+        self.driver.clear_interrupt(Event::Error);
+        self.driver
+            .conf1()
+            .modify(|_, w| w.mem_owner_ch0().set_bit().mem_wr_rst_ch0().set_bit());
+        self.driver
+            .conf1()
+            .modify(|_, w| w.mem_wr_rst_ch0().clear_bit().rx_en_ch0().set_bit());
+    }
+
+    /// Stop receiving
+    pub fn stop(&mut self) {
+        self.driver.conf1().modify(|_, w| w.rx_en_ch0().clear_bit());
+    }
+
+    /// Returns true if the reception is complete
+    pub fn is_done(&self) -> bool {
+        self.driver.is_interrupt_set(Event::ReceiveDone)
+    }
+
+    /// Read the received items up to the end marker
+    ///
+    /// Returns the number of items copied into the buffer, which does not include the end
+    /// marker.
+    pub fn read(&mut self, items: &mut [Item]) -> Result<usize, Error> {
+        if self.driver.is_interrupt_set(Event::Error) {
+            return Err(Error::Overflow);
+        }
+
+        // the peripheral must not write while the memory is read
+        self.driver
+            .conf1()
+            .modify(|_, w| w.rx_en_ch0().clear_bit().mem_owner_ch0().clear_bit());
+
+        let memory = self.driver.memory();
+        let capacity = self.driver.capacity();
+        let mut count = 0;
+        for (index, slot) in items.iter_mut().take(capacity).enumerate() {
+            let item = Item::from_bits(unsafe { memory.add(index).read_volatile() });
+            if item.first().duration == 0 {
+                break;
+            }
+            *slot = item;
+            count += 1;
+            if item.is_end() {
+                break;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Returns the frequency of the ticks of the items
+    pub fn tick_frequency(&self) -> Hertz {
+        self.driver.tick_frequency()
+    }
+
+    /// Returns true if the receiver is waiting for or receiving pulses
+    pub fn is_receiving(&self) -> bool {
+        // state 0 is idle
+        self.driver.status().read().state_ch0().bits() != 0
+    }
+
+    /// Starts listening for an interrupt event
+    ///
+    /// The interrupt of all channels is `RMT_INTR`.
+    pub fn listen(&mut self, event: Event) {
+        self.driver.enable_interrupt(event, true);
+    }
+
+    /// Stop listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        self.driver.enable_interrupt(event, false);
+    }
+
+    /// Returns true if the interrupt for the event is set
+    pub fn is_interrupt_set(&self, event: Event) -> bool {
+        self.driver.is_interrupt_set(event)
+    }
+
+    /// Clear the interrupt for the event
+    pub fn clear_interrupt(&mut self, event: Event) {
+        self.driver.clear_interrupt(event);
+    }
+
+    /// Release the channel and pin
+    pub fn release(self) -> (Channel<CH>, PIN) {
+        (self.driver.release(), self.pin)
+    }
+}
+
+/// Marker of a channel
+pub trait ChannelInstance: private::Sealed {
+    const NUMBER: u8;
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! channels {
+    ($($Channel:ident: $number:literal,)+) => {
+        $(
+            /// Marker of the channel
+            pub struct $Channel;
+
+            impl private::Sealed for $Channel {}
+
+            impl ChannelInstance for $Channel {
+                const NUMBER: u8 = $number;
+            }
+        )+
+    };
+}
+
+channels! {
+    Channel0: 0,
+    Channel1: 1,
+    Channel2: 2,
+    Channel3: 3,
+    Channel4: 4,
+    Channel5: 5,
+    Channel6: 6,
+    Channel7: 7,
+}
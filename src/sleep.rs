@@ -0,0 +1,375 @@
+//! Deep sleep
+//!
+//! In deep sleep the CPUs, most of the RAM and all digital peripherals are powered down. Only
+//! the RTC controller and, depending on the configuration, the RTC peripherals and the RTC
+//! memories stay powered. Waking up from deep sleep resets the chip, so execution restarts from
+//! the bootloader. Data can be kept in RTC memory (e.g. `#[ram(rtc_slow)]`).
+//!
+//! The chip can be woken up by:
+//! - the RTC timer
+//! - EXT0: a single RTC GPIO at a given level (keeps the RTC peripherals powered)
+//! - EXT1: a set of RTC GPIOs, when all are low or any is high
+//! - the touch pads (keeps the RTC peripherals powered, see [touch](crate::touch))
+//!
+//! After the reset the cause of the wakeup can be queried with [wakeup_cause].
+//!
+//! # Example
+//!
+//! ```
+//! if sleep::wakeup_cause() == WakeupCause::Ext0 {
+//!     writeln!(serial, "Woken up by button").unwrap();
+//! }
+//!
+//! let mut button = pins.gpio4.into_pull_up_rtc_input();
+//!
+//! let mut deep_sleep = DeepSleep::new(clkcntrl_config);
+//! deep_sleep
+//!     .enable_timer_wakeup(60.s())
+//!     .enable_ext0_wakeup(&mut button, WakeupLevel::Low);
+//! deep_sleep.start();
+//! ```
+//!
+//! # TODO
+//! - Wake stub support
+//! - ULP wakeup
+
+use crate::clock_control::ClockControlConfig;
+use crate::gpio::RTCInputPin;
+use crate::prelude::*;
+use crate::reset::{reset_reason, ResetReason};
+use crate::target::{rtccntl::RegisterBlock, RTCCNTL, RTCIO};
+use crate::Core;
+
+/// Minimum sleep time in slow RTC clock cycles
+const RTC_CNTL_MIN_SLP_VAL_MIN: u8 = 2;
+
+const RTC_EXT0_TRIG_EN: u16 = 1 << 0;
+const RTC_EXT1_TRIG_EN: u16 = 1 << 1;
+pub(crate) const RTC_GPIO_TRIG_EN: u16 = 1 << 2;
+pub(crate) const RTC_TIMER_TRIG_EN: u16 = 1 << 3;
+const RTC_UART0_TRIG_EN: u16 = 1 << 6;
+const RTC_UART1_TRIG_EN: u16 = 1 << 7;
+const RTC_TOUCH_TRIG_EN: u16 = 1 << 8;
+const RTC_ULP_TRIG_EN: u16 = 1 << 9;
+
+/// Mask of the 18 RTC GPIOs
+const RTC_GPIO_MASK: u32 = (1 << 18) - 1;
+
+/// Cause of the wakeup from deep sleep
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum WakeupCause {
+    /// Not a wakeup from deep sleep (e.g. power on)
+    Undefined,
+    /// EXT0 RTC GPIO
+    Ext0,
+    /// EXT1 RTC GPIOs
+    Ext1,
+    /// RTC timer
+    Timer,
+    /// Touch pad
+    Touch,
+    /// ULP coprocessor
+    Ulp,
+    /// GPIO (light sleep only)
+    Gpio,
+    /// UART (light sleep only)
+    Uart,
+}
+
+/// Level of the EXT0 wakeup pin
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum WakeupLevel {
+    Low = 0,
+    High = 1,
+}
+
+/// Condition of the EXT1 wakeup pins
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Ext1WakeupMode {
+    /// Wake up when all pins are low
+    AllLow = 0,
+    /// Wake up when any pin is high
+    AnyHigh = 1,
+}
+
+/// Power domains which stay powered during deep sleep
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct PowerDomains {
+    /// RTC peripherals (RTC IO, touch, ULP, ...)
+    pub rtc_peripherals: bool,
+    /// RTC fast memory
+    pub rtc_fast_memory: bool,
+    /// RTC slow memory
+    pub rtc_slow_memory: bool,
+}
+
+impl Default for PowerDomains {
+    fn default() -> Self {
+        PowerDomains {
+            rtc_peripherals: false,
+            rtc_fast_memory: true,
+            rtc_slow_memory: true,
+        }
+    }
+}
+
+/// Returns the cause of the wakeup from deep sleep
+pub fn wakeup_cause() -> WakeupCause {
+    let rtc_control = unsafe { &*RTCCNTL::ptr() };
+
+    if reset_reason(Core::PRO) != ResetReason::DeepSleep {
+        return WakeupCause::Undefined;
+    }
+
+    decode_wakeup_cause(rtc_control.wakeup_state.read().wakeup_cause().bits())
+}
+
+/// Decode the wakeup cause field of the RTC controller
+pub fn decode_wakeup_cause(cause: u16) -> WakeupCause {
+    if cause & RTC_EXT0_TRIG_EN != 0 {
+        WakeupCause::Ext0
+    } else if cause & RTC_EXT1_TRIG_EN != 0 {
+        WakeupCause::Ext1
+    } else if cause & RTC_TIMER_TRIG_EN != 0 {
+        WakeupCause::Timer
+    } else if cause & RTC_TOUCH_TRIG_EN != 0 {
+        WakeupCause::Touch
+    } else if cause & RTC_ULP_TRIG_EN != 0 {
+        WakeupCause::Ulp
+    } else if cause & RTC_GPIO_TRIG_EN != 0 {
+        WakeupCause::Gpio
+    } else if cause & (RTC_UART0_TRIG_EN | RTC_UART1_TRIG_EN) != 0 {
+        WakeupCause::Uart
+    } else {
+        WakeupCause::Undefined
+    }
+}
+
+/// Returns a bitmap of the RTC GPIOs which caused the EXT1 wakeup
+pub fn ext1_wakeup_pins() -> u32 {
+    let rtc_control = unsafe { &*RTCCNTL::ptr() };
+    rtc_control
+        .ext_wakeup1_status
+        .read()
+        .ext_wakeup1_status()
+        .bits()
+}
+
+/// Set the wakeup time of the RTC timer in slow RTC clock ticks
+pub(crate) fn set_wakeup_time(rtc_control: &RegisterBlock, ticks: u64) {
+    rtc_control
+        .slp_timer0
+        .write(|w| unsafe { w.slp_val_lo().bits(ticks as u32) });
+    rtc_control
+        .slp_timer1
+        .write(|w| unsafe { w.slp_val_hi().bits((ticks >> 32) as u16) });
+}
+
+/// Deep sleep configuration
+pub struct DeepSleep {
+    clock_control: ClockControlConfig,
+    power_domains: PowerDomains,
+    wakeup_sources: u16,
+    timer_ticks: u64,
+    ext0_pin: u8,
+    ext0_level: WakeupLevel,
+    ext1_pins: u32,
+    ext1_mode: Ext1WakeupMode,
+}
+
+impl DeepSleep {
+    /// Create a deep sleep configuration without wakeup sources
+    pub fn new(clock_control: ClockControlConfig) -> Self {
+        DeepSleep {
+            clock_control,
+            power_domains: PowerDomains::default(),
+            wakeup_sources: 0,
+            timer_ticks: 0,
+            ext0_pin: 0,
+            ext0_level: WakeupLevel::Low,
+            ext1_pins: 0,
+            ext1_mode: Ext1WakeupMode::AllLow,
+        }
+    }
+
+    /// Set the power domains which stay powered
+    ///
+    /// The RTC peripherals are kept powered if needed by the EXT0 or touch wakeup.
+    pub fn power_domains(&mut self, power_domains: PowerDomains) -> &mut Self {
+        self.power_domains = power_domains;
+        self
+    }
+
+    /// Wake up after the duration
+    pub fn enable_timer_wakeup<T: Into<MicroSecondsU64>>(&mut self, duration: T) -> &mut Self {
+        let ticks: TicksU64 = duration.into() * self.clock_control.slow_rtc_frequency();
+        self.timer_ticks = ticks.0;
+        self.wakeup_sources |= RTC_TIMER_TRIG_EN;
+        self
+    }
+
+    /// Wake up when the RTC input pin is at the level
+    ///
+    /// The pin needs to be configured as RTC input, e.g. via `into_pull_up_rtc_input`.
+    pub fn enable_ext0_wakeup<PIN: RTCInputPin>(
+        &mut self,
+        pin: &mut PIN,
+        level: WakeupLevel,
+    ) -> &mut Self {
+        pin.rtc_enable_input(true)
+            .rtc_enable_input_in_sleep_mode(true)
+            .rtc_sleep_mode(true);
+
+        self.ext0_pin = pin.rtc_pin_number();
+        self.ext0_level = level;
+        self.wakeup_sources |= RTC_EXT0_TRIG_EN;
+        self
+    }
+
+    /// Add the RTC input pin to the EXT1 wakeup pins
+    ///
+    /// The pin needs to be configured as RTC input. Pull-ups and pull-downs are only active
+    /// when the RTC peripherals stay powered.
+    pub fn add_ext1_wakeup_pin<PIN: RTCInputPin>(&mut self, pin: &mut PIN) -> &mut Self {
+        pin.rtc_enable_input(true);
+
+        self.ext1_pins |= 1 << pin.rtc_pin_number();
+        self.wakeup_sources |= RTC_EXT1_TRIG_EN;
+        self
+    }
+
+    /// Set the condition of the EXT1 wakeup pins
+    pub fn set_ext1_wakeup_mode(&mut self, mode: Ext1WakeupMode) -> &mut Self {
+        self.ext1_mode = mode;
+        self
+    }
+
+    /// Wake up when a touch pad is touched
+    ///
+    /// The touch pads need to be configured via [touch::Touch](crate::touch::Touch).
+    pub fn enable_touch_wakeup(&mut self) -> &mut Self {
+        self.wakeup_sources |= RTC_TOUCH_TRIG_EN;
+        self
+    }
+
+    /// Disable all wakeup sources
+    pub fn disable_wakeup_sources(&mut self) -> &mut Self {
+        self.wakeup_sources = 0;
+        self.ext1_pins = 0;
+        self
+    }
+
+    /// Enter deep sleep
+    pub fn start(&mut self) -> ! {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+        let rtcio = unsafe { &*RTCIO::ptr() };
+
+        xtensa_lx6::interrupt::disable();
+
+        let keep_rtc_peripherals = self.power_domains.rtc_peripherals
+            || self.wakeup_sources & (RTC_EXT0_TRIG_EN | RTC_TOUCH_TRIG_EN) != 0;
+
+        if self.wakeup_sources & RTC_TIMER_TRIG_EN != 0 {
+            let wakeup_time = self.clock_control.rtc_tick_count().0 + self.timer_ticks;
+            set_wakeup_time(rtc_control, wakeup_time);
+        }
+
+        if self.wakeup_sources & RTC_EXT0_TRIG_EN != 0 {
+            rtcio
+                .ext_wakeup0
+                .write(|w| unsafe { w.ext_wakeup0_sel().bits(self.ext0_pin) });
+            rtc_control
+                .ext_wakeup_conf
+                .modify(|_, w| w.ext_wakeup0_lv().bit(self.ext0_level == WakeupLevel::High));
+        }
+
+        if self.wakeup_sources & RTC_EXT1_TRIG_EN != 0 {
+            rtc_control
+                .ext_wakeup1
+                .modify(|_, w| w.ext_wakeup1_status_clr().set_bit());
+            rtc_control
+                .ext_wakeup1
+                .write(|w| unsafe { w.ext_wakeup1_sel().bits(self.ext1_pins & RTC_GPIO_MASK) });
+            rtc_control.ext_wakeup_conf.modify(|_, w| {
+                w.ext_wakeup1_lv()
+                    .bit(self.ext1_mode == Ext1WakeupMode::AnyHigh)
+            });
+        }
+
+        // shortest possible sleep time limit
+        rtc_control
+            .timer5
+            .modify(|_, w| unsafe { w.min_slp_val().bits(RTC_CNTL_MIN_SLP_VAL_MIN) });
+
+        // RTC memories and peripherals
+        rtc_control.pwc.modify(|_, w| {
+            w.fastmem_folw_cpu()
+                .clear_bit()
+                .slowmem_folw_cpu()
+                .clear_bit()
+                .fastmem_pd_en()
+                .bit(!self.power_domains.rtc_fast_memory)
+                .fastmem_force_pu()
+                .bit(self.power_domains.rtc_fast_memory)
+                .fastmem_force_noiso()
+                .bit(self.power_domains.rtc_fast_memory)
+                .slowmem_pd_en()
+                .bit(!self.power_domains.rtc_slow_memory)
+                .slowmem_force_pu()
+                .bit(self.power_domains.rtc_slow_memory)
+                .slowmem_force_noiso()
+                .bit(self.power_domains.rtc_slow_memory)
+                .pd_en()
+                .bit(!keep_rtc_peripherals)
+        });
+
+        // digital domain
+        rtc_control.dig_pwc.modify(|_, w| {
+            w.dg_wrap_pd_en()
+                .set_bit()
+                .dg_wrap_force_pu()
+                .clear_bit()
+                .dg_wrap_force_pd()
+                .clear_bit()
+                .wifi_pd_en()
+                .set_bit()
+                .rom0_pd_en()
+                .set_bit()
+        });
+        rtc_control.dig_iso.modify(|_, w| {
+            w.dg_pad_force_iso()
+                .clear_bit()
+                .dg_pad_force_noiso()
+                .clear_bit()
+        });
+        rtc_control
+            .options0
+            .modify(|_, w| w.bias_force_nosleep().clear_bit());
+
+        // shut down parts of the RTC which may have been left enabled by the wireless drivers
+        rtc_control.ana_conf.modify(|_, w| {
+            w.ckgen_i2c_pu()
+                .clear_bit()
+                .pll_i2c_pu()
+                .clear_bit()
+                .rfrx_pbus_pu()
+                .clear_bit()
+                .txrf_i2c_pu()
+                .clear_bit()
+        });
+
+        // VDD_SDIO controlled by the state machine
+        rtc_control
+            .sdio_conf
+            .modify(|_, w| w.sdio_force().clear_bit().sdio_pd_en().set_bit());
+
+        rtc_control
+            .wakeup_state
+            .modify(|_, w| unsafe { w.wakeup_ena().bits(self.wakeup_sources) });
+        rtc_control.slp_reject_conf.write(|w| unsafe { w.bits(0) });
+
+        rtc_control.state0.modify(|_, w| w.sleep_en().set_bit());
+
+        loop {}
+    }
+}
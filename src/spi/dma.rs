@@ -0,0 +1,206 @@
+//! DMA transfers for the SPI master
+//!
+//! A DMA transfer takes ownership of the SPI driver and a [DmaBuffer]. Both are returned when the
+//! transfer is complete. Completion can be polled via [Transfer::is_done] or signalled via the
+//! [TransferDone][super::Event::TransferDone] interrupt of the SPI peripheral (`SPI2_INTR` or
+//! `SPI3_INTR`).
+//!
+//! # Example
+//!
+//! ```
+//! #[repr(align(4))]
+//! struct Aligned([u8; 4096]);
+//!
+//! static mut FRAME_BUFFER: Aligned = Aligned([0; 4096]);
+//!
+//! let buffer = DmaBuffer::new(unsafe { &mut FRAME_BUFFER.0 }).unwrap();
+//! let transfer = spi.write_dma(buffer);
+//!
+//! // do something else
+//!
+//! let (spi, buffer) = transfer.wait();
+//! ```
+
+use super::{private::Instance, Event, Spi};
+use crate::dma::DmaBuffer;
+use crate::gpio::{InputPin, OutputPin};
+
+/// Direction of a DMA transfer
+#[derive(PartialEq, Eq, Copy, Clone)]
+enum Direction {
+    Write,
+    Read,
+    Transfer,
+}
+
+impl<SPI: Instance, SCLK: OutputPin, MOSI: OutputPin, MISO: InputPin, CS: OutputPin>
+    Spi<SPI, SCLK, MOSI, MISO, CS>
+{
+    /// Start a full duplex DMA transfer
+    ///
+    /// The data in the buffer is transmitted and replaced by the received data.
+    pub fn transfer_dma(self, buffer: DmaBuffer) -> Transfer<SPI, SCLK, MOSI, MISO, CS> {
+        self.start_dma(buffer, Direction::Transfer)
+    }
+
+    /// Start a DMA transfer only transmitting the data in the buffer
+    pub fn write_dma(self, buffer: DmaBuffer) -> Transfer<SPI, SCLK, MOSI, MISO, CS> {
+        self.start_dma(buffer, Direction::Write)
+    }
+
+    /// Start a DMA transfer only receiving data into the buffer
+    pub fn read_dma(self, buffer: DmaBuffer) -> Transfer<SPI, SCLK, MOSI, MISO, CS> {
+        self.start_dma(buffer, Direction::Read)
+    }
+
+    fn start_dma(
+        mut self,
+        buffer: DmaBuffer,
+        direction: Direction,
+    ) -> Transfer<SPI, SCLK, MOSI, MISO, CS> {
+        if !buffer.is_empty() {
+            let write = direction != Direction::Read;
+            let read = direction != Direction::Write;
+            let bits = buffer.len() as u32 * 8 - 1;
+
+            self.reset_dma();
+
+            self.spi.dma_conf.modify(|_, w| {
+                w.out_data_burst_en()
+                    .set_bit()
+                    .outdscr_burst_en()
+                    .set_bit()
+                    .indscr_burst_en()
+                    .set_bit()
+            });
+
+            self.spi
+                .user
+                .modify(|_, w| w.usr_mosi().bit(write).usr_miso().bit(read));
+
+            unsafe {
+                if write {
+                    self.spi
+                        .mosi_dlen
+                        .write(|w| w.usr_mosi_dbitlen().bits(bits));
+                    self.spi.dma_out_link.write(|w| {
+                        w.outlink_addr()
+                            .bits(buffer.tx_descriptors.address())
+                            .outlink_start()
+                            .set_bit()
+                    });
+                }
+                if read {
+                    self.spi
+                        .miso_dlen
+                        .write(|w| w.usr_miso_dbitlen().bits(bits));
+                    self.spi.dma_in_link.write(|w| {
+                        w.inlink_addr()
+                            .bits(buffer.rx_descriptors.address())
+                            .inlink_start()
+                            .set_bit()
+                    });
+                }
+            }
+
+            self.spi.cmd.modify(|_, w| w.usr().set_bit());
+        }
+
+        Transfer {
+            spi: Some(self),
+            buffer: Some(buffer),
+        }
+    }
+
+    /// Reset the DMA state machines, stop the links and clear the DMA interrupts
+    fn reset_dma(&mut self) {
+        self.spi.dma_conf.modify(|_, w| {
+            w.out_rst()
+                .set_bit()
+                .in_rst()
+                .set_bit()
+                .ahbm_rst()
+                .set_bit()
+                .ahbm_fifo_rst()
+                .set_bit()
+        });
+        self.spi.dma_out_link.write(|w| unsafe { w.bits(0) });
+        self.spi.dma_in_link.write(|w| unsafe { w.bits(0) });
+        self.spi.dma_conf.modify(|_, w| {
+            w.out_rst()
+                .clear_bit()
+                .in_rst()
+                .clear_bit()
+                .ahbm_rst()
+                .clear_bit()
+                .ahbm_fifo_rst()
+                .clear_bit()
+        });
+        self.spi.dma_int_clr.write(|w| unsafe { w.bits(0x1ff) });
+    }
+}
+
+/// DMA transfer in progress
+///
+/// Dropping the transfer blocks until the transfer is complete.
+pub struct Transfer<
+    SPI: Instance,
+    SCLK: OutputPin,
+    MOSI: OutputPin,
+    // default pins to allow type inference
+    MISO: InputPin = crate::gpio::Gpio12<crate::gpio::Input<crate::gpio::Floating>>,
+    CS: OutputPin = crate::gpio::Gpio15<crate::gpio::Output<crate::gpio::PushPull>>,
+> {
+    spi: Option<Spi<SPI, SCLK, MOSI, MISO, CS>>,
+    buffer: Option<DmaBuffer>,
+}
+
+impl<SPI: Instance, SCLK: OutputPin, MOSI: OutputPin, MISO: InputPin, CS: OutputPin>
+    Transfer<SPI, SCLK, MOSI, MISO, CS>
+{
+    /// Returns true if the transfer is complete
+    pub fn is_done(&self) -> bool {
+        self.spi.as_ref().map_or(true, |spi| !spi.is_busy())
+    }
+
+    /// Returns true if the interrupt for the event is set
+    pub fn is_interrupt_set(&self, event: Event) -> bool {
+        self.spi
+            .as_ref()
+            .map_or(false, |spi| spi.is_interrupt_set(event))
+    }
+
+    /// Clear the interrupt for the event
+    pub fn clear_interrupt(&mut self, event: Event) {
+        if let Some(spi) = self.spi.as_mut() {
+            spi.clear_interrupt(event);
+        }
+    }
+
+    /// Wait for the transfer to complete and return the SPI driver and the buffer
+    pub fn wait(mut self) -> (Spi<SPI, SCLK, MOSI, MISO, CS>, DmaBuffer) {
+        self.finish();
+        (self.spi.take().unwrap(), self.buffer.take().unwrap())
+    }
+
+    fn finish(&mut self) {
+        if let Some(spi) = self.spi.as_mut() {
+            while spi.is_busy() {}
+
+            // back to transfers via the data buffer
+            spi.reset_dma();
+            spi.spi
+                .user
+                .modify(|_, w| w.usr_mosi().set_bit().usr_miso().set_bit());
+        }
+    }
+}
+
+impl<SPI: Instance, SCLK: OutputPin, MOSI: OutputPin, MISO: InputPin, CS: OutputPin> Drop
+    for Transfer<SPI, SCLK, MOSI, MISO, CS>
+{
+    fn drop(&mut self) {
+        // the descriptors are freed together with the buffer, so the DMA engine must be done
+        self.finish();
+    }
+}
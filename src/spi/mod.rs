@@ -0,0 +1,681 @@
+//! SPI peripheral control
+//!
+//! Controls the 2 general purpose SPI peripherals (SPI2/HSPI, SPI3/VSPI) in master ([Spi]) or
+//! slave ([SpiSlave][slave::SpiSlave]) mode.
+//!
+//! The SPI peripheral is clocked by the APB clock, so an APB lock is held while the driver
+//! exists.
+//!
+//! When the default pins of the peripheral are used (HSPI: SCLK 14, MOSI 13, MISO 12, CS 15;
+//! VSPI: SCLK 18, MOSI 23, MISO 19, CS 5) the signals are routed directly via the IO_MUX
+//! instead of via the GPIO matrix. This allows frequencies above 40MHz.
+//!
+//! # Example
+//!
+//! Creation of the SPI peripheral and a full duplex transfer.
+//! ```
+//! let mut spi: Spi<_, _, _, _, _> = Spi::new(
+//!     dp.SPI2,
+//!     esp32_hal::spi::Pins {
+//!         sclk: pins.gpio14,
+//!         mosi: pins.gpio13,
+//!         miso: Some(pins.gpio12),
+//!         cs: Some(pins.gpio15),
+//!     },
+//!     esp32_hal::spi::config::Config {
+//!         baudrate: 1.MHz().into(),
+//!         ..esp32_hal::spi::config::Config::default()
+//!     },
+//!     clkcntrl_config,
+//!     &mut dport,
+//! )
+//! .unwrap();
+//!
+//! let mut data = [0x9f, 0, 0, 0];
+//! spi.transfer(&mut data).unwrap();
+//! ```
+//!
+//! For large transfers DMA can be used, see the [dma] module (requires the `alloc` feature).
+//! For slave mode see the [slave] module.
+//!
+//! # TODO
+//! - Input delay compensation (MISO sampling) at high frequencies via the GPIO matrix
+//! - Half duplex transfers with command, address and dummy phases
+//! - Dual and quad modes
+
+use core::convert::Infallible;
+
+use crate::gpio::{InputPin, OutputPin};
+use crate::prelude::*;
+use crate::target;
+
+use embedded_hal::spi;
+
+#[cfg(feature = "alloc")]
+pub mod dma;
+pub mod slave;
+
+/// Size of the SPI data buffer (W0-W15) in bytes
+const SPI_BUFFER_SIZE: usize = 64;
+
+/// SPI error
+#[derive(Debug)]
+pub enum Error {
+    /// Baudrate too low
+    BaudrateTooLow,
+    /// Baudrate too high
+    BaudrateTooHigh,
+    /// More data than fits in the data buffer
+    TooMuchData,
+}
+
+/// Interrupt event
+pub enum Event {
+    /// Transfer is complete
+    TransferDone,
+}
+
+/// SPI configuration
+pub mod config {
+    use crate::units::*;
+    pub use embedded_hal::spi::{Mode, Phase, Polarity, MODE_0, MODE_1, MODE_2, MODE_3};
+
+    /// Order in which the bits are transmitted
+    #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+    pub enum BitOrder {
+        MSBFirst,
+        LSBFirst,
+    }
+
+    /// SPI configuration
+    #[derive(Copy, Clone)]
+    pub struct Config {
+        pub baudrate: Hertz,
+        pub data_mode: Mode,
+        pub bit_order: BitOrder,
+    }
+
+    impl Config {
+        pub fn baudrate(mut self, baudrate: Hertz) -> Self {
+            self.baudrate = baudrate;
+            self
+        }
+
+        pub fn data_mode(mut self, data_mode: Mode) -> Self {
+            self.data_mode = data_mode;
+            self
+        }
+
+        pub fn bit_order(mut self, bit_order: BitOrder) -> Self {
+            self.bit_order = bit_order;
+            self
+        }
+    }
+
+    impl Default for Config {
+        fn default() -> Config {
+            Config {
+                baudrate: Hertz(1_000_000),
+                data_mode: MODE_0,
+                bit_order: BitOrder::MSBFirst,
+            }
+        }
+    }
+
+    /// SPI slave configuration
+    ///
+    /// The baudrate is determined by the master.
+    #[derive(Copy, Clone)]
+    pub struct SlaveConfig {
+        pub data_mode: Mode,
+        pub bit_order: BitOrder,
+    }
+
+    impl SlaveConfig {
+        pub fn data_mode(mut self, data_mode: Mode) -> Self {
+            self.data_mode = data_mode;
+            self
+        }
+
+        pub fn bit_order(mut self, bit_order: BitOrder) -> Self {
+            self.bit_order = bit_order;
+            self
+        }
+    }
+
+    impl Default for SlaveConfig {
+        fn default() -> SlaveConfig {
+            SlaveConfig {
+                data_mode: MODE_0,
+                bit_order: BitOrder::MSBFirst,
+            }
+        }
+    }
+}
+
+/// Pins used by the SPI interface
+///
+/// Note that any pins may be used, but only the default pins allow the highest frequencies.
+/// When no chip select is given, chip select needs to be controlled by the application.
+pub struct Pins<
+    SCLK: OutputPin,
+    MOSI: OutputPin,
+    // default pins to allow type inference
+    MISO: InputPin = crate::gpio::Gpio12<crate::gpio::Input<crate::gpio::Floating>>,
+    CS: OutputPin = crate::gpio::Gpio15<crate::gpio::Output<crate::gpio::PushPull>>,
+> {
+    pub sclk: SCLK,
+    pub mosi: MOSI,
+    pub miso: Option<MISO>,
+    pub cs: Option<CS>,
+}
+
+use private::Instance;
+
+/// SPI abstraction
+pub struct Spi<
+    SPI: Instance,
+    SCLK: OutputPin,
+    MOSI: OutputPin,
+    // default pins to allow type inference
+    MISO: InputPin = crate::gpio::Gpio12<crate::gpio::Input<crate::gpio::Floating>>,
+    CS: OutputPin = crate::gpio::Gpio15<crate::gpio::Output<crate::gpio::PushPull>>,
+> {
+    spi: SPI,
+    pins: Pins<SCLK, MOSI, MISO, CS>,
+    clock_control: crate::clock_control::ClockControlConfig,
+    apb_lock: Option<crate::clock_control::dfs::LockAPB>,
+}
+
+impl<SPI: Instance, SCLK: OutputPin, MOSI: OutputPin, MISO: InputPin, CS: OutputPin>
+    Spi<SPI, SCLK, MOSI, MISO, CS>
+{
+    /// Create a new SPI driver in master mode
+    pub fn new(
+        spi: SPI,
+        pins: Pins<SCLK, MOSI, MISO, CS>,
+        config: config::Config,
+        clock_control: crate::clock_control::ClockControlConfig,
+        dport: &mut target::DPORT,
+    ) -> Result<Self, Error> {
+        let mut spi = Spi {
+            spi,
+            pins,
+            clock_control,
+            apb_lock: None,
+        };
+
+        spi.spi.init_pins(&mut spi.pins);
+        spi.spi.reset(dport).enable(dport);
+
+        spi.spi.slave.write(|w| w.slave_mode().clear_bit());
+
+        // full duplex transfers with only a data phase, chip select active around the data
+        spi.spi.user.write(|w| {
+            w.doutdin()
+                .set_bit()
+                .usr_mosi()
+                .set_bit()
+                .usr_miso()
+                .set_bit()
+                .cs_setup()
+                .set_bit()
+                .cs_hold()
+                .set_bit()
+        });
+        spi.spi.user1.write(|w| unsafe { w.bits(0) });
+        spi.spi.user2.write(|w| unsafe { w.bits(0) });
+        spi.spi.ctrl2.write(|w| unsafe { w.bits(0) });
+
+        let no_cs = spi.pins.cs.is_none();
+        spi.spi.pin.write(|w| {
+            w.cs0_dis()
+                .bit(no_cs)
+                .cs1_dis()
+                .set_bit()
+                .cs2_dis()
+                .set_bit()
+        });
+
+        spi.change_data_mode(config.data_mode)
+            .change_bit_order(config.bit_order)
+            .change_baudrate(config.baudrate)?;
+
+        Ok(spi)
+    }
+
+    /// Change the SPI mode (clock polarity and phase)
+    pub fn change_data_mode(&mut self, data_mode: config::Mode) -> &mut Self {
+        let idle_high = data_mode.polarity == config::Polarity::IdleHigh;
+        let second_edge = data_mode.phase == config::Phase::CaptureOnSecondTransition;
+
+        self.spi.pin.modify(|_, w| w.ck_idle_edge().bit(idle_high));
+        self.spi
+            .user
+            .modify(|_, w| w.ck_out_edge().bit(idle_high != second_edge));
+
+        self
+    }
+
+    /// Change the bit order
+    pub fn change_bit_order(&mut self, bit_order: config::BitOrder) -> &mut Self {
+        let lsb_first = bit_order == config::BitOrder::LSBFirst;
+
+        self.spi.ctrl.modify(|_, w| {
+            w.wr_bit_order()
+                .bit(lsb_first)
+                .rd_bit_order()
+                .bit(lsb_first)
+        });
+
+        self
+    }
+
+    /// Change the baudrate
+    ///
+    /// The nearest baudrate which can be derived from the APB clock is used.
+    pub fn change_baudrate<T: Into<Hertz> + Copy>(
+        &mut self,
+        baudrate: T,
+    ) -> Result<&mut Self, Error> {
+        if let None = self.apb_lock {
+            self.apb_lock = Some(self.clock_control.lock_apb_frequency());
+        }
+
+        let apb_frequency = self.clock_control.apb_frequency_apb_locked();
+
+        match clock_divider(apb_frequency, baudrate.into())? {
+            None => self.spi.clock.write(|w| w.clk_equ_sysclk().set_bit()),
+            Some((pre, n)) => {
+                let h = core::cmp::max((n + 1) / 2, 1);
+                unsafe {
+                    self.spi.clock.write(|w| {
+                        w.clk_equ_sysclk()
+                            .clear_bit()
+                            .clkdiv_pre()
+                            .bits(pre as u16 - 1)
+                            .clkcnt_n()
+                            .bits(n as u8 - 1)
+                            .clkcnt_h()
+                            .bits(h as u8 - 1)
+                            .clkcnt_l()
+                            .bits(n as u8 - 1)
+                    })
+                }
+            }
+        };
+
+        Ok(self)
+    }
+
+    /// Returns the current baudrate
+    pub fn baudrate(&self) -> Hertz {
+        let apb_frequency = self.clock_control.apb_frequency_apb_locked();
+        let clock = self.spi.clock.read();
+
+        if clock.clk_equ_sysclk().bit_is_set() {
+            apb_frequency
+        } else {
+            apb_frequency
+                / ((clock.clkdiv_pre().bits() as u32 + 1) * (clock.clkcnt_n().bits() as u32 + 1))
+        }
+    }
+
+    /// Return true if a transfer is in progress
+    pub fn is_busy(&self) -> bool {
+        self.spi.cmd.read().usr().bit_is_set()
+    }
+
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: Event) {
+        self.spi.enable_interrupt(event, true);
+    }
+
+    /// Stop listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        self.spi.enable_interrupt(event, false);
+    }
+
+    /// Returns true if the interrupt for the event is set
+    pub fn is_interrupt_set(&self, event: Event) -> bool {
+        match event {
+            Event::TransferDone => self.spi.slave.read().trans_done().bit_is_set(),
+        }
+    }
+
+    /// Clear the interrupt for the event
+    pub fn clear_interrupt(&mut self, event: Event) {
+        match event {
+            Event::TransferDone => self.spi.slave.modify(|_, w| w.trans_done().clear_bit()),
+        }
+    }
+
+    /// Release the SPI and GPIO resources
+    pub fn release(self) -> (SPI, Pins<SCLK, MOSI, MISO, CS>) {
+        (self.spi, self.pins)
+    }
+
+    /// Fill the data buffer, start the transfer and wait for completion
+    ///
+    /// The received data is left in the data buffer.
+    fn transfer_chunk(&mut self, chunk: &[u8]) {
+        self.spi.write_buffer(chunk);
+
+        let bits = chunk.len() as u32 * 8 - 1;
+        unsafe {
+            self.spi
+                .mosi_dlen
+                .write(|w| w.usr_mosi_dbitlen().bits(bits));
+            self.spi
+                .miso_dlen
+                .write(|w| w.usr_miso_dbitlen().bits(bits));
+        }
+
+        self.spi.cmd.modify(|_, w| w.usr().set_bit());
+        while self.is_busy() {}
+    }
+
+    /// Transfer the chunks, keeping chip select active in between
+    fn transfer_chunks<F: FnMut(&mut Self, usize)>(&mut self, len: usize, mut f: F) {
+        let chunks = (len + SPI_BUFFER_SIZE - 1) / SPI_BUFFER_SIZE;
+
+        if chunks > 1 {
+            self.spi.pin.modify(|_, w| w.cs_keep_active().set_bit());
+        }
+        for chunk in 0..chunks {
+            f(self, chunk * SPI_BUFFER_SIZE);
+        }
+        if chunks > 1 {
+            self.spi.pin.modify(|_, w| w.cs_keep_active().clear_bit());
+        }
+    }
+}
+
+/// Calculate the clock divider (pre-divider, divider) for the requested frequency
+///
+/// Returns `None` if the source frequency can be used directly.
+fn clock_divider(source: Hertz, frequency: Hertz) -> Result<Option<(u32, u32)>, Error> {
+    if frequency > source {
+        return Err(Error::BaudrateTooHigh);
+    }
+    if frequency == source {
+        return Ok(None);
+    }
+    if frequency < source / (8192 * 64) {
+        return Err(Error::BaudrateTooLow);
+    }
+
+    let mut best = (1, 2);
+    let mut best_error = u32::MAX;
+
+    for n in 2..=64 {
+        let pre = ((source / n + frequency / 2) / frequency).max(1).min(8192);
+        let actual: Hertz = source / (pre * n);
+        let error = if actual > frequency {
+            actual.0 - frequency.0
+        } else {
+            frequency.0 - actual.0
+        };
+
+        if error <= best_error {
+            best = (pre, n);
+            best_error = error;
+        }
+    }
+
+    Ok(Some(best))
+}
+
+impl<SPI: Instance, SCLK: OutputPin, MOSI: OutputPin, MISO: InputPin, CS: OutputPin>
+    spi::FullDuplex<u8> for Spi<SPI, SCLK, MOSI, MISO, CS>
+{
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        if self.is_busy() {
+            Err(nb::Error::WouldBlock)
+        } else {
+            Ok(self.spi.w0.read().bits() as u8)
+        }
+    }
+
+    fn send(&mut self, byte: u8) -> nb::Result<(), Self::Error> {
+        if self.is_busy() {
+            Err(nb::Error::WouldBlock)
+        } else {
+            unsafe {
+                self.spi.w0.write(|w| w.bits(byte as u32));
+                self.spi.mosi_dlen.write(|w| w.usr_mosi_dbitlen().bits(7));
+                self.spi.miso_dlen.write(|w| w.usr_miso_dbitlen().bits(7));
+            }
+            self.spi.cmd.modify(|_, w| w.usr().set_bit());
+            Ok(())
+        }
+    }
+}
+
+impl<SPI: Instance, SCLK: OutputPin, MOSI: OutputPin, MISO: InputPin, CS: OutputPin>
+    embedded_hal::blocking::spi::Transfer<u8> for Spi<SPI, SCLK, MOSI, MISO, CS>
+{
+    type Error = Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        let len = words.len();
+        self.transfer_chunks(len, |spi, start| {
+            let chunk = &mut words[start..core::cmp::min(start + SPI_BUFFER_SIZE, len)];
+            spi.transfer_chunk(chunk);
+            spi.spi.read_buffer(chunk);
+        });
+
+        Ok(words)
+    }
+}
+
+impl<SPI: Instance, SCLK: OutputPin, MOSI: OutputPin, MISO: InputPin, CS: OutputPin>
+    embedded_hal::blocking::spi::Write<u8> for Spi<SPI, SCLK, MOSI, MISO, CS>
+{
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let len = words.len();
+        self.transfer_chunks(len, |spi, start| {
+            spi.transfer_chunk(&words[start..core::cmp::min(start + SPI_BUFFER_SIZE, len)]);
+        });
+
+        Ok(())
+    }
+}
+
+mod private {
+    use super::{slave::SlavePins, Event, Pins};
+    use crate::gpio::{InputPin, InputSignal, OutputPin, OutputSignal};
+    use crate::prelude::*;
+    use crate::target::{self, spi, SPI2, SPI3};
+    use core::ops::Deref;
+
+    pub trait Instance: Deref<Target = spi::RegisterBlock> {
+        fn ptr() -> *const spi::RegisterBlock;
+        /// Enable peripheral
+        fn enable(&mut self, dport: &mut target::DPORT) -> &mut Self;
+        /// Disable peripheral
+        fn disable(&mut self, dport: &mut target::DPORT) -> &mut Self;
+        /// Reset peripheral
+        fn reset(&mut self, dport: &mut target::DPORT) -> &mut Self;
+
+        /// Initialize pins
+        fn init_pins<SCLK: OutputPin, MOSI: OutputPin, MISO: InputPin, CS: OutputPin>(
+            &mut self,
+            pins: &mut Pins<SCLK, MOSI, MISO, CS>,
+        ) -> &mut Self;
+
+        /// Initialize pins for slave mode
+        fn init_slave_pins<SCLK: InputPin, MOSI: InputPin, MISO: OutputPin, CS: InputPin>(
+            &mut self,
+            pins: &mut SlavePins<SCLK, MOSI, MISO, CS>,
+        ) -> &mut Self;
+
+        /// Copy data into the data buffer (W0-W15)
+        fn write_buffer(&self, data: &[u8]) {
+            let words = self.buffer_ptr();
+            for (index, bytes) in data.chunks(4).enumerate() {
+                let mut word = 0u32;
+                for (shift, byte) in bytes.iter().enumerate() {
+                    word |= (*byte as u32) << (shift * 8);
+                }
+                unsafe { core::ptr::write_volatile(words.add(index), word) };
+            }
+        }
+
+        /// Copy data from the data buffer (W0-W15)
+        fn read_buffer(&self, data: &mut [u8]) {
+            let words = self.buffer_ptr();
+            for (index, bytes) in data.chunks_mut(4).enumerate() {
+                let word = unsafe { core::ptr::read_volatile(words.add(index)) };
+                for (shift, byte) in bytes.iter_mut().enumerate() {
+                    *byte = (word >> (shift * 8)) as u8;
+                }
+            }
+        }
+
+        /// Pointer to the data buffer (W0-W15)
+        fn buffer_ptr(&self) -> *mut u32 {
+            // the 16 buffer registers are consecutive, but of different types in the PAC
+            &self.w0 as *const _ as *mut u32
+        }
+
+        /// Enable/disable the interrupt for the event
+        fn enable_interrupt(&self, event: Event, enable: bool) {
+            // bit 4 of int_en enables the trans_done interrupt
+            let mask = match event {
+                Event::TransferDone => 1 << 4,
+            };
+
+            self.slave.modify(|r, w| unsafe {
+                w.int_en().bits(if enable {
+                    r.int_en().bits() | mask
+                } else {
+                    r.int_en().bits() & !mask
+                })
+            });
+        }
+    }
+
+    /// Guards the DPORT clock, reset and DMA channel registers shared by SPI2 and SPI3
+    static SPI_DMA_LOCK: CriticalSectionSpinLockMutex<()> = CriticalSectionSpinLockMutex::new(());
+
+    macro_rules! halSpi {
+        ($(
+            $SPIX:ident: ($spiX:ident, $dma_chan_sel:ident, $dma_channel:expr,
+                $sclk:ident, $mosi:ident, $miso:ident, $cs:ident),
+        )+) => {
+            $(
+                impl Instance for $SPIX {
+                    fn ptr() -> *const spi::RegisterBlock {
+                        $SPIX::ptr()
+                    }
+
+                    fn reset(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                        (&SPI_DMA_LOCK).lock(|_| {
+                            dport.perip_rst_en.modify(|_, w| w.$spiX().set_bit());
+                            dport.perip_rst_en.modify(|_, w| w.$spiX().clear_bit());
+                        });
+                        self
+                    }
+
+                    fn enable(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                        (&SPI_DMA_LOCK).lock(|_| {
+                            // the DMA engine is shared, each SPI peripheral uses its own channel
+                            #[cfg(feature = "alloc")]
+                            {
+                                dport.perip_clk_en.modify(|_, w| w.spi_dma().set_bit());
+                                dport.perip_rst_en.modify(|_, w| w.spi_dma().clear_bit());
+                                dport
+                                    .spi_dma_chan_sel
+                                    .modify(|_, w| unsafe { w.$dma_chan_sel().bits($dma_channel) });
+                            }
+
+                            dport.perip_clk_en.modify(|_, w| w.$spiX().set_bit());
+                            dport.perip_rst_en.modify(|_, w| w.$spiX().clear_bit());
+                        });
+                        self
+                    }
+
+                    fn disable(&mut self, dport: &mut target::DPORT) -> &mut Self {
+                        (&SPI_DMA_LOCK).lock(|_| {
+                            dport.perip_clk_en.modify(|_, w| w.$spiX().clear_bit());
+                            dport.perip_rst_en.modify(|_, w| w.$spiX().set_bit());
+
+                            #[cfg(feature = "alloc")]
+                            {
+                                if dport.perip_clk_en.read().spi2().bit_is_clear()
+                                    && dport.perip_clk_en.read().spi3().bit_is_clear()
+                                {
+                                    dport.perip_clk_en.modify(|_, w| w.spi_dma().clear_bit());
+                                }
+                            }
+                        });
+                        self
+                    }
+
+                    fn init_pins<SCLK: OutputPin, MOSI: OutputPin, MISO: InputPin, CS: OutputPin>(
+                        &mut self, pins: &mut Pins<SCLK, MOSI, MISO, CS>
+                    ) -> &mut Self {
+                        // the io mux is automatically used when these are the default pins
+                        pins
+                            .sclk
+                            .set_to_push_pull_output()
+                            .connect_peripheral_to_output(OutputSignal::$sclk);
+
+                        pins
+                            .mosi
+                            .set_to_push_pull_output()
+                            .connect_peripheral_to_output(OutputSignal::$mosi);
+
+                        if let Some(miso) = pins.miso.as_mut() {
+                            miso
+                            .set_to_input()
+                            .connect_input_to_peripheral(InputSignal::$miso);
+                        }
+
+                        if let Some(cs) = pins.cs.as_mut() {
+                            cs
+                            .set_to_push_pull_output()
+                            .connect_peripheral_to_output(OutputSignal::$cs);
+                        }
+                        self
+                    }
+
+                    fn init_slave_pins<SCLK: InputPin, MOSI: InputPin, MISO: OutputPin, CS: InputPin>(
+                        &mut self, pins: &mut SlavePins<SCLK, MOSI, MISO, CS>
+                    ) -> &mut Self {
+                        pins
+                            .sclk
+                            .set_to_input()
+                            .connect_input_to_peripheral(InputSignal::$sclk);
+
+                        pins
+                            .mosi
+                            .set_to_input()
+                            .connect_input_to_peripheral(InputSignal::$mosi);
+
+                        pins
+                            .miso
+                            .set_to_push_pull_output()
+                            .connect_peripheral_to_output(OutputSignal::$miso);
+
+                        pins
+                            .cs
+                            .set_to_input()
+                            .connect_input_to_peripheral(InputSignal::$cs);
+                        self
+                    }
+                }
+            )+
+        }
+    }
+
+    halSpi! {
+        SPI2: (spi2, spi2_dma_chan_sel, 1, HSPICLK, HSPID, HSPIQ, HSPICS0),
+        SPI3: (spi3, spi3_dma_chan_sel, 2, VSPICLK, VSPID, VSPIQ, VSPICS0),
+    }
+}
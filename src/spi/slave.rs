@@ -0,0 +1,281 @@
+//! SPI slave mode
+//!
+//! A transaction is framed by the chip select of the master. Before the master starts a
+//! transaction the data to transmit is prepared with [SpiSlave::prepare]. When chip select is
+//! released the transaction is complete: the received data can be read with [SpiSlave::read]
+//! and the next transaction needs to be prepared. Up to 64 bytes can be transferred per
+//! transaction; the received data replaces the transmitted data in the data buffer.
+//!
+//! The slave is clocked by the master, but the peripheral itself is clocked by the APB clock, so
+//! an APB lock is held while the slave exists.
+//!
+//! # Example
+//!
+//! Creation of an SPI slave, which handles transactions in the interrupt handler.
+//! ```
+//! let mut slave = SpiSlave::new(
+//!     dp.SPI3,
+//!     esp32_hal::spi::slave::SlavePins {
+//!         sclk: pins.gpio18,
+//!         mosi: pins.gpio23,
+//!         miso: pins.gpio19,
+//!         cs: pins.gpio5,
+//!     },
+//!     esp32_hal::spi::config::SlaveConfig::default(),
+//!     clkcntrl_config,
+//!     &mut dport,
+//! )
+//! .unwrap();
+//!
+//! slave.prepare(&[0x01, 0x02, 0x03, 0x04]).unwrap();
+//! slave.listen(Event::TransferDone);
+//! interrupt::enable_with_priority(
+//!     Core::PRO,
+//!     Interrupt::SPI3_INTR,
+//!     interrupt::InterruptLevel(3),
+//! )
+//! .unwrap();
+//! ```
+//!
+//! In the interrupt handler:
+//! ```
+//! let mut command = [0u8; 64];
+//! let len = slave.read(&mut command);
+//! slave.clear_interrupt(Event::TransferDone);
+//! slave.prepare(&response).unwrap();
+//! ```
+//!
+//! # TODO
+//! - DMA transfers in slave mode
+//! - Slave command/status protocol (half duplex)
+
+use super::{config, private::Instance, Error, Event, SPI_BUFFER_SIZE};
+use crate::gpio::{InputPin, OutputPin};
+use crate::target;
+
+/// Pins used by the SPI interface in slave mode
+pub struct SlavePins<SCLK: InputPin, MOSI: InputPin, MISO: OutputPin, CS: InputPin> {
+    pub sclk: SCLK,
+    pub mosi: MOSI,
+    pub miso: MISO,
+    pub cs: CS,
+}
+
+/// SPI slave abstraction
+pub struct SpiSlave<SPI: Instance, SCLK: InputPin, MOSI: InputPin, MISO: OutputPin, CS: InputPin> {
+    spi: SPI,
+    pins: SlavePins<SCLK, MOSI, MISO, CS>,
+    /// A transaction has been prepared
+    prepared: bool,
+    _apb_lock: crate::clock_control::dfs::LockAPB,
+}
+
+impl<SPI: Instance, SCLK: InputPin, MOSI: InputPin, MISO: OutputPin, CS: InputPin>
+    SpiSlave<SPI, SCLK, MOSI, MISO, CS>
+{
+    /// Create a new SPI driver in slave mode
+    ///
+    /// No transaction is prepared, so the master is ignored until [SpiSlave::prepare] is called.
+    pub fn new(
+        spi: SPI,
+        pins: SlavePins<SCLK, MOSI, MISO, CS>,
+        config: config::SlaveConfig,
+        clock_control: crate::clock_control::ClockControlConfig,
+        dport: &mut target::DPORT,
+    ) -> Result<Self, Error> {
+        let mut slave = SpiSlave {
+            spi,
+            pins,
+            prepared: false,
+            _apb_lock: clock_control.lock_apb_frequency(),
+        };
+
+        slave.spi.init_slave_pins(&mut slave.pins);
+        slave.spi.reset(dport).enable(dport);
+
+        slave.spi.clock.write(|w| unsafe { w.bits(0) });
+        slave.spi.ctrl.write(|w| unsafe { w.bits(0) });
+
+        // full duplex transfers via the data buffer (W0-W15)
+        slave.spi.user.write(|w| {
+            w.doutdin()
+                .set_bit()
+                .usr_mosi()
+                .set_bit()
+                .usr_miso()
+                .set_bit()
+        });
+        slave.spi.user1.write(|w| unsafe { w.bits(0) });
+        slave.spi.user2.write(|w| unsafe { w.bits(0) });
+        slave.spi.slave1.write(|w| unsafe { w.bits(0) });
+
+        slave
+            .spi
+            .slave
+            .write(|w| w.slave_mode().set_bit().slv_wr_rd_buf_en().set_bit());
+        slave.sync_reset();
+
+        slave
+            .change_data_mode(config.data_mode)
+            .change_bit_order(config.bit_order);
+
+        Ok(slave)
+    }
+
+    /// Change the SPI mode (clock polarity and phase)
+    pub fn change_data_mode(&mut self, data_mode: config::Mode) -> &mut Self {
+        // settings as used by esp-idf, the delays compensate the input synchronization
+        let (ck_idle_edge, input_edge, miso_delay_mode, mosi_delay_mode, mosi_delay_num) =
+            match (data_mode.polarity, data_mode.phase) {
+                (config::Polarity::IdleLow, config::Phase::CaptureOnFirstTransition) => {
+                    (true, false, 0, 2, 2)
+                }
+                (config::Polarity::IdleLow, config::Phase::CaptureOnSecondTransition) => {
+                    (true, true, 2, 0, 0)
+                }
+                (config::Polarity::IdleHigh, config::Phase::CaptureOnFirstTransition) => {
+                    (false, true, 0, 1, 2)
+                }
+                (config::Polarity::IdleHigh, config::Phase::CaptureOnSecondTransition) => {
+                    (false, false, 1, 0, 0)
+                }
+            };
+
+        self.spi
+            .pin
+            .modify(|_, w| w.ck_idle_edge().bit(ck_idle_edge));
+        self.spi.user.modify(|_, w| w.ck_i_edge().bit(input_edge));
+        self.spi.ctrl2.modify(|_, w| unsafe {
+            w.miso_delay_mode()
+                .bits(miso_delay_mode)
+                .miso_delay_num()
+                .bits(0)
+                .mosi_delay_mode()
+                .bits(mosi_delay_mode)
+                .mosi_delay_num()
+                .bits(mosi_delay_num)
+        });
+
+        self
+    }
+
+    /// Change the bit order
+    pub fn change_bit_order(&mut self, bit_order: config::BitOrder) -> &mut Self {
+        let lsb_first = bit_order == config::BitOrder::LSBFirst;
+
+        self.spi.ctrl.modify(|_, w| {
+            w.wr_bit_order()
+                .bit(lsb_first)
+                .rd_bit_order()
+                .bit(lsb_first)
+        });
+
+        self
+    }
+
+    /// Prepare the next transaction
+    ///
+    /// The data is transmitted during the next transaction, followed by zeros when the master
+    /// clocks more data. Up to 64 bytes are received independent of the length of the data.
+    pub fn prepare(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.len() > SPI_BUFFER_SIZE {
+            return Err(Error::TooMuchData);
+        }
+
+        self.spi.write_buffer(&[0; SPI_BUFFER_SIZE]);
+        self.spi.write_buffer(data);
+
+        let bits = SPI_BUFFER_SIZE as u32 * 8 - 1;
+        unsafe {
+            self.spi
+                .slv_wrbuf_dlen
+                .write(|w| w.slv_wrbuf_dbitlen().bits(bits));
+            self.spi
+                .slv_rdbuf_dlen
+                .write(|w| w.slv_rdbuf_dbitlen().bits(bits));
+            self.spi
+                .mosi_dlen
+                .write(|w| w.usr_mosi_dbitlen().bits(bits));
+            self.spi
+                .miso_dlen
+                .write(|w| w.usr_miso_dbitlen().bits(bits));
+        }
+        self.prepared = true;
+
+        self.spi.slave.modify(|_, w| w.trans_done().clear_bit());
+        self.sync_reset();
+        self.spi.cmd.modify(|_, w| w.usr().set_bit());
+
+        Ok(())
+    }
+
+    /// Returns true if the prepared transaction is complete
+    pub fn is_done(&self) -> bool {
+        self.spi.slave.read().trans_done().bit_is_set()
+    }
+
+    /// Number of bytes received during the last transaction
+    pub fn received_len(&self) -> usize {
+        if !self.prepared {
+            return 0;
+        }
+
+        let bits = self.spi.slv_rd_bit.read().slv_rdata_bit().bits() as usize;
+
+        // the bit count is one short when the complete transaction length was received
+        let bits = if bits == SPI_BUFFER_SIZE * 8 - 1 {
+            bits + 1
+        } else {
+            bits
+        };
+
+        core::cmp::min((bits + 7) / 8, SPI_BUFFER_SIZE)
+    }
+
+    /// Read the data received during the last transaction
+    ///
+    /// Returns the number of bytes copied into the buffer.
+    pub fn read(&mut self, data: &mut [u8]) -> usize {
+        let len = core::cmp::min(self.received_len(), data.len());
+        self.spi.read_buffer(&mut data[..len]);
+        len
+    }
+
+    /// Starts listening for an interrupt event
+    ///
+    /// The interrupt needs to be routed via [interrupt::enable_with_priority][crate::interrupt]
+    /// (`SPI2_INTR` or `SPI3_INTR`).
+    pub fn listen(&mut self, event: Event) {
+        self.spi.enable_interrupt(event, true);
+    }
+
+    /// Stop listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        self.spi.enable_interrupt(event, false);
+    }
+
+    /// Returns true if the interrupt for the event is set
+    pub fn is_interrupt_set(&self, event: Event) -> bool {
+        match event {
+            Event::TransferDone => self.is_done(),
+        }
+    }
+
+    /// Clear the interrupt for the event
+    pub fn clear_interrupt(&mut self, event: Event) {
+        match event {
+            Event::TransferDone => self.spi.slave.modify(|_, w| w.trans_done().clear_bit()),
+        }
+    }
+
+    /// Release the SPI and GPIO resources
+    pub fn release(self) -> (SPI, SlavePins<SCLK, MOSI, MISO, CS>) {
+        (self.spi, self.pins)
+    }
+
+    /// Reset the slave state machine
+    fn sync_reset(&mut self) {
+        self.spi.slave.modify(|_, w| w.sync_reset().set_bit());
+        self.spi.slave.modify(|_, w| w.sync_reset().clear_bit());
+    }
+}
@@ -0,0 +1,500 @@
+//! Capacitive touch sensor
+//!
+//! The ESP32 has 10 capacitive touch pads. The touch finite state machine periodically measures
+//! all enabled pads by charging and discharging them between a high and a low voltage and
+//! counting the number of cycles within the measurement time. Touching a pad increases its
+//! capacitance, which lowers the count.
+//!
+//! A pad is considered touched when its count is below its threshold. This can trigger the
+//! touch interrupt (`RTC_CORE_INTR`) and wake up the chip from deep sleep.
+//!
+//! | Pad | GPIO   |
+//! |-----|--------|
+//! | T0  | GPIO4  |
+//! | T1  | GPIO0  |
+//! | T2  | GPIO2  |
+//! | T3  | GPIO15 |
+//! | T4  | GPIO13 |
+//! | T5  | GPIO12 |
+//! | T6  | GPIO14 |
+//! | T7  | GPIO27 |
+//! | T8  | GPIO33 |
+//! | T9  | GPIO32 |
+//!
+//! # Example
+//!
+//! ```
+//! let mut touch = Touch::new(analog.touch, Config::default());
+//! let pad = touch.enable_pad(pins.gpio4.into_analog(), 0);
+//!
+//! let mut filter = BaselineFilter::new(pad.read(), 10);
+//! loop {
+//!     if filter.update(pad.read()) {
+//!         writeln!(serial, "touched").unwrap();
+//!     }
+//!     sleep(10.ms());
+//! }
+//! ```
+
+use core::marker::PhantomData;
+
+use crate::gpio::*;
+use crate::target::{RTCCNTL, RTCIO, SENS};
+
+/// Touch wakeup bit in the RTC wakeup enable field
+const RTC_TOUCH_TRIG_EN: u16 = 1 << 8;
+
+/// Touch sensor configuration
+pub mod config {
+    /// High voltage of the charge cycle
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub enum HighVoltage {
+        V2p4 = 0,
+        V2p5 = 1,
+        V2p6 = 2,
+        V2p7 = 3,
+    }
+
+    /// Low voltage of the discharge cycle
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub enum LowVoltage {
+        V0p5 = 0,
+        V0p6 = 1,
+        V0p7 = 2,
+        V0p8 = 3,
+    }
+
+    /// Attenuation of the high voltage
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub enum HighVoltageAttenuation {
+        V1p5 = 0,
+        V1 = 1,
+        V0p5 = 2,
+        V0 = 3,
+    }
+
+    /// Touch sensor configuration
+    #[derive(Clone, Copy, Debug)]
+    pub struct Config {
+        /// Duration of a measurement in cycles of the RTC 8MHz clock
+        pub measurement_cycles: u16,
+        /// Time between measurements in cycles of the RTC slow clock
+        pub sleep_cycles: u16,
+        pub high_voltage: HighVoltage,
+        pub low_voltage: LowVoltage,
+        pub attenuation: HighVoltageAttenuation,
+        /// Charge and discharge speed of the pads (0-7), 0 disables the pads
+        pub slope: u8,
+    }
+
+    impl Config {
+        pub fn measurement_cycles(mut self, measurement_cycles: u16) -> Self {
+            self.measurement_cycles = measurement_cycles;
+            self
+        }
+
+        pub fn sleep_cycles(mut self, sleep_cycles: u16) -> Self {
+            self.sleep_cycles = sleep_cycles;
+            self
+        }
+
+        pub fn voltages(
+            mut self,
+            high_voltage: HighVoltage,
+            low_voltage: LowVoltage,
+            attenuation: HighVoltageAttenuation,
+        ) -> Self {
+            self.high_voltage = high_voltage;
+            self.low_voltage = low_voltage;
+            self.attenuation = attenuation;
+            self
+        }
+
+        pub fn slope(mut self, slope: u8) -> Self {
+            self.slope = slope;
+            self
+        }
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Config {
+                measurement_cycles: 0x7fff,
+                sleep_cycles: 0x1000,
+                high_voltage: HighVoltage::V2p7,
+                low_voltage: LowVoltage::V0p5,
+                attenuation: HighVoltageAttenuation::V1,
+                slope: 7,
+            }
+        }
+    }
+}
+
+use config::Config;
+
+/// Touch sensor peripheral
+pub struct TOUCH {
+    _private: PhantomData<()>,
+}
+
+impl TOUCH {
+    pub(crate) fn new() -> Self {
+        TOUCH {
+            _private: PhantomData,
+        }
+    }
+}
+
+/// Register and position of the 16-bit value of the register index
+///
+/// The threshold and output registers hold the values of two pads each, the even one in the
+/// upper half.
+fn value_register(first: *const u32, index: u8) -> (*mut u32, u32) {
+    let register = unsafe { first.add(index as usize / 2) } as *mut u32;
+    (register, if index % 2 == 0 { 16 } else { 0 })
+}
+
+/// Touch sensor controller
+pub struct Touch {
+    touch: TOUCH,
+    slope: u8,
+}
+
+impl Touch {
+    /// Configure and start the touch state machine
+    pub fn new(touch: TOUCH, config: Config) -> Self {
+        let sensors = unsafe { &*SENS::ptr() };
+        let rtcio = unsafe { &*RTCIO::ptr() };
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+
+        sensors.sar_touch_ctrl1.modify(|_, w| unsafe {
+            w.touch_meas_delay()
+                .bits(config.measurement_cycles)
+                .touch_xpd_wait()
+                .bits(0xff)
+                // touched when the count is below the threshold
+                .touch_out_sel()
+                .clear_bit()
+                // interrupt and wakeup when any pad of set 1 is touched
+                .touch_out_1en()
+                .set_bit()
+        });
+
+        (&RTCIO_LOCK).lock(|_| {
+            rtcio.touch_cfg.modify(|_, w| unsafe {
+                w.touch_drefh()
+                    .bits(config.high_voltage as u8)
+                    .touch_drefl()
+                    .bits(config.low_voltage as u8)
+                    .touch_drange()
+                    .bits(config.attenuation as u8)
+            })
+        });
+
+        // measurements triggered by the timer
+        sensors.sar_touch_ctrl2.modify(|_, w| unsafe {
+            w.touch_sleep_cycles()
+                .bits(config.sleep_cycles)
+                .touch_start_force()
+                .clear_bit()
+                .touch_start_en()
+                .clear_bit()
+                .touch_start_fsm_en()
+                .set_bit()
+        });
+        rtc_control
+            .state0
+            .modify(|_, w| w.touch_slp_timer_en().set_bit());
+
+        Touch {
+            touch,
+            slope: config.slope & 0b111,
+        }
+    }
+
+    /// Enable a touch pad with the threshold
+    pub fn enable_pad<PIN: TouchPin>(&mut self, pin: PIN, threshold: u16) -> TouchPad<PIN> {
+        let sensors = unsafe { &*SENS::ptr() };
+        let mask = 1 << PIN::INDEX;
+
+        (&RTCIO_LOCK).lock(|_| PIN::configure(self.slope));
+
+        let mut pad = TouchPad { pin };
+        pad.set_threshold(threshold);
+
+        sensors.sar_touch_enable.modify(|r, w| unsafe {
+            w.touch_pad_worken()
+                .bits(r.touch_pad_worken().bits() | mask)
+                .touch_pad_outen1()
+                .bits(r.touch_pad_outen1().bits() | mask)
+                .touch_pad_outen2()
+                .bits(r.touch_pad_outen2().bits() | mask)
+        });
+
+        pad
+    }
+
+    /// Disable a touch pad and return the pin
+    pub fn disable_pad<PIN: TouchPin>(&mut self, pad: TouchPad<PIN>) -> PIN {
+        let sensors = unsafe { &*SENS::ptr() };
+        let mask = !(1 << PIN::INDEX);
+
+        sensors.sar_touch_enable.modify(|r, w| unsafe {
+            w.touch_pad_worken()
+                .bits(r.touch_pad_worken().bits() & mask)
+                .touch_pad_outen1()
+                .bits(r.touch_pad_outen1().bits() & mask)
+                .touch_pad_outen2()
+                .bits(r.touch_pad_outen2().bits() & mask)
+        });
+
+        pad.pin
+    }
+
+    /// Returns a bitmap of the touched pads (bit 0 for T0 etc.)
+    pub fn touched_pads(&self) -> u16 {
+        let sensors = unsafe { &*SENS::ptr() };
+        let status = sensors.sar_touch_ctrl2.read().touch_meas_en().bits();
+
+        // the register bits of T8 and T9 are swapped
+        (status & 0xff) | ((status >> 1) & 0x100) | ((status << 1) & 0x200)
+    }
+
+    /// Clear the status of the touched pads
+    pub fn clear_touched_pads(&mut self) {
+        let sensors = unsafe { &*SENS::ptr() };
+
+        sensors
+            .sar_touch_ctrl2
+            .modify(|_, w| w.touch_meas_en_clr().set_bit());
+        sensors
+            .sar_touch_ctrl2
+            .modify(|_, w| w.touch_meas_en_clr().clear_bit());
+    }
+
+    /// Starts listening for the touch interrupt
+    ///
+    /// The interrupt needs to be routed via [interrupt::enable][crate::interrupt]
+    /// (`RTC_CORE_INTR`).
+    pub fn listen(&mut self) {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+        rtc_control
+            .int_ena
+            .modify(|_, w| w.touch_int_ena().set_bit());
+    }
+
+    /// Stop listening for the touch interrupt
+    pub fn unlisten(&mut self) {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+        rtc_control
+            .int_ena
+            .modify(|_, w| w.touch_int_ena().clear_bit());
+    }
+
+    /// Returns true if the touch interrupt is set
+    pub fn is_interrupt_set(&self) -> bool {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+        rtc_control.int_raw.read().touch_int_raw().bit_is_set()
+    }
+
+    /// Clear the touch interrupt and the status of the touched pads
+    pub fn clear_interrupt(&mut self) {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+        rtc_control.int_clr.write(|w| w.touch_int_clr().set_bit());
+        self.clear_touched_pads();
+    }
+
+    /// Enable or disable wakeup from deep sleep when a pad is touched
+    pub fn enable_wakeup(&mut self, enable: bool) {
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+        rtc_control.wakeup_state.modify(|r, w| unsafe {
+            let wakeup = r.wakeup_ena().bits();
+            w.wakeup_ena().bits(if enable {
+                wakeup | RTC_TOUCH_TRIG_EN
+            } else {
+                wakeup & !RTC_TOUCH_TRIG_EN
+            })
+        });
+    }
+
+    /// Stop the touch state machine and release the peripheral
+    pub fn release(mut self) -> TOUCH {
+        let sensors = unsafe { &*SENS::ptr() };
+        let rtc_control = unsafe { &*RTCCNTL::ptr() };
+
+        self.enable_wakeup(false);
+        self.unlisten();
+        rtc_control
+            .state0
+            .modify(|_, w| w.touch_slp_timer_en().clear_bit());
+        sensors
+            .sar_touch_ctrl2
+            .modify(|_, w| w.touch_start_fsm_en().clear_bit());
+
+        self.touch
+    }
+}
+
+/// Enabled touch pad
+pub struct TouchPad<PIN: TouchPin> {
+    pin: PIN,
+}
+
+impl<PIN: TouchPin> TouchPad<PIN> {
+    /// Touch pad number (0 for T0 etc.)
+    pub fn number(&self) -> u8 {
+        PIN::PAD
+    }
+
+    /// Returns the count of the last measurement
+    pub fn read(&self) -> u16 {
+        let sensors = unsafe { &*SENS::ptr() };
+        let (register, shift) = value_register(sensors.sar_touch_out1.as_ptr(), PIN::INDEX);
+
+        (unsafe { core::ptr::read_volatile(register) } >> shift) as u16
+    }
+
+    /// Returns the threshold
+    pub fn threshold(&self) -> u16 {
+        let sensors = unsafe { &*SENS::ptr() };
+        let (register, shift) = value_register(sensors.sar_touch_thres1.as_ptr(), PIN::INDEX);
+
+        (unsafe { core::ptr::read_volatile(register) } >> shift) as u16
+    }
+
+    /// Set the threshold, below which the pad is considered touched
+    pub fn set_threshold(&mut self, threshold: u16) {
+        let sensors = unsafe { &*SENS::ptr() };
+        let (register, shift) = value_register(sensors.sar_touch_thres1.as_ptr(), PIN::INDEX);
+
+        // the register is shared with another pad
+        (&RTCIO_LOCK).lock(|_| unsafe {
+            let value = core::ptr::read_volatile(register);
+            core::ptr::write_volatile(
+                register,
+                (value & !(0xffff << shift)) | ((threshold as u32) << shift),
+            );
+        });
+    }
+
+    /// Returns true if the last measurement is below the threshold
+    pub fn is_touched(&self) -> bool {
+        self.read() < self.threshold()
+    }
+}
+
+/// Baseline tracking filter for touch measurements
+///
+/// The measurements are smoothed with an IIR filter. The baseline follows the smoothed value
+/// slowly while the pad is not touched, to compensate for drift due to temperature or
+/// humidity. The pad is considered touched when the smoothed value drops by more than the
+/// given percentage below the baseline.
+#[derive(Clone, Copy, Debug)]
+pub struct BaselineFilter {
+    /// Smoothed value, scaled by 16
+    filtered: u32,
+    /// Baseline, scaled by 16
+    baseline: u32,
+    /// Drop below the baseline in percent to detect a touch
+    threshold_percent: u8,
+    touched: bool,
+}
+
+impl BaselineFilter {
+    /// Create a filter with the initial (untouched) measurement
+    pub fn new(initial: u16, threshold_percent: u8) -> Self {
+        BaselineFilter {
+            filtered: (initial as u32) << 4,
+            baseline: (initial as u32) << 4,
+            threshold_percent: threshold_percent.min(100),
+            touched: false,
+        }
+    }
+
+    /// Add a measurement and return true if the pad is touched
+    pub fn update(&mut self, value: u16) -> bool {
+        let value = (value as u32) << 4;
+
+        // smoothing with a factor of 1/4
+        self.filtered = self.filtered - self.filtered / 4 + value / 4;
+
+        let limit = self.baseline / 100 * (100 - self.threshold_percent as u32);
+        self.touched = self.filtered < limit;
+
+        // baseline tracking with a factor of 1/64, frozen while touched
+        if !self.touched {
+            self.baseline = self.baseline - self.baseline / 64 + self.filtered / 64;
+        }
+
+        self.touched
+    }
+
+    /// Returns true if the pad was touched at the last update
+    pub fn is_touched(&self) -> bool {
+        self.touched
+    }
+
+    /// Smoothed measurement
+    pub fn filtered(&self) -> u16 {
+        (self.filtered >> 4) as u16
+    }
+
+    /// Baseline of the untouched pad
+    pub fn baseline(&self) -> u16 {
+        (self.baseline >> 4) as u16
+    }
+
+    /// Threshold for [TouchPad::set_threshold] derived from the baseline
+    pub fn threshold(&self) -> u16 {
+        ((self.baseline / 100 * (100 - self.threshold_percent as u32)) >> 4) as u16
+    }
+}
+
+/// Pin usable as touch pad
+pub trait TouchPin: private::Sealed {
+    /// Touch pad number
+    const PAD: u8;
+    /// Index in the touch registers (T8 and T9 are swapped)
+    const INDEX: u8;
+
+    /// Configure the pad for touch measurements
+    fn configure(slope: u8);
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! touch_pins {
+    ($($pxi:ident: ($pad:expr, $index:expr, $touch_reg:ident),)+) => {
+        $(
+            impl private::Sealed for $pxi<Analog> {}
+
+            impl TouchPin for $pxi<Analog> {
+                const PAD: u8 = $pad;
+                const INDEX: u8 = $index;
+
+                fn configure(slope: u8) {
+                    let rtcio = unsafe { &*RTCIO::ptr() };
+
+                    rtcio.$touch_reg.modify(|_, w| unsafe {
+                        w.dac().bits(slope).tie_opt().clear_bit().xpd().set_bit()
+                    });
+                }
+            }
+        )+
+    };
+}
+
+touch_pins! {
+    Gpio4: (0, 0, touch_pad0),
+    Gpio0: (1, 1, touch_pad1),
+    Gpio2: (2, 2, touch_pad2),
+    Gpio15: (3, 3, touch_pad3),
+    Gpio13: (4, 4, touch_pad4),
+    Gpio12: (5, 5, touch_pad5),
+    Gpio14: (6, 6, touch_pad6),
+    Gpio27: (7, 7, touch_pad7),
+    Gpio33: (8, 9, touch_pad9),
+    Gpio32: (9, 8, touch_pad8),
+}